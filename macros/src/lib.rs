@@ -0,0 +1,505 @@
+// macros/src/lib.rs
+// 声明式服务客户端 (类似 Spring Cloud OpenFeign 的 @FeignClient)
+//
+// 用法：
+// ```ignore
+// #[service_client(service = state.base_config.auth_service_name)]
+// pub trait AuthApi {
+//     #[get("/token/check_token")]
+//     async fn check_token(&self, #[query] token: &str) -> Result<AuthResponse, AppError>;
+//
+//     #[put("/users/{id}")]
+//     async fn update_user(&self, #[path] id: i64, #[body] req: &UpdateUser) -> Result<User, AppError>;
+// }
+//
+// let user = AuthApiClient::new(&state).check_token(token).await?;
+// ```
+//
+// 生成内容：
+// - 原 trait (去掉参数/方法上的注解)
+// - `{Trait}Client<'a>` 结构体 (持有 `&AppState`) 及其 trait 实现，
+//   底层使用 `clients::service_client::ServiceRequest` (Nacos 发现、负载均衡、重试、熔断)
+//
+// `service` / `group` 是任意表达式，可以使用 `state` (即 `&AppState`) 读取配置。
+// `envelope = true` 时上游的 `{code, msg, data}` 统一响应结构会被自动解开 (见 `ServiceRequest::envelope`)。
+//
+// 方法注解：`#[get]` `#[post]` `#[put]` `#[patch]` `#[delete]` `#[head]`，参数为路径 (可包含 `{name}` 占位符)
+// 参数注解：
+// - `#[path]` / `#[path("name")]`：替换路径中的占位符 (Display，按路径段做 percent-encoding，`.` / `..` 返回错误)
+// - `#[query]` / `#[query("name")]`：单个查询参数
+// - `#[query_params]`：整个可序列化结构体作为查询参数
+// - `#[body]`：JSON 请求体；`#[form]`：表单请求体
+// - `#[header("X-Name")]`：请求头
+// - `#[hash_key]`：consistent_hash 负载均衡使用的 key
+// 返回值：`Result<T, AppError>` 解析 JSON (envelope 模式下解析 data)；`Result<(), AppError>` 只检查状态码；
+// `Result<RawResponse, AppError>` 返回原始响应。
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::parse::{Parse, ParseStream};
+use syn::spanned::Spanned;
+use syn::{
+    Attribute, Error, Expr, FnArg, GenericArgument, Ident, ItemTrait, LitStr, Pat, PathArguments,
+    ReturnType, Token, TraitItem, TraitItemFn, Type,
+};
+
+/// 声明式服务客户端，见模块注释
+#[proc_macro_attribute]
+pub fn service_client(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let args = syn::parse_macro_input!(attr as ClientArgs);
+    let item_trait = syn::parse_macro_input!(item as ItemTrait);
+    expand(args, item_trait)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+// --- 1. 宏参数 ---
+
+/// `#[service_client(service = <expr>, group = <expr>, envelope = true)]`
+struct ClientArgs {
+    service: Expr,
+    group: Option<Expr>,
+    // 上游返回 `{code, msg, data}` 统一响应结构时，自动解开并返回 data
+    envelope: bool,
+}
+
+impl Parse for ClientArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut service = None;
+        let mut group = None;
+        let mut envelope = false;
+
+        while !input.is_empty() {
+            let key: Ident = input.parse()?;
+            input.parse::<Token![=]>()?;
+            let value: Expr = input.parse()?;
+            match key.to_string().as_str() {
+                "service" => service = Some(value),
+                "group" => group = Some(value),
+                "envelope" => match value {
+                    Expr::Lit(syn::ExprLit { lit: syn::Lit::Bool(flag), .. }) => envelope = flag.value,
+                    _ => return Err(Error::new(value.span(), "`envelope` 只能是 true / false")),
+                },
+                _ => {
+                    return Err(Error::new(
+                        key.span(),
+                        "只支持 `service`、`group` 和 `envelope` 参数",
+                    ));
+                }
+            }
+            if !input.is_empty() {
+                input.parse::<Token![,]>()?;
+            }
+        }
+
+        let service = service.ok_or_else(|| input.error("缺少 `service = ...` 参数"))?;
+        Ok(ClientArgs { service, group, envelope })
+    }
+}
+
+// --- 2. 方法 / 参数注解 ---
+
+const HTTP_METHODS: [&str; 6] = ["get", "post", "put", "patch", "delete", "head"];
+
+/// 参数的用途
+enum ParamKind {
+    Path(String),
+    Query(String),
+    QueryParams,
+    Body,
+    Form,
+    Header(String),
+    HashKey,
+}
+
+/// 返回值的处理方式
+enum ResponseKind {
+    Json,
+    Unit,
+    Raw,
+}
+
+/// 解析一个参数上的注解 (并从参数上移除)
+fn take_param_kind(attrs: &mut Vec<Attribute>, ident: &Ident, span: proc_macro2::Span) -> syn::Result<ParamKind> {
+    let mut kind = None;
+    let mut remaining = Vec::new();
+
+    for attr in attrs.drain(..) {
+        let Some(name) = attr.path().get_ident().map(Ident::to_string) else {
+            remaining.push(attr);
+            continue;
+        };
+        // `#[path]` 或 `#[path("alias")]`
+        let alias = || -> syn::Result<String> {
+            match &attr.meta {
+                syn::Meta::Path(_) => Ok(ident.to_string()),
+                _ => Ok(attr.parse_args::<LitStr>()?.value()),
+            }
+        };
+        let parsed = match name.as_str() {
+            "path" => ParamKind::Path(alias()?),
+            "query" => ParamKind::Query(alias()?),
+            "query_params" => ParamKind::QueryParams,
+            "body" => ParamKind::Body,
+            "form" => ParamKind::Form,
+            "header" => ParamKind::Header(attr.parse_args::<LitStr>()?.value()),
+            "hash_key" => ParamKind::HashKey,
+            _ => {
+                remaining.push(attr);
+                continue;
+            }
+        };
+        if kind.replace(parsed).is_some() {
+            return Err(Error::new(attr.span(), "每个参数只能有一个注解"));
+        }
+    }
+
+    *attrs = remaining;
+    kind.ok_or_else(|| {
+        Error::new(
+            span,
+            "参数缺少注解：#[path] / #[query] / #[query_params] / #[body] / #[form] / #[header(\"..\")] / #[hash_key]",
+        )
+    })
+}
+
+/// 解析方法上的 HTTP 注解 (并从方法上移除)，返回 (方法名, 路径模板)
+fn take_http_method(method: &mut TraitItemFn) -> syn::Result<(Ident, LitStr)> {
+    let mut found = None;
+    let mut remaining = Vec::new();
+
+    for attr in method.attrs.drain(..) {
+        match attr.path().get_ident() {
+            Some(ident) if HTTP_METHODS.contains(&ident.to_string().as_str()) => {
+                let path: LitStr = attr.parse_args()?;
+                if found.replace((ident.clone(), path)).is_some() {
+                    return Err(Error::new(attr.span(), "每个方法只能有一个 HTTP 注解"));
+                }
+            }
+            _ => remaining.push(attr),
+        }
+    }
+
+    method.attrs = remaining;
+    found.ok_or_else(|| {
+        Error::new(
+            method.sig.ident.span(),
+            "方法缺少 HTTP 注解：#[get(\"..\")] / #[post(\"..\")] / #[put] / #[patch] / #[delete] / #[head]",
+        )
+    })
+}
+
+/// 根据返回值 `Result<T, _>` 中的 T 决定响应的处理方式
+fn response_kind(output: &ReturnType) -> syn::Result<ResponseKind> {
+    let ReturnType::Type(_, ty) = output else {
+        return Err(Error::new(output.span(), "返回值必须是 Result<T, AppError>"));
+    };
+    let ok_type = match &**ty {
+        Type::Path(type_path) => type_path.path.segments.last().and_then(|segment| {
+            match (&segment.ident, &segment.arguments) {
+                (ident, PathArguments::AngleBracketed(args)) if ident == "Result" => {
+                    args.args.first().and_then(|arg| match arg {
+                        GenericArgument::Type(ty) => Some(ty),
+                        _ => None,
+                    })
+                }
+                _ => None,
+            }
+        }),
+        _ => None,
+    }
+    .ok_or_else(|| Error::new(ty.span(), "返回值必须是 Result<T, AppError>"))?;
+
+    Ok(match ok_type {
+        Type::Tuple(tuple) if tuple.elems.is_empty() => ResponseKind::Unit,
+        Type::Path(type_path)
+            if type_path
+                .path
+                .segments
+                .last()
+                .is_some_and(|segment| segment.ident == "RawResponse") =>
+        {
+            ResponseKind::Raw
+        }
+        _ => ResponseKind::Json,
+    })
+}
+
+/// 将 `/users/{id}` 转换为 `format!` 调用；占位符必须有对应的 `#[path]` 参数
+fn path_expr(template: &LitStr, path_params: &[(String, Ident)]) -> syn::Result<TokenStream2> {
+    let value = template.value();
+    let mut format_str = String::new();
+    let mut format_args = Vec::new();
+    let mut rest = value.as_str();
+
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..]
+            .find('}')
+            .map(|offset| start + offset)
+            .ok_or_else(|| Error::new(template.span(), "路径中的 `{` 没有闭合"))?;
+        let name = &rest[start + 1..end];
+        let (_, ident) = path_params
+            .iter()
+            .find(|(alias, _)| alias == name)
+            .ok_or_else(|| Error::new(template.span(), format!("路径占位符 `{{{}}}` 没有对应的 #[path] 参数", name)))?;
+        format_str.push_str(&escape_format(&rest[..start]));
+        format_str.push_str("{}");
+        format_args.push(ident.clone());
+        rest = &rest[end + 1..];
+    }
+    format_str.push_str(&escape_format(rest));
+
+    for (alias, ident) in path_params {
+        if !value.contains(&format!("{{{}}}", alias)) {
+            return Err(Error::new(ident.span(), format!("路径中没有占位符 `{{{}}}`", alias)));
+        }
+    }
+
+    // 每个参数都按路径段编码，避免 `/`、`?`、`#` 等改写请求路径 (`.` / `..` 返回错误)
+    Ok(quote! {
+        ::std::format!(
+            #format_str
+            #(, crate::clients::service_client::encode_path_segment(&#format_args)?)*
+        )
+    })
+}
+
+/// 辅助函数：转义路径字面量中的 `}`，使其可以安全地放进 `format!` 的格式串
+fn escape_format(literal: &str) -> String {
+    literal.replace('}', "}}")
+}
+
+// --- 3. 代码生成 ---
+
+fn expand(args: ClientArgs, mut item_trait: ItemTrait) -> syn::Result<TokenStream2> {
+    let trait_ident = item_trait.ident.clone();
+    let client_ident = format_ident!("{}Client", trait_ident);
+    let vis = item_trait.vis.clone();
+    let service = &args.service;
+    let group = match &args.group {
+        Some(group) => quote! { ::std::option::Option::Some(::std::string::ToString::to_string(&(#group))) },
+        None => quote! { ::std::option::Option::None },
+    };
+
+    let mut methods = Vec::new();
+    for item in item_trait.items.iter_mut() {
+        let TraitItem::Fn(method) = item else {
+            continue;
+        };
+        if method.sig.asyncness.is_none() {
+            return Err(Error::new(method.sig.span(), "方法必须是 async fn"));
+        }
+        if !matches!(method.sig.inputs.first(), Some(FnArg::Receiver(receiver)) if receiver.reference.is_some() && receiver.mutability.is_none())
+        {
+            return Err(Error::new(method.sig.span(), "方法的第一个参数必须是 &self"));
+        }
+
+        let (http_method, template) = take_http_method(method)?;
+        let http_method = format_ident!("{}", http_method.to_string().to_uppercase());
+
+        // 解析参数
+        let mut path_params = Vec::new();
+        let mut calls = Vec::new();
+        for input in method.sig.inputs.iter_mut().skip(1) {
+            let FnArg::Typed(pat_type) = input else {
+                continue;
+            };
+            let Pat::Ident(pat_ident) = &*pat_type.pat else {
+                return Err(Error::new(pat_type.pat.span(), "参数必须是简单的标识符"));
+            };
+            let ident = pat_ident.ident.clone();
+            let span = pat_type.span();
+            match take_param_kind(&mut pat_type.attrs, &ident, span)? {
+                ParamKind::Path(alias) => path_params.push((alias, ident)),
+                ParamKind::Query(name) => calls.push(quote! { .query(&[(#name, &#ident)]) }),
+                ParamKind::QueryParams => calls.push(quote! { .query(&#ident) }),
+                ParamKind::Body => calls.push(quote! { .json(&#ident) }),
+                ParamKind::Form => calls.push(quote! { .form(&#ident) }),
+                ParamKind::Header(name) => calls.push(quote! { .header(#name, #ident) }),
+                ParamKind::HashKey => {
+                    calls.push(quote! { .hash_key(::std::string::ToString::to_string(&#ident)) })
+                }
+            }
+        }
+
+        let path = path_expr(&template, &path_params)?;
+        let send = match response_kind(&method.sig.output)? {
+            ResponseKind::Json if args.envelope => quote! { .envelope().send_json().await },
+            ResponseKind::Json => quote! { .send_json().await },
+            ResponseKind::Raw => quote! { .send_raw().await },
+            // envelope 模式下还需要检查 code
+            ResponseKind::Unit if args.envelope => quote! { .envelope().send_json::<()>().await },
+            ResponseKind::Unit => quote! {
+                .send_raw().await?.error_for_status(service_name, &path).map(|_| ())
+            },
+        };
+
+        let sig = &method.sig;
+        methods.push(quote! {
+            #sig {
+                let state = self.state;
+                let service_name: &str = ::core::convert::AsRef::<str>::as_ref(&(#service));
+                let path = #path;
+                crate::clients::service_client::ServiceRequest::new(
+                    state,
+                    ::reqwest::Method::#http_method,
+                    service_name,
+                    &path,
+                )
+                .group_opt(#group)
+                #(#calls)*
+                #send
+            }
+        });
+    }
+
+    let doc = format!("`{}` 的实现：通过 Nacos 发现服务并发起 HTTP 调用 (由 #[service_client] 生成)", trait_ident);
+    Ok(quote! {
+        #[allow(async_fn_in_trait)]
+        #item_trait
+
+        #[doc = #doc]
+        #[derive(Clone, Copy)]
+        #vis struct #client_ident<'a> {
+            state: &'a crate::state::AppState,
+        }
+
+        impl<'a> #client_ident<'a> {
+            pub fn new(state: &'a crate::state::AppState) -> Self {
+                Self { state }
+            }
+        }
+
+        impl #trait_ident for #client_ident<'_> {
+            #(#methods)*
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syn::parse_quote;
+
+    fn args(tokens: TokenStream2) -> ClientArgs {
+        syn::parse2(tokens).unwrap()
+    }
+
+    /// 展开并去掉空白，便于按片段断言
+    fn expand_ok(item: ItemTrait) -> String {
+        expand(args(quote! { service = "upms" }), item)
+            .unwrap()
+            .to_string()
+            .split_whitespace()
+            .collect()
+    }
+
+    fn expand_err(item: ItemTrait) -> String {
+        expand(args(quote! { service = "upms" }), item)
+            .unwrap_err()
+            .to_string()
+    }
+
+    #[test]
+    fn expands_client_with_encoded_path_params() {
+        let code = expand_ok(parse_quote! {
+            pub trait UserApi {
+                #[put("/users/{id}/roles/{role}")]
+                async fn assign(
+                    &self,
+                    #[path] id: i64,
+                    #[path("role")] role_name: &str,
+                    #[query] force: bool,
+                    #[header("X-Tenant-Id")] tenant: &str,
+                    #[body] req: &Assign,
+                ) -> Result<(), AppError>;
+            }
+        });
+
+        assert!(code.contains("pubstructUserApiClient<'a>"));
+        assert!(code.contains("::reqwest::Method::PUT"));
+        assert!(code.contains(
+            "::std::format!(\"/users/{}/roles/{}\",crate::clients::service_client::encode_path_segment(&id)?,crate::clients::service_client::encode_path_segment(&role_name)?)"
+        ));
+        assert!(code.contains(".query(&[(\"force\",&force)])"));
+        assert!(code.contains(".header(\"X-Tenant-Id\",tenant)"));
+        assert!(code.contains(".json(&req)"));
+        assert!(code.contains(".error_for_status(service_name,&path)"));
+        // 参数上的注解已被移除
+        assert!(!code.contains("#[path"));
+    }
+
+    #[test]
+    fn escapes_braces_in_literal_path() {
+        let path: LitStr = parse_quote!("/a}b/{id}");
+        let id: Ident = parse_quote!(id);
+        let code = path_expr(&path, &[("id".to_string(), id)]).unwrap().to_string();
+        assert!(code.contains("\"/a}}b/{}\""));
+    }
+
+    #[test]
+    fn rejects_placeholder_without_path_param() {
+        let err = expand_err(parse_quote! {
+            trait Api {
+                #[get("/users/{id}")]
+                async fn get(&self, #[query] id: i64) -> Result<User, AppError>;
+            }
+        });
+        assert_eq!(err, "路径占位符 `{id}` 没有对应的 #[path] 参数");
+    }
+
+    #[test]
+    fn rejects_path_param_without_placeholder() {
+        let err = expand_err(parse_quote! {
+            trait Api {
+                #[get("/users")]
+                async fn get(&self, #[path] id: i64) -> Result<User, AppError>;
+            }
+        });
+        assert_eq!(err, "路径中没有占位符 `{id}`");
+    }
+
+    #[test]
+    fn rejects_unclosed_placeholder() {
+        let err = expand_err(parse_quote! {
+            trait Api {
+                #[get("/users/{id")]
+                async fn get(&self, #[path] id: i64) -> Result<User, AppError>;
+            }
+        });
+        assert_eq!(err, "路径中的 `{` 没有闭合");
+    }
+
+    #[test]
+    fn rejects_invalid_methods() {
+        let err = expand_err(parse_quote! {
+            trait Api {
+                async fn get(&self) -> Result<User, AppError>;
+            }
+        });
+        assert!(err.starts_with("方法缺少 HTTP 注解"));
+
+        let err = expand_err(parse_quote! {
+            trait Api {
+                #[get("/users")]
+                fn get(&self) -> Result<User, AppError>;
+            }
+        });
+        assert_eq!(err, "方法必须是 async fn");
+
+        let err = expand_err(parse_quote! {
+            trait Api {
+                #[get("/users")]
+                async fn get(&self, id: i64) -> Result<User, AppError>;
+            }
+        });
+        assert!(err.starts_with("参数缺少注解"));
+    }
+
+    #[test]
+    fn requires_service_argument() {
+        assert!(syn::parse2::<ClientArgs>(quote! { group = "g" }).is_err());
+        assert!(syn::parse2::<ClientArgs>(quote! { service = "s", envelope = 1 }).is_err());
+    }
+}
@@ -0,0 +1,24 @@
+-- migrations/001_kms_app_access_key_rotation.sql
+-- 访问密钥改为摘要存储并支持轮换 (在部署该版本 *之前* 执行一次)
+--
+-- 1. 新增轮换相关的列 (实体 `models/kms_app_access.rs` 会读写它们，不执行时所有查询都会失败)
+-- 2. 把旧记录中的明文密钥改写为 SHA-256 摘要：服务只按摘要认证，不再兼容明文记录
+-- 3. 旧记录的展示前缀置为固定占位符 (取明文的前几个字符会泄露密钥的一部分)
+
+ALTER TABLE kms_app_access
+    ADD COLUMN key_prefix varchar(32) DEFAULT NULL COMMENT '访问密钥展示前缀',
+    ADD COLUMN prev_app_access_key varchar(255) DEFAULT NULL COMMENT '轮换前旧密钥的摘要',
+    ADD COLUMN prev_key_expire_time timestamp NULL DEFAULT NULL COMMENT '旧密钥失效时间';
+
+-- 已是 64 位小写十六进制摘要的记录不受影响 ('c'：区分大小写匹配)
+UPDATE kms_app_access
+   SET app_access_key = SHA2(app_access_key, 256),
+       key_prefix = 'legacy-****'
+ WHERE NOT REGEXP_LIKE(app_access_key, '^[0-9a-f]{64}$', 'c');
+
+-- 服务生成的密钥前缀总是 `ak_` 开头，其余前缀 (取自旧明文) 一律替换为占位符
+UPDATE kms_app_access
+   SET key_prefix = 'legacy-****'
+ WHERE key_prefix IS NOT NULL
+   AND key_prefix <> 'legacy-****'
+   AND NOT REGEXP_LIKE(key_prefix, '^ak_', 'c');
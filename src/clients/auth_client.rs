@@ -1,8 +1,11 @@
 // src/clients/auth_client.rs
 // 专门负责与 Auth 服务 (rtsp-upms-service) 通信
 
-use crate::errors::{AppError, ServiceError};
+use crate::errors::{AppError, ServiceError, UpstreamError};
 use crate::state::AppState;
+use axum::http::StatusCode;
+use axum_template_macros::service_client;
+use jsonwebtoken::jwk::JwkSet;
 use serde::Deserialize;
 use tracing::{info, warn};
 
@@ -14,6 +17,9 @@ use super::service_client;
 #[derive(Debug, Deserialize)]
 pub struct AuthResponse {
     pub user_info: UserInfo,
+    // Token 过期时间 (Unix 秒)，用于限制缓存时间
+    #[serde(default)]
+    pub exp: Option<i64>,
 }
 #[derive(Debug, Deserialize, Clone)]
 pub struct UserInfo {
@@ -27,7 +33,15 @@ pub struct Authority {
     pub authority: String,
 }
 
-// --- 2. 定义我们的 "Feign 客户端" 方法 ---
+// --- 2. 定义我们的 "Feign 客户端" ---
+// 由 #[service_client] 生成 `AuthApiClient`，服务名取自基础配置 `AUTH_SERVICE_NAME`
+
+#[service_client(service = state.base_config.auth_service_name)]
+pub trait AuthApi {
+    /// Auth 服务的 check_token 接口
+    #[get("/token/check_token")]
+    async fn check_token(&self, #[query] token: &str) -> Result<AuthResponse, AppError>;
+}
 
 /// 封装调用 Auth 服务的 check_token 接口
 pub async fn check_token(
@@ -37,31 +51,86 @@ pub async fn check_token(
     
     info!("(AuthClient) 正在调用 check_token...");
 
-    // 1. 从 AppState 中 *获取* 实际的服务名
-    let auth_service_name = &state.base_config.auth_service_name;
-
-    // 2. 定义查询参数
-    let query_params = [("token", token)];
+    let result = AuthApiClient::new(state).check_token(token).await;
 
-    // 3. --- 核心修改点 ---
-    // 调用 *简便版* 的 GET (get_service_with_query)，它会自动使用 `None` 作为 group
-    let result = service_client::get_service_with_query(
-        state,
-        auth_service_name, 
-        "/token/check_token",
-        &query_params,       
-    )
-    .await;
-
-    // --- 错误处理 (保持不变) ---
+    // --- 错误处理 ---
+    // Auth 服务对无效 Token 返回 400/401/403；其余 4xx (429 限流、404 路径错误等)、5xx 和网络错误
+    // 说明 Auth 服务或调用本身有问题，原样向上抛出 (不会被当作 Token 无效而负缓存)
     match result {
         Ok(auth_response) => Ok(auth_response), // 成功
-        Err(AppError::InternalError(msg)) => {
-            warn!("(AuthClient) Auth 服务调用失败 (可能 Token 无效): {}", msg);
+        Err(AppError::Upstream(e)) if is_invalid_token_status(e.status) => {
+            warn!("(AuthClient) Token 无效: {}", e);
             Err(ServiceError::Unauthorized.into()) // 转换为业务上的“未授权”
         }
         Err(e) => {
             Err(e)
         }
     }
-}
\ No newline at end of file
+}
+
+/// 辅助函数：check_token 的这些状态码表示 Token 无效
+fn is_invalid_token_status(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::BAD_REQUEST | StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN
+    )
+}
+
+/// 默认的 JWKS 路径 (Spring Authorization Server 的默认端点)
+const DEFAULT_JWKS_PATH: &str = "/oauth2/jwks";
+
+/// 获取 Auth 服务发布的 JWKS (JSON Web Key Set)，用于本地校验 JWT
+///
+/// # Arguments
+/// * `jwks_url` - 完整 URL，配置后直接请求，不走 Nacos 发现
+/// * `jwks_path` - 通过 Nacos 发现 Auth 服务时使用的路径，默认 `/oauth2/jwks`
+pub async fn fetch_jwks(
+    state: &AppState,
+    jwks_url: Option<&str>,
+    jwks_path: Option<&str>,
+) -> Result<JwkSet, AppError> {
+    match jwks_url {
+        Some(url) => {
+            info!("(AuthClient) 正在从 {} 获取 JWKS...", url);
+            let response = state.http_client.get(url).send().await.map_err(|e| {
+                AppError::InternalError(format!("Failed to fetch JWKS from {}: {}", url, e))
+            })?;
+            if !response.status().is_success() {
+                let status = response.status();
+                let body = response.bytes().await.unwrap_or_default();
+                return Err(AppError::Upstream(Box::new(UpstreamError::from_body(
+                    url, "", status, &body,
+                ))));
+            }
+            response.json::<JwkSet>().await.map_err(|e| {
+                AppError::InternalError(format!("Failed to parse JWKS from {}: {}", url, e))
+            })
+        }
+        None => {
+            let path = jwks_path.unwrap_or(DEFAULT_JWKS_PATH);
+            info!("(AuthClient) 正在通过 Nacos 获取 JWKS: {}", path);
+            service_client::get_service(state, &state.base_config.auth_service_name, path).await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_400_401_403_mean_invalid_token() {
+        for status in [StatusCode::BAD_REQUEST, StatusCode::UNAUTHORIZED, StatusCode::FORBIDDEN] {
+            assert!(is_invalid_token_status(status));
+        }
+        for status in [
+            StatusCode::NOT_FOUND,
+            StatusCode::REQUEST_TIMEOUT,
+            StatusCode::PAYLOAD_TOO_LARGE,
+            StatusCode::TOO_MANY_REQUESTS,
+            StatusCode::INTERNAL_SERVER_ERROR,
+        ] {
+            assert!(!is_invalid_token_status(status));
+        }
+    }
+}
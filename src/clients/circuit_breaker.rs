@@ -0,0 +1,362 @@
+// src/clients/circuit_breaker.rs
+// 下游服务熔断器 (closed / open / half-open)
+//
+// - closed：正常放行；连续失败达到 `failure_threshold` 次后进入 open
+// - open：直接拒绝 (AppError::CircuitOpen -> 503)，`open_secs` 秒后进入 half-open
+// - half-open：最多放行 `half_open_max_calls` 个探测请求；探测成功回到 closed，失败重新 open
+//
+// 失败的定义：连接失败 / 超时等传输错误，或上游返回 5xx。4xx 说明上游是活着的，不计为失败。
+// 默认按服务熔断；`per_instance: true` 时按实例熔断，负载均衡会跳过已熔断的实例。
+
+use crate::config::app_specific::CircuitBreakerConfig;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// 默认连续失败阈值
+const DEFAULT_FAILURE_THRESHOLD: u32 = 5;
+/// 默认熔断时长 (秒)
+const DEFAULT_OPEN_SECS: u64 = 30;
+/// 默认 half-open 状态下允许的探测请求数
+const DEFAULT_HALF_OPEN_MAX_CALLS: u32 = 1;
+
+/// 熔断器状态
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    #[default]
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+/// 合并默认值之后的熔断配置
+#[derive(Debug, Clone, Copy)]
+pub struct BreakerSettings {
+    pub enabled: bool,
+    pub per_instance: bool,
+    pub failure_threshold: u32,
+    pub open_duration: Duration,
+    pub half_open_max_calls: u32,
+}
+
+impl BreakerSettings {
+    /// 按字段合并：`upstreams.<name>.circuit_breaker` 优先于全局 `circuit_breaker`，都没有时使用默认值
+    pub fn resolve(
+        upstream: Option<&CircuitBreakerConfig>,
+        global: Option<&CircuitBreakerConfig>,
+    ) -> Self {
+        BreakerSettings {
+            enabled: pick(upstream, global, |c| c.enabled).unwrap_or(true),
+            per_instance: pick(upstream, global, |c| c.per_instance).unwrap_or(false),
+            failure_threshold: pick(upstream, global, |c| c.failure_threshold)
+                .unwrap_or(DEFAULT_FAILURE_THRESHOLD)
+                .max(1),
+            open_duration: Duration::from_secs(
+                pick(upstream, global, |c| c.open_secs).unwrap_or(DEFAULT_OPEN_SECS),
+            ),
+            half_open_max_calls: pick(upstream, global, |c| c.half_open_max_calls)
+                .unwrap_or(DEFAULT_HALF_OPEN_MAX_CALLS)
+                .max(1),
+        }
+    }
+
+    /// 熔断器的 key：按服务熔断时为服务名，按实例熔断时为 `服务名@ip:port`
+    pub fn key(&self, service_name: &str, address: &str) -> String {
+        if self.per_instance {
+            format!("{}@{}", service_name, address)
+        } else {
+            service_name.to_string()
+        }
+    }
+}
+
+/// 辅助函数：按字段取配置，upstream 优先
+fn pick<T>(
+    upstream: Option<&CircuitBreakerConfig>,
+    global: Option<&CircuitBreakerConfig>,
+    field: impl Fn(&CircuitBreakerConfig) -> Option<T>,
+) -> Option<T> {
+    upstream.and_then(&field).or_else(|| global.and_then(&field))
+}
+
+/// 单个熔断器的状态
+#[derive(Debug, Default)]
+struct Breaker {
+    state: CircuitState,
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+    open_duration: Duration,
+    half_open_in_flight: u32,
+}
+
+impl Breaker {
+    /// open 状态下是否已经过了熔断时长
+    fn open_expired(&self) -> bool {
+        self.opened_at
+            .is_none_or(|opened_at| opened_at.elapsed() >= self.open_duration)
+    }
+
+    fn trip(&mut self, key: &str, settings: &BreakerSettings) {
+        warn!(
+            "(CircuitBreaker) {} 熔断 (连续失败 {} 次)，{:?} 内拒绝调用",
+            key, self.consecutive_failures, settings.open_duration
+        );
+        self.state = CircuitState::Open;
+        self.opened_at = Some(Instant::now());
+        self.open_duration = settings.open_duration;
+    }
+}
+
+/// 熔断器状态快照 (GET /admin/circuit-breakers)
+#[derive(Debug, Clone, Serialize)]
+pub struct CircuitBreakerSnapshot {
+    pub key: String,
+    pub state: CircuitState,
+    pub consecutive_failures: u32,
+    // open 状态下距离进入 half-open 的剩余秒数
+    pub retry_after_secs: Option<u64>,
+}
+
+/// 所有熔断器 (存放在 AppState 中，所有请求共享)
+#[derive(Default)]
+pub struct CircuitBreakerRegistry {
+    breakers: Mutex<HashMap<String, Breaker>>,
+}
+
+/// 一次被放行的调用
+///
+/// 调用结束后必须通过 `record` 上报结果；未上报就被 drop (例如请求被取消) 时只释放探测名额，不改变状态。
+pub struct CircuitPermit<'a> {
+    registry: &'a CircuitBreakerRegistry,
+    key: String,
+    settings: BreakerSettings,
+    probe: bool,
+    recorded: bool,
+}
+
+impl CircuitBreakerRegistry {
+    /// 尝试放行一次调用；熔断中返回 None
+    pub fn try_acquire(&self, key: &str, settings: &BreakerSettings) -> Option<CircuitPermit<'_>> {
+        let mut breakers = self.breakers.lock().unwrap_or_else(|e| e.into_inner());
+        let breaker = breakers.entry(key.to_string()).or_default();
+
+        let probe = match breaker.state {
+            CircuitState::Closed => false,
+            CircuitState::Open if breaker.open_expired() => {
+                info!("(CircuitBreaker) {} 进入 half-open，放行探测请求", key);
+                breaker.state = CircuitState::HalfOpen;
+                breaker.half_open_in_flight = 1;
+                true
+            }
+            CircuitState::Open => return None,
+            CircuitState::HalfOpen if breaker.half_open_in_flight < settings.half_open_max_calls => {
+                breaker.half_open_in_flight += 1;
+                true
+            }
+            CircuitState::HalfOpen => return None,
+        };
+
+        Some(CircuitPermit {
+            registry: self,
+            key: key.to_string(),
+            settings: *settings,
+            probe,
+            recorded: false,
+        })
+    }
+
+    /// 某个服务下处于熔断中的实例地址 (仅 per_instance 模式下有值)
+    pub fn open_instances(&self, service_name: &str) -> Vec<String> {
+        let prefix = format!("{}@", service_name);
+        let breakers = self.breakers.lock().unwrap_or_else(|e| e.into_inner());
+        breakers
+            .iter()
+            .filter(|(_, breaker)| breaker.state == CircuitState::Open && !breaker.open_expired())
+            .filter_map(|(key, _)| key.strip_prefix(&prefix).map(str::to_string))
+            .collect()
+    }
+
+    /// 所有熔断器的状态快照 (按 key 排序)
+    pub fn snapshot(&self) -> Vec<CircuitBreakerSnapshot> {
+        let breakers = self.breakers.lock().unwrap_or_else(|e| e.into_inner());
+        let mut snapshots: Vec<CircuitBreakerSnapshot> = breakers
+            .iter()
+            .map(|(key, breaker)| CircuitBreakerSnapshot {
+                key: key.clone(),
+                state: breaker.state,
+                consecutive_failures: breaker.consecutive_failures,
+                retry_after_secs: match (breaker.state, breaker.opened_at) {
+                    (CircuitState::Open, Some(opened_at)) => Some(
+                        breaker
+                            .open_duration
+                            .saturating_sub(opened_at.elapsed())
+                            .as_secs(),
+                    ),
+                    _ => None,
+                },
+            })
+            .collect();
+        snapshots.sort_by(|a, b| a.key.cmp(&b.key));
+        snapshots
+    }
+
+    /// 上报一次调用结果
+    fn on_result(&self, permit: &CircuitPermit<'_>, success: bool) {
+        let mut breakers = self.breakers.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(breaker) = breakers.get_mut(&permit.key) else {
+            return;
+        };
+        if permit.probe {
+            breaker.half_open_in_flight = breaker.half_open_in_flight.saturating_sub(1);
+        }
+
+        match (breaker.state, success) {
+            (CircuitState::HalfOpen, true) if permit.probe => {
+                info!("(CircuitBreaker) {} 探测成功，恢复 closed", permit.key);
+                breaker.state = CircuitState::Closed;
+                breaker.consecutive_failures = 0;
+                breaker.opened_at = None;
+            }
+            (CircuitState::HalfOpen, false) if permit.probe => {
+                breaker.consecutive_failures += 1;
+                breaker.trip(&permit.key, &permit.settings);
+            }
+            (CircuitState::Closed, true) => breaker.consecutive_failures = 0,
+            (CircuitState::Closed, false) => {
+                breaker.consecutive_failures += 1;
+                if breaker.consecutive_failures >= permit.settings.failure_threshold {
+                    breaker.trip(&permit.key, &permit.settings);
+                }
+            }
+            // 熔断前就已发出的请求，结果不影响当前状态
+            _ => {}
+        }
+    }
+
+    /// 释放一个未上报结果的探测名额
+    fn release_probe(&self, key: &str) {
+        let mut breakers = self.breakers.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(breaker) = breakers.get_mut(key) {
+            breaker.half_open_in_flight = breaker.half_open_in_flight.saturating_sub(1);
+        }
+    }
+}
+
+impl CircuitPermit<'_> {
+    /// 上报调用结果
+    pub fn record(mut self, success: bool) {
+        self.recorded = true;
+        self.registry.on_result(&self, success);
+    }
+}
+
+impl Drop for CircuitPermit<'_> {
+    fn drop(&mut self) {
+        if !self.recorded && self.probe {
+            self.registry.release_probe(&self.key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &str = "demo-service";
+
+    fn settings(failure_threshold: u32, open_duration: Duration) -> BreakerSettings {
+        BreakerSettings {
+            enabled: true,
+            per_instance: false,
+            failure_threshold,
+            open_duration,
+            half_open_max_calls: 1,
+        }
+    }
+
+    fn state(registry: &CircuitBreakerRegistry) -> CircuitState {
+        registry.snapshot()[0].state
+    }
+
+    /// 连续失败 n 次
+    fn fail(registry: &CircuitBreakerRegistry, settings: &BreakerSettings, n: u32) {
+        for _ in 0..n {
+            registry.try_acquire(KEY, settings).unwrap().record(false);
+        }
+    }
+
+    #[test]
+    fn closed_trips_open_after_consecutive_failures() {
+        let registry = CircuitBreakerRegistry::default();
+        let settings = settings(3, Duration::from_secs(60));
+
+        fail(&registry, &settings, 2);
+        // 成功会清零连续失败计数
+        registry.try_acquire(KEY, &settings).unwrap().record(true);
+        fail(&registry, &settings, 2);
+        assert_eq!(state(&registry), CircuitState::Closed);
+
+        fail(&registry, &settings, 1);
+        assert_eq!(state(&registry), CircuitState::Open);
+        assert!(registry.try_acquire(KEY, &settings).is_none());
+        assert!(registry.snapshot()[0].retry_after_secs.is_some());
+    }
+
+    #[test]
+    fn half_open_probe_success_closes() {
+        let registry = CircuitBreakerRegistry::default();
+        let settings = settings(1, Duration::ZERO);
+        fail(&registry, &settings, 1);
+        assert_eq!(state(&registry), CircuitState::Open);
+
+        // 熔断时长已过：放行一个探测请求，名额用完后拒绝
+        let probe = registry.try_acquire(KEY, &settings).unwrap();
+        assert_eq!(state(&registry), CircuitState::HalfOpen);
+        assert!(registry.try_acquire(KEY, &settings).is_none());
+
+        probe.record(true);
+        assert_eq!(state(&registry), CircuitState::Closed);
+        assert_eq!(registry.snapshot()[0].consecutive_failures, 0);
+    }
+
+    #[test]
+    fn half_open_probe_failure_reopens() {
+        let registry = CircuitBreakerRegistry::default();
+        let settings = settings(1, Duration::ZERO);
+        fail(&registry, &settings, 1);
+
+        registry.try_acquire(KEY, &settings).unwrap().record(false);
+        assert_eq!(state(&registry), CircuitState::Open);
+    }
+
+    #[test]
+    fn dropped_probe_releases_slot() {
+        let registry = CircuitBreakerRegistry::default();
+        let settings = settings(1, Duration::ZERO);
+        fail(&registry, &settings, 1);
+
+        drop(registry.try_acquire(KEY, &settings).unwrap());
+        assert_eq!(state(&registry), CircuitState::HalfOpen);
+        assert!(registry.try_acquire(KEY, &settings).is_some());
+    }
+
+    #[test]
+    fn open_instances_lists_per_instance_breakers() {
+        let registry = CircuitBreakerRegistry::default();
+        let settings = BreakerSettings {
+            per_instance: true,
+            ..settings(1, Duration::from_secs(60))
+        };
+        let key = settings.key(KEY, "10.0.0.1:8080");
+        registry.try_acquire(&key, &settings).unwrap().record(false);
+        registry
+            .try_acquire(&settings.key(KEY, "10.0.0.2:8080"), &settings)
+            .unwrap()
+            .record(true);
+
+        assert_eq!(registry.open_instances(KEY), vec!["10.0.0.1:8080".to_string()]);
+    }
+}
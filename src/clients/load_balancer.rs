@@ -0,0 +1,453 @@
+// src/clients/load_balancer.rs
+// 客户端负载均衡：从 Nacos 取出健康实例列表，再按 `upstreams` 配置的策略选出一个实例。
+//
+// - weighted_random (默认)：按 Nacos 实例权重随机
+// - round_robin：轮询
+// - least_in_flight：选择进行中请求最少的实例 (按权重折算)
+// - consistent_hash：按请求 key 做加权 rendezvous hashing，实例增减时只有少量 key 会迁移
+//
+// 实例可以按 cluster / metadata 过滤；metadata 中 `secure=true` 或 `scheme=https` 时使用 https。
+
+use crate::config::app_specific::{LoadBalanceStrategy, UpstreamConfig};
+use crate::errors::AppError;
+use crate::state::AppState;
+use nacos_sdk::api::naming::ServiceInstance;
+use rand::Rng;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tracing::{debug, warn};
+
+/// 负载均衡器的运行时状态 (存放在 AppState 中，所有请求共享)
+#[derive(Default)]
+pub struct LoadBalancer {
+    // 轮询计数器：group@@service -> 下一个下标
+    round_robin: Mutex<HashMap<String, usize>>,
+    // 进行中的请求数：group@@service -> (ip:port -> 计数)
+    // 每次选择时清理已下线且计数为 0 的实例，Map 大小不超过当前实例数 + 仍有请求的旧实例数
+    in_flight: Mutex<HashMap<String, HashMap<String, Arc<AtomicUsize>>>>,
+}
+
+/// 选中的实例
+///
+/// 持有期间该实例的“进行中请求数”会 +1，drop 时自动 -1，
+/// 因此调用方应一直持有它直到响应读取完毕。
+pub struct SelectedInstance {
+    // 例如 `https://10.0.0.1:8443`
+    pub base_url: String,
+    // 例如 `10.0.0.1:8443`
+    pub address: String,
+    _in_flight: InFlightGuard,
+}
+
+/// 进行中请求计数的 RAII 守卫
+struct InFlightGuard(Arc<AtomicUsize>);
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl LoadBalancer {
+    /// 为一次调用选择下游实例
+    ///
+    /// # Arguments
+    /// * `service_name` - Nacos 服务名
+    /// * `group_name` - Nacos 分组，None 为默认分组
+    /// * `hash_key` - 一致性哈希使用的请求 key；策略为 consistent_hash 但没有 key 时退化为加权随机
+    /// * `exclude` - 需要避开的实例地址 (重试时传入已失败的实例)；全部被排除时忽略该参数
+    pub async fn select(
+        &self,
+        state: &AppState,
+        service_name: &str,
+        group_name: Option<String>,
+        hash_key: Option<&str>,
+        exclude: &[String],
+    ) -> Result<SelectedInstance, AppError> {
+        let upstream = state
+            .app_config
+            .read()
+            .await
+            .upstreams
+            .get(service_name)
+            .cloned()
+            .unwrap_or_default();
+
+        let counter_key = format!(
+            "{}@@{}",
+            group_name.as_deref().unwrap_or("DEFAULT_GROUP"),
+            service_name
+        );
+
+        // 离线模式下没有 Nacos 客户端，直接失败 (不做服务发现)
+        let Some(naming_client) = &state.naming_client else {
+            warn!("(LoadBalancer) 离线模式 (APP_CONFIG_FILE) 下无法调用服务 {}", service_name);
+            return Err(AppError::InternalError(format!(
+                "离线模式下不支持服务发现，无法调用服务 '{}'",
+                service_name
+            )));
+        };
+
+        // 1. 从 Nacos 取出健康实例 (按 cluster 过滤)，再在本地按 cluster / metadata 过滤
+        let mut candidates: Vec<ServiceInstance> = naming_client
+            .select_instances(
+                service_name.to_string(),
+                group_name,
+                upstream.clusters.clone(),
+                true,
+                true,
+            )
+            .await?
+            .into_iter()
+            .filter(|instance| is_candidate(instance, &upstream))
+            .collect();
+
+        if candidates.is_empty() {
+            warn!(
+                "(LoadBalancer) 服务 {} 没有可用的实例 (clusters: {:?}, metadata: {:?})",
+                service_name, upstream.clusters, upstream.metadata
+            );
+            return Err(AppError::InternalError(format!(
+                "服务 '{}' 没有可用的实例",
+                service_name
+            )));
+        }
+
+        self.prune_in_flight(&counter_key, &candidates);
+
+        // 重试时尽量换一个实例
+        if !exclude.is_empty() {
+            let remaining: Vec<ServiceInstance> = candidates
+                .iter()
+                .filter(|instance| !exclude.contains(&format!("{}:{}", instance.ip, instance.port)))
+                .cloned()
+                .collect();
+            if !remaining.is_empty() {
+                candidates = remaining;
+            }
+        }
+
+        // 2. 按策略选择
+        let index = match (upstream.load_balance, hash_key) {
+            (LoadBalanceStrategy::WeightedRandom, _) => weighted_random(&candidates),
+            (LoadBalanceStrategy::RoundRobin, _) => self.next_round_robin(&counter_key) % candidates.len(),
+            (LoadBalanceStrategy::LeastInFlight, _) => self.least_in_flight(&counter_key, &candidates),
+            (LoadBalanceStrategy::ConsistentHash, Some(key)) => consistent_hash(&candidates, key),
+            (LoadBalanceStrategy::ConsistentHash, None) => {
+                debug!(
+                    "(LoadBalancer) 服务 {} 使用 consistent_hash 但请求没有 hash key，退化为加权随机",
+                    service_name
+                );
+                weighted_random(&candidates)
+            }
+        };
+
+        let instance = &candidates[index];
+        let address = format!("{}:{}", instance.ip, instance.port);
+        let counter = self.in_flight_counter(&counter_key, &address);
+        counter.fetch_add(1, Ordering::Relaxed);
+
+        Ok(SelectedInstance {
+            base_url: format!("{}://{}", scheme(instance), address),
+            address,
+            _in_flight: InFlightGuard(counter),
+        })
+    }
+
+    /// 取出并递增某个服务的轮询计数
+    fn next_round_robin(&self, counter_key: &str) -> usize {
+        let mut counters = self.round_robin.lock().unwrap_or_else(|e| e.into_inner());
+        let counter = counters.entry(counter_key.to_string()).or_insert(0);
+        let current = *counter;
+        *counter = counter.wrapping_add(1);
+        current
+    }
+
+    /// 某个实例的进行中请求计数器
+    fn in_flight_counter(&self, counter_key: &str, address: &str) -> Arc<AtomicUsize> {
+        let mut counters = self.in_flight.lock().unwrap_or_else(|e| e.into_inner());
+        counters
+            .entry(counter_key.to_string())
+            .or_default()
+            .entry(address.to_string())
+            .or_default()
+            .clone()
+    }
+
+    /// 清理不在当前实例列表中、且没有守卫持有的计数器 (实例下线 / IP 变化后不再无限增长)
+    fn prune_in_flight(&self, counter_key: &str, instances: &[ServiceInstance]) {
+        let mut counters = self.in_flight.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(service_counters) = counters.get_mut(counter_key) else {
+            return;
+        };
+        service_counters.retain(|address, counter| {
+            Arc::strong_count(counter) > 1
+                || instances
+                    .iter()
+                    .any(|instance| format!("{}:{}", instance.ip, instance.port) == *address)
+        });
+    }
+
+    /// 选择 (进行中请求数 / 权重) 最小的实例；并列时从轮询位置开始取第一个，避免总是打到同一个实例
+    fn least_in_flight(&self, counter_key: &str, candidates: &[ServiceInstance]) -> usize {
+        let offset = self.next_round_robin(counter_key);
+        (0..candidates.len())
+            .map(|i| (i + offset) % candidates.len())
+            .map(|i| {
+                let instance = &candidates[i];
+                let address = format!("{}:{}", instance.ip, instance.port);
+                let load = self.in_flight_counter(counter_key, &address).load(Ordering::Relaxed) as f64;
+                (i, load / instance.weight)
+            })
+            .fold(None, |best: Option<(usize, f64)>, (i, load)| match best {
+                Some((_, best_load)) if best_load <= load => best,
+                _ => Some((i, load)),
+            })
+            .map(|(i, _)| i)
+            .unwrap_or(0)
+    }
+}
+
+/// Nacos 中未指定 cluster 的实例所属的集群名
+const DEFAULT_CLUSTER: &str = "DEFAULT";
+
+/// 辅助函数：实例能否参与负载均衡 (已启用、权重大于 0，且满足 cluster / metadata 过滤)
+///
+/// cluster 已在 `select_instances` 时交给 Nacos 过滤，这里再检查一遍，不依赖 SDK 的过滤行为。
+fn is_candidate(instance: &ServiceInstance, upstream: &UpstreamConfig) -> bool {
+    instance.enabled
+        && instance.weight > 0.0
+        && matches_cluster(instance, upstream)
+        && matches_metadata(instance, upstream)
+}
+
+/// 辅助函数：实例是否属于配置的 cluster 之一 (未配置 cluster 时不过滤)
+fn matches_cluster(instance: &ServiceInstance, upstream: &UpstreamConfig) -> bool {
+    let cluster = instance.cluster_name.as_deref().unwrap_or(DEFAULT_CLUSTER);
+    upstream.clusters.is_empty() || upstream.clusters.iter().any(|c| c == cluster)
+}
+
+/// 辅助函数：实例 metadata 是否包含配置要求的全部键值
+fn matches_metadata(instance: &ServiceInstance, upstream: &UpstreamConfig) -> bool {
+    upstream
+        .metadata
+        .iter()
+        .all(|(key, value)| instance.metadata.get(key) == Some(value))
+}
+
+/// 辅助函数：根据实例 metadata 决定协议
+fn scheme(instance: &ServiceInstance) -> &'static str {
+    let secure = instance
+        .metadata
+        .get("secure")
+        .is_some_and(|v| v.eq_ignore_ascii_case("true"));
+    let https = instance
+        .metadata
+        .get("scheme")
+        .is_some_and(|v| v.eq_ignore_ascii_case("https"));
+    if secure || https { "https" } else { "http" }
+}
+
+/// 辅助函数：按 Nacos 权重随机选择
+fn weighted_random(candidates: &[ServiceInstance]) -> usize {
+    let total: f64 = candidates.iter().map(|instance| instance.weight).sum();
+    let mut point = rand::rng().random_range(0.0..total);
+    for (i, instance) in candidates.iter().enumerate() {
+        if point < instance.weight {
+            return i;
+        }
+        point -= instance.weight;
+    }
+    candidates.len() - 1
+}
+
+/// 辅助函数：加权 rendezvous hashing
+///
+/// 每个实例的得分为 `-weight / ln(h)`，h 为 (key, 实例地址) 的哈希映射到 (0, 1)，取得分最高者。
+fn consistent_hash(candidates: &[ServiceInstance], key: &str) -> usize {
+    candidates
+        .iter()
+        .enumerate()
+        .map(|(i, instance)| {
+            let digest = Sha256::digest(format!("{}#{}:{}", key, instance.ip, instance.port));
+            let mut bytes = [0u8; 8];
+            bytes.copy_from_slice(&digest[..8]);
+            // 取高 53 位映射到 (0, 1)
+            let unit = ((u64::from_be_bytes(bytes) >> 11) as f64 + 0.5) / (1u64 << 53) as f64;
+            (i, -instance.weight / unit.ln())
+        })
+        .fold((0, f64::MIN), |best, current| if current.1 > best.1 { current } else { best })
+        .0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &str = "DEFAULT_GROUP@@demo";
+
+    fn instance(ip: &str, weight: f64) -> ServiceInstance {
+        ServiceInstance {
+            ip: ip.to_string(),
+            port: 8080,
+            weight,
+            ..Default::default()
+        }
+    }
+
+    /// 模拟一个进行中的请求 (返回的守卫持有期间计数 +1)
+    fn hold(lb: &LoadBalancer, ip: &str) -> InFlightGuard {
+        let counter = lb.in_flight_counter(KEY, &format!("{}:8080", ip));
+        counter.fetch_add(1, Ordering::Relaxed);
+        InFlightGuard(counter)
+    }
+
+    #[test]
+    fn weighted_random_follows_weights() {
+        let candidates = [instance("10.0.0.1", 1.0), instance("10.0.0.2", 3.0)];
+        let mut hits = [0usize; 2];
+        for _ in 0..4000 {
+            hits[weighted_random(&candidates)] += 1;
+        }
+        // 期望约 1000 : 3000
+        assert!((700..1300).contains(&hits[0]), "hits: {:?}", hits);
+        assert_eq!(weighted_random(&candidates[..1]), 0);
+    }
+
+    #[test]
+    fn least_in_flight_picks_lowest_weighted_load() {
+        let lb = LoadBalancer::default();
+        let candidates = [instance("10.0.0.1", 1.0), instance("10.0.0.2", 1.0)];
+        let _busy = hold(&lb, "10.0.0.1");
+        for _ in 0..4 {
+            assert_eq!(lb.least_in_flight(KEY, &candidates), 1);
+        }
+
+        // 权重 3 的实例有 2 个请求 (2/3)，仍比权重 1 的实例的 1 个请求 (1/1) 轻
+        let weighted = [instance("10.0.0.1", 1.0), instance("10.0.0.3", 3.0)];
+        let _a = hold(&lb, "10.0.0.3");
+        let _b = hold(&lb, "10.0.0.3");
+        assert_eq!(lb.least_in_flight(KEY, &weighted), 1);
+    }
+
+    #[test]
+    fn least_in_flight_rotates_on_ties() {
+        let lb = LoadBalancer::default();
+        let candidates = [instance("10.0.0.1", 1.0), instance("10.0.0.2", 1.0)];
+        let picks: Vec<usize> = (0..4).map(|_| lb.least_in_flight(KEY, &candidates)).collect();
+        assert_eq!(picks, vec![0, 1, 0, 1]);
+    }
+
+    #[test]
+    fn prune_removes_idle_counters_of_gone_instances() {
+        let lb = LoadBalancer::default();
+        let busy = hold(&lb, "10.0.0.1");
+        drop(hold(&lb, "10.0.0.2"));
+        drop(hold(&lb, "10.0.0.3"));
+
+        // 10.0.0.1 仍有请求，10.0.0.3 仍在实例列表中，10.0.0.2 被清理
+        lb.prune_in_flight(KEY, &[instance("10.0.0.3", 1.0)]);
+        let addresses = |lb: &LoadBalancer| {
+            let counters = lb.in_flight.lock().unwrap();
+            let mut addresses: Vec<String> = counters[KEY].keys().cloned().collect();
+            addresses.sort();
+            addresses
+        };
+        assert_eq!(addresses(&lb), vec!["10.0.0.1:8080", "10.0.0.3:8080"]);
+
+        drop(busy);
+        lb.prune_in_flight(KEY, &[instance("10.0.0.3", 1.0)]);
+        assert_eq!(addresses(&lb), vec!["10.0.0.3:8080"]);
+    }
+
+    fn with_metadata(mut instance: ServiceInstance, pairs: &[(&str, &str)]) -> ServiceInstance {
+        instance.metadata = pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        instance
+    }
+
+    #[test]
+    fn consistent_hash_maps_key_to_stable_instance() {
+        let candidates: Vec<ServiceInstance> =
+            (1..=5).map(|i| instance(&format!("10.0.0.{}", i), 1.0)).collect();
+        for key in ["user-1", "user-2", "tenant-42"] {
+            let chosen = consistent_hash(&candidates, key);
+            for _ in 0..10 {
+                assert_eq!(consistent_hash(&candidates, key), chosen);
+            }
+            // 与实例顺序无关
+            let mut reversed = candidates.clone();
+            reversed.reverse();
+            assert_eq!(reversed[consistent_hash(&reversed, key)].ip, candidates[chosen].ip);
+
+            // 去掉另一个实例不影响该 key 的选择 (rendezvous hashing 只迁移被移除实例上的 key)
+            let other = (chosen + 1) % candidates.len();
+            let remaining: Vec<ServiceInstance> = candidates
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| *i != other)
+                .map(|(_, instance)| instance.clone())
+                .collect();
+            assert_eq!(remaining[consistent_hash(&remaining, key)].ip, candidates[chosen].ip);
+        }
+    }
+
+    #[test]
+    fn consistent_hash_distribution_follows_weights() {
+        let candidates = [instance("10.0.0.1", 1.0), instance("10.0.0.2", 3.0)];
+        let mut hits = [0usize; 2];
+        for i in 0..4000 {
+            hits[consistent_hash(&candidates, &format!("key-{}", i))] += 1;
+        }
+        // 期望约 1000 : 3000
+        assert!((700..1300).contains(&hits[0]), "hits: {:?}", hits);
+    }
+
+    #[test]
+    fn scheme_follows_instance_metadata() {
+        let base = instance("10.0.0.1", 1.0);
+        assert_eq!(scheme(&base), "http");
+        assert_eq!(scheme(&with_metadata(base.clone(), &[("secure", "TRUE")])), "https");
+        assert_eq!(scheme(&with_metadata(base.clone(), &[("scheme", "https")])), "https");
+        assert_eq!(scheme(&with_metadata(base.clone(), &[("secure", "false")])), "http");
+        assert_eq!(scheme(&with_metadata(base, &[("scheme", "http")])), "http");
+    }
+
+    #[test]
+    fn cluster_and_metadata_filters_exclude_non_matching_instances() {
+        let upstream = UpstreamConfig {
+            clusters: vec!["bj".to_string(), "DEFAULT".to_string()],
+            metadata: [("version".to_string(), "v2".to_string())].into(),
+            ..Default::default()
+        };
+        let v2 = |ip: &str, cluster: Option<&str>| {
+            let mut instance = with_metadata(instance(ip, 1.0), &[("version", "v2"), ("zone", "a")]);
+            instance.enabled = true;
+            instance.cluster_name = cluster.map(str::to_string);
+            instance
+        };
+
+        assert!(is_candidate(&v2("10.0.0.1", Some("bj")), &upstream));
+        // 未指定 cluster 的实例属于 DEFAULT
+        assert!(is_candidate(&v2("10.0.0.2", None), &upstream));
+        assert!(!is_candidate(&v2("10.0.0.3", Some("sh")), &upstream));
+
+        let mut v1 = v2("10.0.0.4", Some("bj"));
+        v1.metadata.insert("version".to_string(), "v1".to_string());
+        assert!(!is_candidate(&v1, &upstream));
+        let mut missing = v2("10.0.0.5", Some("bj"));
+        missing.metadata.clear();
+        assert!(!is_candidate(&missing, &upstream));
+
+        let mut disabled = v2("10.0.0.6", Some("bj"));
+        disabled.enabled = false;
+        assert!(!is_candidate(&disabled, &upstream));
+        let mut zero_weight = v2("10.0.0.7", Some("bj"));
+        zero_weight.weight = 0.0;
+        assert!(!is_candidate(&zero_weight, &upstream));
+
+        // 未配置过滤条件时只要求启用且权重大于 0
+        assert!(is_candidate(&v2("10.0.0.8", Some("sh")), &UpstreamConfig::default()));
+    }
+}
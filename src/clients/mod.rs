@@ -3,7 +3,17 @@
 
 // --- 修改点 ---
 // 重命名为 service_client
-mod service_client;
+// 公开：业务代码可以直接使用 `ServiceRequest` builder 调用任意 Nacos 服务
+pub mod service_client;
+
+// --- 新增：客户端负载均衡 (AppState 中持有其运行时状态) ---
+pub mod load_balancer;
+
+// --- 新增：下游服务熔断器 (AppState 中持有其状态) ---
+pub mod circuit_breaker;
+
+// --- 新增：出站调用的 Header 透传 (request id、trace context、Authorization) ---
+pub mod propagation;
 
 // -------------------------------------
 // --- 具体的业务客户端 ---
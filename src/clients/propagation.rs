@@ -0,0 +1,172 @@
+// src/clients/propagation.rs
+// 出站调用的 Header 透传：把入站请求的 request id、W3C trace context，
+// 以及 (可选) 调用方的 Bearer Token 或服务间调用 Token 带到每一次下游调用上。
+//
+// 配置：全局 `propagation`，可在 `upstreams.<服务名>.propagation` 中按字段覆盖。
+
+use crate::config::app_specific::{AppSpecificConfig, PropagationConfig};
+use crate::middleware::request_context::{
+    self, REQUEST_ID_HEADER, RequestContext, TRACEPARENT_HEADER, TRACESTATE_HEADER,
+};
+use crate::setup::telemetry;
+use crate::state::AppState;
+use reqwest::header::{AUTHORIZATION, HeaderMap, HeaderName, HeaderValue};
+use tracing::warn;
+
+/// 计算某次下游调用需要透传的 Header
+///
+/// Authorization 的取值顺序：`forward_authorization` 开启且入站请求带了 Bearer Token 时透传调用方的 Token，
+/// 否则使用配置的 `service_token` (服务间调用 Token)。
+pub async fn outbound_headers(state: &AppState, service_name: &str) -> HeaderMap {
+    let settings = Settings::resolve(&*state.app_config.read().await, service_name);
+    settings.headers(
+        request_context::current().unwrap_or_default(),
+        telemetry::current_trace_headers(),
+    )
+}
+
+/// 某个下游服务生效的透传配置 (`upstreams.<服务名>.propagation` 按字段覆盖全局 `propagation`)
+struct Settings {
+    request_id: bool,
+    trace_context: bool,
+    forward_authorization: bool,
+    service_token: Option<String>,
+}
+
+impl Settings {
+    fn resolve(config: &AppSpecificConfig, service_name: &str) -> Self {
+        let upstream = config
+            .upstreams
+            .get(service_name)
+            .and_then(|u| u.propagation.as_ref());
+        let global = config.propagation.as_ref();
+        let pick_bool = |field: fn(&PropagationConfig) -> Option<bool>| {
+            upstream.and_then(field).or_else(|| global.and_then(field))
+        };
+
+        Settings {
+            request_id: pick_bool(|c| c.request_id).unwrap_or(true),
+            trace_context: pick_bool(|c| c.trace_context).unwrap_or(true),
+            forward_authorization: pick_bool(|c| c.forward_authorization).unwrap_or(false),
+            service_token: upstream
+                .and_then(|c| c.service_token.clone())
+                .or_else(|| global.and_then(|c| c.service_token.clone())),
+        }
+    }
+
+    /// 根据入站请求的上下文组装 Header；`trace_headers` 为启用链路追踪时当前 span 的 context
+    fn headers(
+        self,
+        context: RequestContext,
+        trace_headers: Option<(String, Option<String>)>,
+    ) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+
+        if self.request_id {
+            insert(&mut headers, REQUEST_ID_HEADER, context.request_id);
+        }
+        if self.trace_context {
+            // 启用链路追踪时传递当前 (service_client) span 的 context，否则原样透传入站的 Header
+            let (traceparent, tracestate) = match trace_headers {
+                Some((traceparent, tracestate)) => (Some(traceparent), tracestate),
+                None => (context.traceparent, context.tracestate),
+            };
+            insert(&mut headers, TRACEPARENT_HEADER, traceparent);
+            insert(&mut headers, TRACESTATE_HEADER, tracestate);
+        }
+
+        let service_token = self.service_token;
+        let authorization = context
+            .authorization
+            .filter(|_| self.forward_authorization)
+            .or_else(|| service_token.map(|token| format!("Bearer {}", token)));
+        insert(&mut headers, AUTHORIZATION.as_str(), authorization);
+
+        headers
+    }
+}
+
+/// 辅助函数：值存在且合法时写入 Header
+fn insert(headers: &mut HeaderMap, name: &'static str, value: Option<String>) {
+    let Some(value) = value else {
+        return;
+    };
+    match HeaderValue::try_from(value) {
+        Ok(value) => {
+            headers.insert(HeaderName::from_static(name), value);
+        }
+        Err(e) => warn!("(Propagation) 无法透传 Header {}: {}", name, e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::app_specific::parse_nacos_config;
+
+    fn context() -> RequestContext {
+        RequestContext {
+            request_id: Some("req-1".to_string()),
+            traceparent: Some("00-inbound-01".to_string()),
+            tracestate: Some("vendor=1".to_string()),
+            authorization: Some("Bearer caller-token".to_string()),
+        }
+    }
+
+    fn headers(yaml: &str, service_name: &str, trace: Option<(String, Option<String>)>) -> HeaderMap {
+        let config = parse_nacos_config(yaml).unwrap();
+        Settings::resolve(&config, service_name).headers(context(), trace)
+    }
+
+    fn get<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+        headers.get(name).map(|v| v.to_str().unwrap())
+    }
+
+    #[test]
+    fn defaults_forward_request_id_and_trace_context_only() {
+        let headers = headers("{}", "upms", None);
+        assert_eq!(get(&headers, REQUEST_ID_HEADER), Some("req-1"));
+        assert_eq!(get(&headers, TRACEPARENT_HEADER), Some("00-inbound-01"));
+        assert_eq!(get(&headers, TRACESTATE_HEADER), Some("vendor=1"));
+        assert_eq!(get(&headers, "authorization"), None);
+    }
+
+    #[test]
+    fn trace_context_prefers_the_current_span() {
+        let headers = headers("{}", "upms", Some(("00-span-01".to_string(), None)));
+        assert_eq!(get(&headers, TRACEPARENT_HEADER), Some("00-span-01"));
+        assert_eq!(get(&headers, TRACESTATE_HEADER), None);
+    }
+
+    #[test]
+    fn caller_token_takes_precedence_over_service_token_when_forwarding() {
+        let yaml = "propagation:\n  service_token: svc\n";
+        assert_eq!(get(&headers(yaml, "upms", None), "authorization"), Some("Bearer svc"));
+
+        let yaml = "propagation:\n  forward_authorization: true\n  service_token: svc\n";
+        assert_eq!(get(&headers(yaml, "upms", None), "authorization"), Some("Bearer caller-token"));
+    }
+
+    #[test]
+    fn upstream_settings_override_global_per_field() {
+        let yaml = "\
+propagation:
+  request_id: false
+  service_token: global
+upstreams:
+  upms:
+    propagation:
+      trace_context: false
+      service_token: upms-token
+";
+        let upms = headers(yaml, "upms", None);
+        assert_eq!(get(&upms, REQUEST_ID_HEADER), None);
+        assert_eq!(get(&upms, TRACEPARENT_HEADER), None);
+        assert_eq!(get(&upms, "authorization"), Some("Bearer upms-token"));
+
+        let other = headers(yaml, "order", None);
+        assert_eq!(get(&other, REQUEST_ID_HEADER), None);
+        assert_eq!(get(&other, TRACEPARENT_HEADER), Some("00-inbound-01"));
+        assert_eq!(get(&other, "authorization"), Some("Bearer global"));
+    }
+}
@@ -1,14 +1,34 @@
 // src/clients/service_client.rs
 // (原 common_client.rs)
 // 提供了通用的、Nacos 感知的 HTTP 客户端逻辑。
+//
+// - `get_service` / `post_service` 等函数：最常用的 JSON 调用 (clients 模块内部使用)
+// - `ServiceRequest`：公开的 builder API，支持所有 HTTP 方法、自定义 Header、
+//   form / multipart / 流式请求体，以及返回原始响应 (`send_raw`)
 
-use crate::errors::{AppError};
+use crate::errors::{AppError, ServiceError, UpstreamError};
+use crate::metrics::OutboundOutcome;
 use crate::state::AppState;
+use super::circuit_breaker::BreakerSettings;
+use super::load_balancer::SelectedInstance;
+use super::propagation;
+use axum::body::Bytes;
+use futures_core::TryStream;
+use percent_encoding::{AsciiSet, CONTROLS, utf8_percent_encode};
+use rand::Rng;
+use reqwest::header::{CONTENT_TYPE, HeaderMap, HeaderName, HeaderValue};
+use reqwest::multipart::Form;
+use reqwest::{Body, Method, RequestBuilder, Response, StatusCode};
+use serde::Deserialize;
 use serde::de::DeserializeOwned; 
 use serde::Serialize; 
-use tracing::{error, info, warn};
+use std::time::{Duration, Instant};
+use tracing::{Instrument, error, field::Empty, info, warn};
 
 // --- "重载" (Overloads) - 暴露给其他 client 模块的简便函数 ---
+//
+// 这些函数把上游响应体原样解析为 `T`，不会解开 `{code, msg, data}` 统一响应结构；
+// 上游返回统一响应结构时，改用 `ServiceRequest::envelope()` 或 `#[service_client(envelope = true)]`。
 
 /// (最简 GET) 通用 GET 请求，不带查询参数，使用 Nacos 默认分组。
 #[allow(dead_code)] // 允许未使用
@@ -21,7 +41,7 @@ where
     T: DeserializeOwned + 'static,
 {
     // 自动调用“完整版”函数，传入 None (group) 和 () (空查询)
-    get_service_with_group(state, service_name, None, endpoint_path, &()).await
+    get_service_with_group(state, service_name, None, None, endpoint_path, &()).await
 }
 
 /// (带 Query 的 GET) 通用 GET 请求，带查询参数，使用 Nacos 默认分组。
@@ -37,7 +57,7 @@ where
     Q: Serialize + ?Sized,
 {
     // 自动调用“完整版”函数，并传入 None 作为 group_name
-    get_service_with_group(state, service_name, None, endpoint_path, query_params).await
+    get_service_with_group(state, service_name, None, None, endpoint_path, query_params).await
 }
 
 /// (最简 POST) 通用 POST 请求，只带 Body，使用 Nacos 默认分组。
@@ -53,7 +73,7 @@ where
     B: Serialize + ?Sized,
 {
     // 自动调用“完整版”函数，传入 None (group) 和 () (空查询)
-    post_service_with_group(state, service_name, None, endpoint_path, &(), body).await
+    post_service_with_group(state, service_name, None, None, endpoint_path, &(), body).await
 }
 
 /// (带 Query 的 POST) 通用 POST 请求，带查询参数和 Body，使用 Nacos 默认分组。
@@ -71,17 +91,20 @@ where
     B: Serialize + ?Sized,
 {
     // 自动调用“完整版”函数，并传入 None 作为 group_name
-    post_service_with_group(state, service_name, None, endpoint_path, query_params, body).await
+    post_service_with_group(state, service_name, None, None, endpoint_path, query_params, body).await
 }
 
 
 // --- 核心实现 (现在叫 "with_group") ---
 
 /// (完整版) 通用 GET 请求，可指定 Nacos Group
+///
+/// GET 是幂等的：连接失败或返回 502/503/504 时会按配置重试 (每次重试重新选择实例)。
 pub(super) async fn get_service_with_group<T, Q>(
     state: &AppState,
     service_name: &str, // e.g., "rtsp-upms-service"
     group_name: Option<String>, // <-- 允许指定 Group
+    hash_key: Option<&str>, // <-- 新增：consistent_hash 负载均衡使用的请求 key
     endpoint_path: &str,  // e.g., "/check_token"
     query_params: &Q,     // e.g., &[("token", token_str)]
 ) -> Result<T, AppError>
@@ -89,50 +112,22 @@ where
     T: DeserializeOwned + 'static,
     Q: Serialize + ?Sized, 
 {
-    // 1. 获取基础 URL (现在只通过 Nacos 发现)
-    let base_url = discover_service_url(state, service_name, group_name).await?;
-    let target_url = format!("{}{}", base_url, endpoint_path);
-
-    info!("(ServiceClient) GET: {}", target_url);
-
-    // 2. 发起 HTTP GET 请求
-    let response = state
-        .http_client
-        .get(target_url)
-        .query(query_params) 
-        .send()
+    ServiceRequest::new(state, Method::GET, service_name, endpoint_path)
+        .group_opt(group_name)
+        .hash_key_opt(hash_key)
+        .query(query_params)
+        .send_json()
         .await
-        .map_err(|e| {
-            error!("(ServiceClient) GET 请求失败: {}", e);
-            AppError::InternalError(format!("Failed to call service {}: {}", service_name, e))
-        })?;
-
-    // 3. 改进错误处理
-    if response.status().is_success() {
-        let data = response.json::<T>().await.map_err(|e| {
-            error!("(ServiceClient) 解析 GET 响应 JSON 失败: {}", e);
-            AppError::InternalError(format!("Failed to parse response from {}: {}", service_name, e))
-        })?;
-        Ok(data)
-    } else {
-        let status = response.status();
-        let error_body = response.text().await.unwrap_or_default();
-        warn!(
-            "(ServiceClient) 服务 {} GET 返回非 200 状态码: {} | Body: {}",
-            service_name, status, error_body
-        );
-        Err(AppError::InternalError(format!(
-            "上游服务 '{}' (路径: '{}') 返回状态码 {}",
-            service_name, endpoint_path, status
-        )))
-    }
 }
 
 /// (完整版) 通用 POST 请求，可指定 Nacos Group
+///
+/// POST 不是幂等的，不会自动重试，但仍然使用按服务配置的超时。
 pub(super) async fn post_service_with_group<T, Q, B>(
     state: &AppState,
     service_name: &str,
     group_name: Option<String>, // <-- 允许指定 Group
+    hash_key: Option<&str>, // <-- 新增：consistent_hash 负载均衡使用的请求 key
     endpoint_path: &str,
     query_params: &Q, 
     body: &B,
@@ -142,59 +137,805 @@ where
     Q: Serialize + ?Sized, 
     B: Serialize + ?Sized,
 {
-    // 1. 获取基础 URL (逻辑复用)
-    let base_url = discover_service_url(state, service_name, group_name).await?;
-    let target_url = format!("{}{}", base_url, endpoint_path);
-
-    info!("(ServiceClient) POST: {}", target_url);
-
-    // 2. 发起 HTTP POST 请求
-    let response = state
-        .http_client
-        .post(target_url)
-        .query(query_params) 
-        .json(body)          
-        .send()
+    ServiceRequest::new(state, Method::POST, service_name, endpoint_path)
+        .group_opt(group_name)
+        .hash_key_opt(hash_key)
+        .query(query_params)
+        .json(body)
+        .send_json()
         .await
-        .map_err(|e| {
-            error!("(ServiceClient) POST 请求失败: {}", e);
-            AppError::InternalError(format!("Failed to call service {}: {}", service_name, e))
+}
+
+
+// --- 路径参数编码 ---
+
+/// 路径段中需要编码的字符 (WHATWG URL 的 path percent-encode set，另加 `/` 和 `%`)
+#[allow(dead_code)] // 只有带 #[path] 参数的客户端会用到
+const PATH_SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}')
+    .add(b'/')
+    .add(b'%');
+
+/// 将一个值编码为单个路径段 (`#[service_client]` 生成的代码用它替换 `#[path]` 占位符)
+///
+/// 值中的 `/`、`?`、`#`、`%` 等都会被编码，因此不能借路径参数改写请求的路径或查询串。
+/// `.` / `..` 会被 URL 规范化当作 "当前/上级目录" 去掉路径段 (编码为 `%2e` 也一样)，直接拒绝；
+/// 值中的 `%` 会被编码，因此 `%2e` 这类输入不会变成点号段。
+#[allow(dead_code)] // 只有带 #[path] 参数的客户端会用到
+pub fn encode_path_segment(value: &impl std::fmt::Display) -> Result<String, AppError> {
+    let value = value.to_string();
+    if value == "." || value == ".." {
+        return Err(ServiceError::InvalidArgument(format!("路径参数不能是 `{}`", value)).into());
+    }
+    Ok(utf8_percent_encode(&value, PATH_SEGMENT).to_string())
+}
+
+
+// --- Builder API (公开，业务代码可直接使用) ---
+
+/// 上游响应的原始内容 (不论状态码)，由调用方自行处理 404/409 等
+#[derive(Debug, Clone)]
+#[allow(dead_code)] // 公开 API：业务代码与 #[service_client] 生成的客户端按需使用
+pub struct RawResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Bytes,
+}
+
+#[allow(dead_code)] // 公开 API：业务代码与 #[service_client] 生成的客户端按需使用
+impl RawResponse {
+    /// 状态码是否为 2xx
+    pub fn is_success(&self) -> bool {
+        self.status.is_success()
+    }
+
+    /// 非 2xx 时转换为 `AppError::Upstream` (与 `send_json` 的错误一致)
+    pub fn error_for_status(self, service_name: &str, path: &str) -> Result<Self, AppError> {
+        if self.is_success() {
+            return Ok(self);
+        }
+        Err(AppError::Upstream(Box::new(UpstreamError::from_body(
+            service_name,
+            path,
+            self.status,
+            &self.body,
+        ))))
+    }
+
+    /// 将响应体解析为 JSON
+    pub fn json<T: DeserializeOwned>(&self) -> Result<T, AppError> {
+        serde_json::from_slice(&self.body)
+            .map_err(|e| AppError::InternalError(format!("解析响应 JSON 失败: {}", e)))
+    }
+}
+
+/// 上游的统一响应结构 (与我们的 `response::ApiResponse` 相同)
+#[derive(Deserialize)]
+struct Envelope<T> {
+    code: i64,
+    msg: Option<String>,
+    data: Option<T>,
+}
+
+impl<T: DeserializeOwned> Envelope<T> {
+    /// `code == 0` 时取出 `data`，否则转换为 `AppError::Upstream`
+    fn into_data(self, service_name: &str, path: &str) -> Result<T, AppError> {
+        if self.code != 0 {
+            warn!(
+                "(ServiceClient) 服务 {} (路径: {}) 返回业务错误: code={}, msg={:?}",
+                service_name, path, self.code, self.msg
+            );
+            return Err(AppError::Upstream(Box::new(UpstreamError::business(
+                service_name,
+                path,
+                self.code,
+                self.msg,
+            ))));
+        }
+        match self.data {
+            Some(data) => Ok(data),
+            // 没有 data 时，T 为 () / Option<_> 等可以从 null 解析的类型才算成功
+            None => serde_json::from_value(serde_json::Value::Null).map_err(|_| {
+                AppError::InternalError(format!(
+                    "上游服务 '{}' (路径: '{}') 的响应缺少 data",
+                    service_name, path
+                ))
+            }),
+        }
+    }
+}
+
+/// 请求体
+#[allow(dead_code)] // multipart / 流只由业务代码构造
+enum RequestBody {
+    Empty,
+    // 可重复发送的请求体 (JSON / form / bytes)，重试时复用
+    Reusable { bytes: Bytes, content_type: Option<&'static str> },
+    // 只能发送一次的请求体 (multipart / 流)，使用它的请求不会自动重试
+    Multipart(Form),
+    Stream(Body),
+}
+
+/// 对 Nacos 服务的一次调用 (builder 风格)
+///
+/// ```ignore
+/// let resp = ServiceRequest::new(&state, Method::PUT, "rtsp-upms-service", "/user/1")
+///     .header("X-Tenant-Id", "1")
+///     .json(&update)
+///     .send_raw()
+///     .await?;
+/// if resp.status == StatusCode::CONFLICT { ... }
+/// ```
+///
+/// - 服务发现、负载均衡、超时、重试与熔断与 `get_service` 等函数一致
+/// - 构建过程中的错误 (非法 Header、序列化失败) 会推迟到发送时返回
+#[must_use = "ServiceRequest 需要调用 send_json / send_raw 才会发出"]
+pub struct ServiceRequest<'a> {
+    state: &'a AppState,
+    method: Method,
+    service_name: &'a str,
+    path: &'a str,
+    group_name: Option<String>,
+    hash_key: Option<String>,
+    headers: HeaderMap,
+    query: Option<String>,
+    body: RequestBody,
+    envelope: bool,
+    error: Option<AppError>,
+}
+
+#[allow(dead_code)] // 公开 API：业务代码与 #[service_client] 生成的客户端按需使用
+impl<'a> ServiceRequest<'a> {
+    /// 创建一个请求
+    ///
+    /// # Arguments
+    /// * `service_name` - Nacos 服务名，例如 "rtsp-upms-service"
+    /// * `path` - 接口路径，例如 "/user/1"
+    pub fn new(state: &'a AppState, method: Method, service_name: &'a str, path: &'a str) -> Self {
+        ServiceRequest {
+            state,
+            method,
+            service_name,
+            path,
+            group_name: None,
+            hash_key: None,
+            headers: HeaderMap::new(),
+            query: None,
+            body: RequestBody::Empty,
+            envelope: false,
+            error: None,
+        }
+    }
+
+    /// 指定 Nacos Group (None 为默认分组 DEFAULT_GROUP)
+    pub fn group_opt(mut self, group_name: Option<String>) -> Self {
+        self.group_name = group_name;
+        self
+    }
+
+    /// consistent_hash 负载均衡使用的请求 key (例如用户 ID)
+    pub fn hash_key(self, key: impl Into<String>) -> Self {
+        self.hash_key_opt(Some(&key.into()))
+    }
+
+    pub fn hash_key_opt(mut self, key: Option<&str>) -> Self {
+        self.hash_key = key.map(str::to_string);
+        self
+    }
+
+    /// 添加一个请求头
+    pub fn header<K, V>(mut self, name: K, value: V) -> Self
+    where
+        K: TryInto<HeaderName>,
+        K::Error: std::fmt::Display,
+        V: TryInto<HeaderValue>,
+        V::Error: std::fmt::Display,
+    {
+        match (name.try_into(), value.try_into()) {
+            (Ok(name), Ok(value)) => {
+                self.headers.append(name, value);
+            }
+            (Err(e), _) => self.fail(format!("非法的请求头名称: {}", e)),
+            (_, Err(e)) => self.fail(format!("非法的请求头值: {}", e)),
+        }
+        self
+    }
+
+    /// 查询参数 (多次调用会追加)
+    pub fn query<Q: Serialize + ?Sized>(mut self, query: &Q) -> Self {
+        match serde_urlencoded::to_string(query) {
+            Ok(encoded) if encoded.is_empty() => {}
+            Ok(encoded) => {
+                self.query = Some(match self.query.take() {
+                    Some(existing) => format!("{}&{}", existing, encoded),
+                    None => encoded,
+                });
+            }
+            Err(e) => self.fail(format!("序列化查询参数失败: {}", e)),
+        }
+        self
+    }
+
+    /// JSON 请求体
+    pub fn json<B: Serialize + ?Sized>(mut self, body: &B) -> Self {
+        match serde_json::to_vec(body) {
+            Ok(bytes) => {
+                self.body = RequestBody::Reusable {
+                    bytes: bytes.into(),
+                    content_type: Some("application/json"),
+                }
+            }
+            Err(e) => self.fail(format!("序列化 JSON 请求体失败: {}", e)),
+        }
+        self
+    }
+
+    /// `application/x-www-form-urlencoded` 请求体
+    pub fn form<B: Serialize + ?Sized>(mut self, body: &B) -> Self {
+        match serde_urlencoded::to_string(body) {
+            Ok(encoded) => {
+                self.body = RequestBody::Reusable {
+                    bytes: encoded.into(),
+                    content_type: Some("application/x-www-form-urlencoded"),
+                }
+            }
+            Err(e) => self.fail(format!("序列化表单请求体失败: {}", e)),
+        }
+        self
+    }
+
+    /// `multipart/form-data` 请求体 (只能发送一次，不会自动重试)
+    pub fn multipart(mut self, form: Form) -> Self {
+        self.body = RequestBody::Multipart(form);
+        self
+    }
+
+    /// 流式请求体 (只能发送一次，不会自动重试)
+    pub fn stream<S>(mut self, stream: S) -> Self
+    where
+        S: TryStream + Send + 'static,
+        S::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+        Bytes: From<S::Ok>,
+    {
+        self.body = RequestBody::Stream(Body::wrap_stream(stream));
+        self
+    }
+
+    /// 上游返回统一响应结构 `{code, msg, data}` 时使用：`send_json` 会解开外层结构，
+    /// `code == 0` 时返回 `data`，否则返回保留了上游 code / msg 的 `AppError::Upstream`
+    pub fn envelope(mut self) -> Self {
+        self.envelope = true;
+        self
+    }
+
+    /// 发送请求，2xx 时将响应解析为 `T`，否则返回错误
+    pub async fn send_json<T: DeserializeOwned + 'static>(self) -> Result<T, AppError> {
+        let (service_name, method, path) = (self.service_name, self.method.clone(), self.path);
+        let envelope = self.envelope;
+        let (response, _instance) = self.send().await?;
+        if envelope {
+            let envelope: Envelope<T> = parse_json_response(response, service_name, method, path).await?;
+            envelope.into_data(service_name, path)
+        } else {
+            parse_json_response(response, service_name, method, path).await
+        }
+    }
+
+    /// 发送请求，返回原始的状态码、响应头和响应体 (非 2xx 不视为错误)
+    pub async fn send_raw(self) -> Result<RawResponse, AppError> {
+        let (service_name, path) = (self.service_name, self.path);
+        let (response, _instance) = self.send().await?;
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = response.bytes().await.map_err(reqwest::Error::without_url).map_err(|e| {
+            error!("(ServiceClient) 读取服务 {} 的响应体失败: {}", service_name, e);
+            transport_error(service_name, path, &e)
         })?;
+        Ok(RawResponse { status, headers, body })
+    }
+
+    /// 辅助函数：在 service_client span 中发送请求 (持有返回的 SelectedInstance 直到响应读取完毕)
+    ///
+    /// span 记录服务名、最终选中的 Nacos 实例与响应状态码；透传的 traceparent 指向这个 span。
+    async fn send(self) -> Result<(Response, SelectedInstance), AppError> {
+        let span = tracing::info_span!(
+            "service_client",
+            otel.name = %format!("{} {}", self.method, self.service_name),
+            otel.kind = "client",
+            peer.service = self.service_name,
+            http.request.method = %self.method,
+            url.path = self.path,
+            server.address = Empty,
+            http.response.status_code = Empty,
+            otel.status_code = Empty,
+        );
+        let result = self.send_inner().instrument(span.clone()).await;
+        match &result {
+            Ok((response, _)) => {
+                span.record("http.response.status_code", response.status().as_u16());
+                if response.status().is_server_error() {
+                    span.record("otel.status_code", "ERROR");
+                }
+            }
+            Err(_) => {
+                span.record("otel.status_code", "ERROR");
+            }
+        }
+        result
+    }
+
+    /// 辅助函数：组装请求体与 Header 后交给 `send_with_retry`
+    async fn send_inner(mut self) -> Result<(Response, SelectedInstance), AppError> {
+        if let Some(e) = self.error.take() {
+            return Err(e);
+        }
+
+        let path = append_query(self.path, self.query.as_deref());
+        let retry_allowed = matches!(self.body, RequestBody::Empty | RequestBody::Reusable { .. });
+        // 透传 Header (request id / trace context / Authorization) 在前，显式设置的同名 Header 覆盖它们
+        let mut headers = propagation::outbound_headers(self.state, self.service_name).await;
+        headers.extend(self.headers);
+        let mut body = Some(self.body);
+
+        let target = CallTarget {
+            service_name: self.service_name,
+            group_name: self.group_name,
+            hash_key: self.hash_key.as_deref(),
+            method: self.method,
+            path: &path,
+            retry_allowed,
+        };
+
+        send_with_retry(self.state, target, |request| {
+            let request = request.headers(headers.clone());
+            match body.take() {
+                Some(RequestBody::Reusable { bytes, content_type }) => {
+                    let request = match content_type {
+                        Some(content_type) => request.header(CONTENT_TYPE, content_type),
+                        None => request,
+                    };
+                    // 重试时需要再次发送，放回去 (Bytes 的 clone 只是增加引用计数)
+                    body = Some(RequestBody::Reusable {
+                        bytes: bytes.clone(),
+                        content_type,
+                    });
+                    request.body(bytes)
+                }
+                Some(RequestBody::Multipart(form)) => request.multipart(form),
+                Some(RequestBody::Stream(stream)) => request.body(stream),
+                Some(RequestBody::Empty) | None => request,
+            }
+        })
+        .await
+    }
+
+    /// 辅助函数：记录第一个构建错误
+    fn fail(&mut self, message: String) {
+        warn!("(ServiceClient) 构建请求失败: {}", message);
+        self.error.get_or_insert(AppError::InternalError(message));
+    }
+}
+
+
+// --- 超时与重试 ---
+
+/// 重试退避的基础时间
+const RETRY_BASE_BACKOFF: Duration = Duration::from_millis(100);
+/// 重试退避的上限
+const RETRY_MAX_BACKOFF: Duration = Duration::from_secs(2);
+/// 这些状态码说明上游暂时不可用，幂等请求可以换一个实例重试
+const RETRYABLE_STATUS: [StatusCode; 3] = [
+    StatusCode::BAD_GATEWAY,
+    StatusCode::SERVICE_UNAVAILABLE,
+    StatusCode::GATEWAY_TIMEOUT,
+];
+
+/// 某个下游服务的调用策略
+struct CallPolicy {
+    // 单次请求超时；None 时使用 `build_http_client` 中的全局超时
+    timeout: Option<Duration>,
+    // 失败后的最大重试次数 (不含第一次请求)
+    retry_attempts: u32,
+    // 熔断配置
+    breaker: BreakerSettings,
+}
+
+/// 辅助函数：读取调用策略，`upstreams.<service_name>` 优先于全局 `service`
+async fn resolve_call_policy(state: &AppState, service_name: &str) -> CallPolicy {
+    let config = state.app_config.read().await;
+    let upstream = config.upstreams.get(service_name);
+    let global = config.service.as_ref();
+
+    let timeout_ms = upstream
+        .and_then(|u| u.timeout_ms)
+        .or_else(|| global.and_then(|g| g.timeout_ms));
+    let retry_attempts = upstream
+        .and_then(|u| u.retry_attempts)
+        .or_else(|| global.and_then(|g| g.retry_attempts))
+        .unwrap_or(0);
+
+    CallPolicy {
+        timeout: timeout_ms.map(Duration::from_millis),
+        retry_attempts,
+        breaker: BreakerSettings::resolve(
+            upstream.and_then(|u| u.circuit_breaker.as_ref()),
+            config.circuit_breaker.as_ref(),
+        ),
+    }
+}
+
+/// 辅助函数：按 HTTP 语义判断方法是否幂等 (只有幂等请求才会自动重试)
+fn is_idempotent(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::PUT | Method::DELETE | Method::OPTIONS
+    )
+}
+
+/// 辅助函数：带抖动的指数退避 (full jitter)，第 n 次重试等待 [0, min(上限, 基础 * 2^n)) 内的随机时间
+fn backoff(retry: u32) -> Duration {
+    let ceiling = RETRY_BASE_BACKOFF
+        .saturating_mul(2u32.saturating_pow(retry))
+        .min(RETRY_MAX_BACKOFF);
+    ceiling.mul_f64(rand::rng().random_range(0.0..1.0))
+}
+
+/// 辅助函数：没有拿到上游响应 (连接失败、超时、读取响应体失败) 时的错误，超时 504，其余 502
+fn transport_error(service_name: &str, path: &str, e: &reqwest::Error) -> AppError {
+    AppError::Upstream(Box::new(UpstreamError::transport(
+        service_name,
+        strip_query(path),
+        e.is_timeout(),
+        e.to_string(),
+    )))
+}
+
+/// 辅助函数：把编码后的查询参数拼接到路径上 (路径本身已带查询串时用 `&` 追加)
+fn append_query(path: &str, query: Option<&str>) -> String {
+    match query {
+        Some(query) if path.contains('?') => format!("{}&{}", path, query),
+        Some(query) => format!("{}?{}", path, query),
+        None => path.to_string(),
+    }
+}
+
+/// 辅助函数：去掉路径上的查询串 (用于日志)
+fn strip_query(path: &str) -> &str {
+    path.split_once('?').map_or(path, |(path, _)| path)
+}
+
+/// 一次调用的目标与选项 (由 `ServiceRequest` 组装)
+struct CallTarget<'a> {
+    service_name: &'a str,
+    group_name: Option<String>,
+    hash_key: Option<&'a str>,
+    method: Method,
+    // 已拼接查询参数的路径
+    path: &'a str,
+    // 请求体能否重复发送 (multipart / 流只能发送一次)
+    retry_allowed: bool,
+}
+
+/// 辅助函数：选择实例并发送请求，按调用策略处理超时与重试
+///
+/// 重试条件：请求幂等、请求体可重复发送 (`retry_allowed`)，且连接失败或返回 502/503/504。
+/// 每次重试都会排除已经失败过的实例。
+/// 每次请求前都会检查熔断器，熔断中返回 `AppError::CircuitOpen`。
+/// 返回的 `SelectedInstance` 需要一直持有到响应读取完毕 (用于 least_in_flight 计数)。
+async fn send_with_retry<F>(
+    state: &AppState,
+    target: CallTarget<'_>,
+    mut build_request: F,
+) -> Result<(Response, SelectedInstance), AppError>
+where
+    F: FnMut(RequestBuilder) -> RequestBuilder,
+{
+    let CallTarget {
+        service_name,
+        group_name,
+        hash_key,
+        method,
+        path: endpoint_path,
+        retry_allowed,
+    } = target;
+    let policy = resolve_call_policy(state, service_name).await;
+    let max_retries = if retry_allowed && is_idempotent(&method) {
+        policy.retry_attempts
+    } else {
+        0
+    };
+    let timeout = policy.timeout;
+    let mut failed_instances: Vec<String> = Vec::new();
+    let mut retry = 0;
+
+    let breaker = &policy.breaker;
+
+    loop {
+        // 按实例熔断时，负载均衡跳过已熔断的实例
+        let mut exclude = failed_instances.clone();
+        if breaker.enabled && breaker.per_instance {
+            exclude.extend(state.circuit_breakers.open_instances(service_name));
+        }
+        let instance =
+            discover_service_instance(state, service_name, group_name.clone(), hash_key, &exclude)
+                .await?;
+
+        // 熔断中直接快速失败，不再等待上游超时
+        let permit = if breaker.enabled {
+            let key = breaker.key(service_name, &instance.address);
+            match state.circuit_breakers.try_acquire(&key, breaker) {
+                Some(permit) => Some(permit),
+                None => {
+                    warn!("(ServiceClient) {} 熔断中，拒绝调用", key);
+                    state.metrics.observe_outbound(
+                        service_name,
+                        method.as_str(),
+                        OutboundOutcome::CircuitOpen,
+                        Duration::ZERO,
+                    );
+                    return Err(AppError::CircuitOpen(service_name.to_string()));
+                }
+            }
+        } else {
+            None
+        };
+
+        let target_url = format!("{}{}", instance.base_url, endpoint_path);
+        // 重试时覆盖为最后一次选中的实例
+        tracing::Span::current().record("server.address", instance.address.as_str());
+
+        // 查询串中可能有 token 等敏感参数，日志只记录路径
+        info!("(ServiceClient) {}: {}{}", method, instance.base_url, strip_query(endpoint_path));
+
+        let mut request = build_request(state.http_client.request(method.clone(), target_url));
+        if let Some(timeout) = timeout {
+            request = request.timeout(timeout);
+        }
+        let started = Instant::now();
+        // reqwest 的错误信息带完整 URL (含查询串)，去掉后再记录
+        let result = request.send().await.map_err(reqwest::Error::without_url);
+        let outcome = match &result {
+            Ok(response) if response.status().is_server_error() => OutboundOutcome::ServerError,
+            Ok(response) if response.status().is_client_error() => OutboundOutcome::ClientError,
+            Ok(_) => OutboundOutcome::Success,
+            Err(_) => OutboundOutcome::TransportError,
+        };
+        state
+            .metrics
+            .observe_outbound(service_name, method.as_str(), outcome, started.elapsed());
+
+        // 传输错误和 5xx 计为失败，4xx 说明上游是活着的
+        if let Some(permit) = permit {
+            let success = matches!(&result, Ok(response) if !response.status().is_server_error());
+            permit.record(success);
+        }
+
+        let retryable = match &result {
+            Ok(response) => RETRYABLE_STATUS.contains(&response.status()),
+            Err(e) => e.is_connect(),
+        };
+        if retryable && retry < max_retries {
+            let delay = backoff(retry);
+            match &result {
+                Ok(response) => warn!(
+                    "(ServiceClient) 服务 {} 实例 {} 返回 {}，{:?} 后重试 ({}/{})",
+                    service_name, instance.address, response.status(), delay, retry + 1, max_retries
+                ),
+                Err(e) => warn!(
+                    "(ServiceClient) 服务 {} 实例 {} 连接失败: {}，{:?} 后重试 ({}/{})",
+                    service_name, instance.address, e, delay, retry + 1, max_retries
+                ),
+            }
+            failed_instances.push(instance.address.clone());
+            drop(instance);
+            tokio::time::sleep(delay).await;
+            retry += 1;
+            continue;
+        }
+
+        return match result {
+            Ok(response) => Ok((response, instance)),
+            Err(e) => {
+                error!("(ServiceClient) {} 请求失败: {}", method, e);
+                Err(transport_error(service_name, endpoint_path, &e))
+            }
+        };
+    }
+}
 
-    // 3. 改进错误处理
+/// 辅助函数：2xx 时解析 JSON，否则记录响应体并返回 `AppError::Upstream`
+async fn parse_json_response<T>(
+    response: Response,
+    service_name: &str,
+    method: Method,
+    endpoint_path: &str,
+) -> Result<T, AppError>
+where
+    T: DeserializeOwned + 'static,
+{
     if response.status().is_success() {
-        let data = response.json::<T>().await.map_err(|e| {
-            error!("(ServiceClient) 解析 POST 响应 JSON 失败: {}", e);
+        let data = response.json::<T>().await.map_err(reqwest::Error::without_url).map_err(|e| {
+            error!("(ServiceClient) 解析 {} 响应 JSON 失败: {}", method, e);
             AppError::InternalError(format!("Failed to parse response from {}: {}", service_name, e))
         })?;
         Ok(data)
     } else {
         let status = response.status();
-        let error_body = response.text().await.unwrap_or_default();
+        let error_body = response.bytes().await.unwrap_or_default();
         warn!(
-            "(ServiceClient) 服务 {} POST 返回非 200 状态码: {} | Body: {}",
-            service_name, status, error_body
+            "(ServiceClient) 服务 {} {} 返回非 200 状态码: {} | Body: {}",
+            service_name, method, status, String::from_utf8_lossy(&error_body)
         );
-        Err(AppError::InternalError(format!(
-            "上游服务 '{}' (路径: '{}') 返回状态码 {}",
-            service_name, endpoint_path, status
-        )))
+        Err(AppError::Upstream(Box::new(UpstreamError::from_body(
+            service_name,
+            endpoint_path,
+            status,
+            &error_body,
+        ))))
     }
 }
 
 
-/// 辅助函数：封装 Nacos 服务发现
-async fn discover_service_url(
+/// 辅助函数：封装 Nacos 服务发现与负载均衡
+///
+/// 策略、cluster / metadata 过滤由 Nacos 配置 `upstreams.<service_name>` 决定，见 `load_balancer.rs`。
+async fn discover_service_instance(
     state: &AppState,
     service_name: &str,
-    group_name: Option<String>, 
-) -> Result<String, AppError> {
-    
-    // 直接执行 Nacos 服务发现
-    let instance = state
-        .naming_client
-        .select_one_healthy_instance(service_name.to_string(), group_name, Vec::new(), true)
-        .await?;
-    
-    Ok(format!("http://{}:{}", instance.ip, instance.port))
-}
\ No newline at end of file
+    group_name: Option<String>,
+    hash_key: Option<&str>,
+    exclude: &[String],
+) -> Result<SelectedInstance, AppError> {
+    state
+        .load_balancer
+        .select(state, service_name, group_name, hash_key, exclude)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_idempotent_methods_are_retried() {
+        for method in [Method::GET, Method::HEAD, Method::PUT, Method::DELETE, Method::OPTIONS] {
+            assert!(is_idempotent(&method), "{}", method);
+        }
+        for method in [Method::POST, Method::PATCH, Method::CONNECT, Method::TRACE] {
+            assert!(!is_idempotent(&method), "{}", method);
+        }
+    }
+
+    #[test]
+    fn backoff_stays_within_exponential_ceiling() {
+        for retry in 0..40 {
+            let ceiling = (RETRY_BASE_BACKOFF * 2u32.saturating_pow(retry.min(31))).min(RETRY_MAX_BACKOFF);
+            for _ in 0..50 {
+                let delay = backoff(retry);
+                assert!(delay < ceiling, "retry {}: {:?} >= {:?}", retry, delay, ceiling);
+            }
+        }
+        // 超过上限后不再增长
+        assert!(backoff(u32::MAX) < RETRY_MAX_BACKOFF);
+    }
+
+    #[tokio::test]
+    async fn connection_errors_map_to_bad_gateway() {
+        // 先占用一个端口再释放，保证没有进程在监听
+        let addr = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let e = reqwest::Client::new()
+            .get(format!("http://{}/user/1?token=secret", addr))
+            .send()
+            .await
+            .map_err(reqwest::Error::without_url)
+            .unwrap_err();
+        assert!(e.is_connect());
+
+        let AppError::Upstream(upstream) = transport_error("upms", "/user/1?token=secret", &e) else {
+            panic!("expected AppError::Upstream");
+        };
+        assert_eq!(upstream.status, StatusCode::BAD_GATEWAY);
+        assert_eq!(upstream.path, "/user/1");
+        assert!(!upstream.to_string().contains("secret"));
+    }
+
+    #[tokio::test]
+    async fn timeouts_map_to_gateway_timeout() {
+        // 接受连接但从不响应
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (_socket, _) = listener.accept().await.unwrap();
+            std::future::pending::<()>().await;
+        });
+
+        let e = reqwest::Client::new()
+            .get(format!("http://{}/slow", addr))
+            .timeout(Duration::from_millis(50))
+            .send()
+            .await
+            .unwrap_err();
+        assert!(e.is_timeout());
+
+        let AppError::Upstream(upstream) = transport_error("upms", "/slow", &e) else {
+            panic!("expected AppError::Upstream");
+        };
+        assert_eq!(upstream.status, StatusCode::GATEWAY_TIMEOUT);
+        server.abort();
+    }
+
+    #[test]
+    fn append_query_handles_existing_query_string() {
+        assert_eq!(append_query("/users", None), "/users");
+        assert_eq!(append_query("/users", Some("page=1")), "/users?page=1");
+        assert_eq!(append_query("/users?type=1", Some("page=1")), "/users?type=1&page=1");
+    }
+
+    #[test]
+    fn strip_query_drops_query_string() {
+        assert_eq!(strip_query("/token/check_token?token=secret"), "/token/check_token");
+        assert_eq!(strip_query("/users"), "/users");
+    }
+
+    fn unwrap<T: DeserializeOwned>(json: &str) -> Result<T, AppError> {
+        serde_json::from_str::<Envelope<T>>(json).unwrap().into_data("upms", "/user/1")
+    }
+
+    #[test]
+    fn envelope_returns_data_on_success() {
+        assert_eq!(unwrap::<i64>(r#"{"code":0,"msg":"ok","data":7}"#).unwrap(), 7);
+        // 没有 data 时只有 () / Option 能解析成功
+        unwrap::<()>(r#"{"code":0}"#).unwrap();
+        assert_eq!(unwrap::<Option<i64>>(r#"{"code":0,"data":null}"#).unwrap(), None);
+        assert!(matches!(unwrap::<i64>(r#"{"code":0}"#), Err(AppError::InternalError(_))));
+    }
+
+    #[test]
+    fn envelope_keeps_upstream_code_on_failure() {
+        match unwrap::<i64>(r#"{"code":10004,"msg":"not found","data":null}"#) {
+            Err(AppError::Upstream(e)) => {
+                assert!(e.is_business_error());
+                assert_eq!((e.code, e.msg.as_deref()), (Some(10004), Some("not found")));
+            }
+            other => panic!("unexpected: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn encode_path_segment_escapes_separators() {
+        let encode = |value: &str| encode_path_segment(&value).unwrap();
+        assert_eq!(encode_path_segment(&42).unwrap(), "42");
+        assert_eq!(encode("a-b_c.d~"), "a-b_c.d~");
+        assert_eq!(encode("../admin?x=1#f"), "..%2Fadmin%3Fx=1%23f");
+        assert_eq!(encode("50% off"), "50%25%20off");
+        assert_eq!(encode("张三"), "%E5%BC%A0%E4%B8%89");
+    }
+
+    #[test]
+    fn encode_path_segment_rejects_dot_segments() {
+        for value in [".", ".."] {
+            assert!(matches!(
+                encode_path_segment(&value),
+                Err(AppError::Service(ServiceError::InvalidArgument(_)))
+            ));
+        }
+        // 编码后的点号不会被当作点号段
+        assert_eq!(encode_path_segment(&"%2e%2e").unwrap(), "%252e%252e");
+        assert_eq!(encode_path_segment(&"...").unwrap(), "...");
+
+        // 拼出的 URL 经规范化后路径段不会丢失
+        let url = |segment: &str| {
+            reqwest::Url::parse(&format!("http://upms/user/{}/roles", segment)).unwrap().path().to_string()
+        };
+        assert_eq!(url(&encode_path_segment(&"%2e%2e").unwrap()), "/user/%252e%252e/roles");
+        assert_eq!(url(".."), "/roles");
+    }
+}
@@ -2,6 +2,10 @@
 // 存放从 Nacos 加载的具体业务配置结构体。
 
 use serde::Deserialize; // 需要导入 Deserialize
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, OnceLock};
+use crate::middleware::logging::AccessLogRules;
 use serde_yaml; // 需要导入 serde_yaml 来使用其 Error 类型
 
 // --- Nacos 业务配置 (使用嵌套结构体) ---
@@ -26,6 +30,37 @@ pub struct AppSpecificConfig {
 
     // 对应 YAML 中的 service 嵌套结构
     pub service: Option<ServiceConfig>,
+
+    // 对应 YAML 中的 app_access_auth 嵌套结构 (X-App-Access-Key 认证)
+    pub app_access_auth: Option<AppAccessAuthConfig>,
+
+    // 对应 YAML 中的 auth 嵌套结构 (Bearer Token 的校验方式)
+    pub auth: Option<AuthConfig>,
+
+    // 对应 YAML 中的 rbac 嵌套结构 (角色 -> 权限)
+    pub rbac: Option<RbacConfig>,
+
+    // 对应 YAML 中的 circuit_breaker 嵌套结构 (下游服务熔断的全局默认值)
+    pub circuit_breaker: Option<CircuitBreakerConfig>,
+
+    // 对应 YAML 中的 propagation 嵌套结构 (出站调用透传哪些 Header 的全局默认值)
+    pub propagation: Option<PropagationConfig>,
+
+    // 对应 YAML 中的 access_log 嵌套结构 (访问日志的采样与脱敏)
+    pub access_log: Option<AccessLogConfig>,
+
+    // 对应 YAML 中的 tracing 嵌套结构 (OpenTelemetry 链路追踪导出)
+    pub tracing: Option<TracingConfig>,
+
+    // 对应 YAML 中的 health 嵌套结构 (readiness 检查的超时与可选组件)
+    pub health: Option<HealthConfig>,
+
+    // 对应 YAML 中的 shutdown 嵌套结构 (优雅停机的等待时间)
+    pub shutdown: Option<ShutdownConfig>,
+
+    // 对应 YAML 中的 upstreams 嵌套结构 (按服务名配置下游服务的调用方式)
+    #[serde(default)]
+    pub upstreams: HashMap<String, UpstreamConfig>,
 }
 
 
@@ -60,10 +95,306 @@ pub struct FeatureFlags {
 #[derive(Debug, Clone, Deserialize, Default)]
 #[allow(dead_code)]
 pub struct ServiceConfig {
-    // 对应 YAML 中的 timeout_ms
+    // 对应 YAML 中的 timeout_ms (下游调用的默认单次超时，可被 upstreams.<name>.timeout_ms 覆盖)
+    pub timeout_ms: Option<u64>,
+    // 对应 YAML 中的 retry_attempts (幂等下游调用的默认重试次数，可被 upstreams.<name>.retry_attempts 覆盖)
+    pub retry_attempts: Option<u32>,
+}
+
+/// App 访问密钥 (X-App-Access-Key) 认证配置
+///
+/// `kms_app_access` 表本身不存储权限，机器调用方的权限在这里配置：
+/// ```yaml
+/// app_access_auth:
+///   default_permissions: ["kms_kmsAppAccess_view"]
+///   permissions:
+///     order-service: ["kms_kmsAppAccess_view", "kms_kmsAppAccess_edit"]
+/// ```
+#[derive(Debug, Clone, Deserialize, Default)]
+#[allow(dead_code)]
+pub struct AppAccessAuthConfig {
+    // 所有通过密钥认证的应用都拥有的权限
+    #[serde(default)]
+    pub default_permissions: Vec<String>,
+    // 按应用名称 (kms_app_access.name) 追加的权限
+    #[serde(default)]
+    pub permissions: HashMap<String, Vec<String>>,
+}
+
+/// 角色 -> 权限 映射配置
+///
+/// ```yaml
+/// rbac:
+///   roles:
+///     ROLE_kms_admin: ["kms_*"]
+///     kms_operator: ["kms_kmsAppAccess_view", "kms_kmsAppAccess_edit_own"]
+/// ```
+#[derive(Debug, Clone, Deserialize, Default)]
+#[allow(dead_code)]
+pub struct RbacConfig {
+    #[serde(default)]
+    pub roles: HashMap<String, Vec<String>>,
+}
+
+/// Bearer Token 的校验方式
+#[derive(Debug, Clone, Copy, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthMode {
+    // 每个请求都调用 Auth 服务的 /token/check_token (默认)
+    #[default]
+    Remote,
+    // 使用 Auth 服务发布的 JWKS 在本地校验 JWT
+    Jwt,
+}
+
+/// 认证配置
+///
+/// ```yaml
+/// auth:
+///   mode: jwt            # remote | jwt，修改后无需重启即可生效
+///   jwt:
+///     jwks_path: /oauth2/jwks
+///     issuer: http://rtsp-auth
+///     jwks_refresh_secs: 300
+///   token_cache:         # 仅对 remote 模式生效
+///     enabled: true
+///     ttl_secs: 300
+///     negative_ttl_secs: 10
+/// ```
+#[derive(Debug, Clone, Deserialize, Default)]
+#[allow(dead_code)]
+pub struct AuthConfig {
+    #[serde(default)]
+    pub mode: AuthMode,
+    pub jwt: Option<JwtConfig>,
+    pub token_cache: Option<TokenCacheConfig>,
+}
+
+/// remote 模式下 check_token 结果的 Redis 缓存配置
+#[derive(Debug, Clone, Deserialize, Default)]
+#[allow(dead_code)]
+pub struct TokenCacheConfig {
+    // 是否启用缓存，默认关闭
+    #[serde(default)]
+    pub enabled: bool,
+    // 校验成功结果的缓存时间 (秒)，默认 300
+    pub ttl_secs: Option<u64>,
+    // 校验失败 (Token 无效) 结果的缓存时间 (秒)，默认 10；为 0 表示不做负缓存
+    pub negative_ttl_secs: Option<u64>,
+}
+
+/// 本地 JWT 校验配置 (`Debug` 不输出 `hs256_secret`，见下方实现)
+#[derive(Clone, Deserialize, Default)]
+#[allow(dead_code)]
+pub struct JwtConfig {
+    // JWKS 在 Auth 服务上的路径 (通过 Nacos 发现 Auth 服务)，默认 `/oauth2/jwks`
+    pub jwks_path: Option<String>,
+    // JWKS 的完整 URL (配置后不再走 Nacos 发现)
+    pub jwks_url: Option<String>,
+    // JWKS 缓存刷新间隔 (秒)，默认 300
+    pub jwks_refresh_secs: Option<u64>,
+    // HS256 的共享密钥 (只有使用 HS256 时才需要)
+    pub hs256_secret: Option<String>,
+    // 期望的 iss / aud (不配置则不校验，启动和配置变更时会记录警告)
+    pub issuer: Option<String>,
+    pub audience: Option<String>,
+    // 校验 exp / nbf 时允许的时钟偏差 (秒)，默认 30
+    pub leeway_secs: Option<u64>,
+}
+
+// 配置会以 `{:?}` 写入日志 (启动与 Nacos 配置变更时)，密钥只显示是否配置
+impl fmt::Debug for JwtConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JwtConfig")
+            .field("jwks_path", &self.jwks_path)
+            .field("jwks_url", &self.jwks_url)
+            .field("jwks_refresh_secs", &self.jwks_refresh_secs)
+            .field("hs256_secret", &redacted(&self.hs256_secret))
+            .field("issuer", &self.issuer)
+            .field("audience", &self.audience)
+            .field("leeway_secs", &self.leeway_secs)
+            .finish()
+    }
+}
+
+/// 辅助函数：日志中代替密钥输出
+fn redacted(secret: &Option<String>) -> Option<&'static str> {
+    secret.as_ref().map(|_| "***")
+}
+
+/// 客户端负载均衡策略
+#[derive(Debug, Clone, Copy, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LoadBalanceStrategy {
+    // 按 Nacos 实例权重随机 (默认)
+    #[default]
+    WeightedRandom,
+    // 轮询
+    RoundRobin,
+    // 选择进行中请求最少的实例 (按权重折算)
+    LeastInFlight,
+    // 按请求 key 做一致性哈希，同一个 key 总是落到同一个实例
+    ConsistentHash,
+}
+
+/// 下游服务 (Nacos 服务名) 的调用配置
+///
+/// ```yaml
+/// upstreams:
+///   rtsp-auth:
+///     load_balance: round_robin   # weighted_random | round_robin | least_in_flight | consistent_hash
+///     clusters: ["DEFAULT"]       # 只使用这些集群的实例 (不配置则不过滤)
+///     metadata:                   # 只使用 metadata 包含这些键值的实例
+///       version: v2
+///     timeout_ms: 3000            # 覆盖全局 service.timeout_ms
+///     retry_attempts: 2           # 覆盖全局 service.retry_attempts (只对幂等请求生效)
+///     circuit_breaker:            # 按字段覆盖全局 circuit_breaker
+///       failure_threshold: 3
+///     propagation:                # 按字段覆盖全局 propagation
+///       forward_authorization: true
+/// ```
+#[derive(Debug, Clone, Deserialize, Default)]
+#[allow(dead_code)]
+pub struct UpstreamConfig {
+    #[serde(default)]
+    pub load_balance: LoadBalanceStrategy,
+    #[serde(default)]
+    pub clusters: Vec<String>,
+    #[serde(default)]
+    pub metadata: HashMap<String, String>,
+    // 单次请求超时 (毫秒)，不配置则使用 service.timeout_ms
     pub timeout_ms: Option<u64>,
-    // 对应 YAML 中的 retry_attempts
+    // 连接失败或 502/503/504 时的最大重试次数，不配置则使用 service.retry_attempts
     pub retry_attempts: Option<u32>,
+    // 熔断配置，未配置的字段使用全局 circuit_breaker
+    pub circuit_breaker: Option<CircuitBreakerConfig>,
+    // Header 透传配置，未配置的字段使用全局 propagation
+    pub propagation: Option<PropagationConfig>,
+}
+
+/// 下游服务熔断配置
+///
+/// ```yaml
+/// circuit_breaker:
+///   enabled: true            # 默认 true
+///   failure_threshold: 5     # 连续失败多少次后熔断，默认 5
+///   open_secs: 30            # 熔断多久后进入 half-open，默认 30
+///   half_open_max_calls: 1   # half-open 状态下允许的探测请求数，默认 1
+///   per_instance: false      # true 时按实例熔断 (负载均衡跳过已熔断实例)，默认按服务
+/// ```
+#[derive(Debug, Clone, Deserialize, Default)]
+#[allow(dead_code)]
+pub struct CircuitBreakerConfig {
+    pub enabled: Option<bool>,
+    pub failure_threshold: Option<u32>,
+    pub open_secs: Option<u64>,
+    pub half_open_max_calls: Option<u32>,
+    pub per_instance: Option<bool>,
+}
+
+/// 出站调用的 Header 透传配置
+///
+/// ```yaml
+/// propagation:
+///   request_id: true              # 透传 X-Request-Id，默认 true
+///   trace_context: true           # 透传 traceparent / tracestate，默认 true
+///   forward_authorization: false  # 透传调用方的 Bearer Token，默认 false
+///   service_token: "xxx"          # 不透传 (或调用方没有 Token) 时使用的服务间调用 Token
+/// ```
+#[derive(Clone, Deserialize, Default)]
+pub struct PropagationConfig {
+    pub request_id: Option<bool>,
+    pub trace_context: Option<bool>,
+    pub forward_authorization: Option<bool>,
+    pub service_token: Option<String>,
+}
+
+// 同 JwtConfig：不在日志中输出 service_token
+impl fmt::Debug for PropagationConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PropagationConfig")
+            .field("request_id", &self.request_id)
+            .field("trace_context", &self.trace_context)
+            .field("forward_authorization", &self.forward_authorization)
+            .field("service_token", &redacted(&self.service_token))
+            .finish()
+    }
+}
+
+/// 访问日志配置
+///
+/// ```yaml
+/// access_log:
+///   enabled: true                       # 默认 true
+///   trust_forwarded_for: false          # 客户端 IP 优先取 X-Forwarded-For / X-Real-IP，默认 false (仅在可信代理之后开启)
+///   redact_query_params: ["api_key"]    # 额外隐藏这些查询参数的值 (在默认的 token / access_token / password 之外)
+///   sampling:                           # 按路由模板 (支持通配符) 采样，4xx / 5xx 总是记录
+///     "/": 0.01
+///     "/health*": 0.0
+/// ```
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct AccessLogConfig {
+    pub enabled: Option<bool>,
+    pub trust_forwarded_for: Option<bool>,
+    pub redact_query_params: Option<Vec<String>>,
+    #[serde(default)]
+    pub sampling: HashMap<String, f64>,
+    // 预处理后的脱敏 / 采样规则，第一次使用时生成 (配置变更会整体替换本结构体，缓存随之失效)
+    #[serde(skip)]
+    rules: OnceLock<Arc<AccessLogRules>>,
+}
+
+impl AccessLogConfig {
+    /// 预处理后的脱敏 / 采样规则 (每份配置只编译一次通配符)
+    pub fn rules(&self) -> Arc<AccessLogRules> {
+        self.rules
+            .get_or_init(|| Arc::new(AccessLogRules::new(self)))
+            .clone()
+    }
+}
+
+/// OpenTelemetry 链路追踪配置 (修改后无需重启)
+///
+/// ```yaml
+/// tracing:
+///   enabled: true                                    # 配置了 tracing 时默认 true
+///   endpoint: "http://otel-collector:4318/v1/traces" # OTLP/HTTP 地址，默认 http://localhost:4318/v1/traces
+///   sample_ratio: 0.1                                # 根 span 采样率 (0.0 ~ 1.0)，默认 1.0；有上游 traceparent 时跟随上游
+/// ```
+#[derive(Debug, Clone, Deserialize, Default, PartialEq)]
+pub struct TracingConfig {
+    pub enabled: Option<bool>,
+    pub endpoint: Option<String>,
+    pub sample_ratio: Option<f64>,
+}
+
+/// 健康检查配置
+///
+/// ```yaml
+/// health:
+///   timeout_ms: 2000        # 每项检查的超时，默认 2000
+///   optional: ["redis"]     # 这些组件 DOWN 时 readiness 仍返回 200 (只在响应中体现)
+/// ```
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct HealthConfig {
+    pub timeout_ms: Option<u64>,
+    #[serde(default)]
+    pub optional: Vec<String>,
+}
+
+/// 优雅停机配置 (在收到停机信号时读取)
+///
+/// ```yaml
+/// shutdown:
+///   propagation_delay_ms: 5000   # 注销 Nacos 后继续接收请求的时间 (等调用方刷新实例列表)，默认 5000
+///   drain_timeout_ms: 20000      # 停止接收新连接后，等待进行中请求完成的最长时间，默认 20000
+/// ```
+///
+/// 两者之和应小于 K8s 的 `terminationGracePeriodSeconds` (默认 30s)。
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct ShutdownConfig {
+    pub propagation_delay_ms: Option<u64>,
+    pub drain_timeout_ms: Option<u64>,
 }
 
 
@@ -73,3 +404,35 @@ pub fn parse_nacos_config(content: &str) -> Result<AppSpecificConfig, serde_yaml
     serde_yaml::from_str(content)
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn debug_output_redacts_secrets() {
+        let config = parse_nacos_config(
+            "\
+auth:
+  mode: jwt
+  jwt:
+    hs256_secret: top-secret
+    issuer: https://auth.example.com
+propagation:
+  service_token: global-token
+upstreams:
+  upms:
+    propagation:
+      service_token: upms-token
+",
+        )
+        .unwrap();
+        let debug = format!("{:?}", config);
+        for secret in ["top-secret", "global-token", "upms-token"] {
+            assert!(!debug.contains(secret));
+        }
+        assert!(debug.contains("hs256_secret: Some(\"***\")"));
+        assert!(debug.contains("service_token: Some(\"***\")"));
+        assert!(debug.contains("https://auth.example.com"));
+    }
+}
@@ -9,6 +9,9 @@ use thiserror::Error;
 // 告诉编译器，去同级目录下的 "app_specific.rs" 文件加载 app_specific 子模块
 pub mod app_specific;
 
+/// 未设置 METRICS_ADDR 时管理端口 (/metrics) 的监听地址
+const DEFAULT_METRICS_ADDR: &str = "0.0.0.0:9100";
+
 // --- 基础配置 (从环境变量加载) ---
 
 /// 应用的基础配置，从环境变量加载
@@ -27,6 +30,17 @@ pub struct Config {
     pub nacos_config_group: String,
     // --- 新增：Auth 服务的 Nacos 名 ---
     pub auth_service_name: String,
+    // --- 新增：独立的管理端口 (只提供 /metrics，不在业务端口上暴露) ---
+    pub metrics_addr: String,
+    // --- 新增：启动时连接依赖的重试策略 (见 setup/startup.rs) ---
+    pub startup_max_attempts: u32,
+    pub startup_backoff_ms: u64,
+    // 允许降级启动 (连接失败时延迟到首次使用再连接) 的依赖，取值 db / redis
+    pub startup_lazy_dependencies: Vec<String>,
+    // --- 新增：离线模式，从本地 YAML 文件读取业务配置 (不访问 Nacos) ---
+    pub app_config_file: Option<String>,
+    // --- 新增：最近一次 Nacos 配置的本地快照 (Nacos 不可用时用于启动)，不配置时不写快照 ---
+    pub config_snapshot_file: Option<String>,
 }
 
 /// 配置加载错误枚举 (保持公共)
@@ -46,18 +60,48 @@ impl Config {
         let app_name = env::var("APP_NAME")?;
         let server_addr = env::var("SERVER_ADDR").unwrap_or_else(|_| "0.0.0.0:4000".to_string());
         let database_url = env::var("DATABASE_URL").ok(); // 可选
-        let nacos_addr = env::var("NACOS_ADDR")?;
+        let app_config_file = env::var("APP_CONFIG_FILE").ok().filter(|path| !path.is_empty()); // 可选
+        // 离线模式下不创建 Nacos 客户端，NACOS_ADDR / NACOS_CONFIG_DATA_ID 可以不配置 (为空，不会被使用)
+        let offline = app_config_file.is_some();
+        let nacos_addr = match env::var("NACOS_ADDR") {
+            Err(_) if offline => String::new(),
+            addr => addr?,
+        };
         let nacos_naming_namespace = env::var("NACOS_NAMING_NAMESPACE").unwrap_or_default();
         let nacos_config_namespace = env::var("NACOS_CONFIG_NAMESPACE").unwrap_or_default();
         let nacos_username = env::var("NACOS_USERNAME").ok(); // 可选
         let nacos_password = env::var("NACOS_PASSWORD").ok(); // 可选
-        let nacos_config_data_id = env::var("NACOS_CONFIG_DATA_ID")?;
+        let nacos_config_data_id = match env::var("NACOS_CONFIG_DATA_ID") {
+            Err(_) if offline => String::new(),
+            data_id => data_id?,
+        };
         let nacos_config_group =
             env::var("NACOS_CONFIG_GROUP").unwrap_or_else(|_| "DEFAULT_GROUP".to_string());
         // --- 新增：加载 Auth 服务名 ---
         // 使用 .unwrap_or_else 提供一个默认值
         let auth_service_name =
             env::var("AUTH_SERVICE_NAME").unwrap_or_else(|_| "rtsp-auth".to_string());
+        let metrics_addr = env::var("METRICS_ADDR")
+            .ok()
+            .filter(|addr| !addr.is_empty())
+            .unwrap_or_else(|| DEFAULT_METRICS_ADDR.to_string());
+        // 格式不正确时使用默认值
+        let startup_max_attempts = env::var("STARTUP_MAX_ATTEMPTS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(10)
+            .max(1);
+        let startup_backoff_ms = env::var("STARTUP_BACKOFF_MS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(500);
+        let startup_lazy_dependencies = env::var("STARTUP_LAZY_DEPENDENCIES")
+            .unwrap_or_default()
+            .split(',')
+            .map(|name| name.trim().to_ascii_lowercase())
+            .filter(|name| !name.is_empty())
+            .collect();
+        let config_snapshot_file = env::var("APP_CONFIG_SNAPSHOT").ok().filter(|path| !path.is_empty()); // 可选
         Ok(Config {
             app_name, // <-- 新增
             server_addr,
@@ -70,6 +114,12 @@ impl Config {
             nacos_config_data_id,
             nacos_config_group,
             auth_service_name,
+            metrics_addr,
+            startup_max_attempts,
+            startup_backoff_ms,
+            startup_lazy_dependencies,
+            app_config_file,
+            config_snapshot_file,
         })
     }
 }
@@ -0,0 +1,140 @@
+// src/dto/kms_app_access_dto.rs
+// `kms_app_access` 相关的请求体定义 (DTO)
+
+use crate::models::kms_app_access;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use validator::Validate; // 导入 `Validate` trait 以使用 `#[derive(Validate)]`
+
+/// POST /app-access 的请求体
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateAppAccessRequest {
+    // 类似于 Java 的 @NotNull
+    pub access_info_id: i64,
+
+    // 类似于 Java 的 @NotNull 和 @Size(min=1)
+    #[validate(length(min = 1, max = 255, message = "应用名称(name)不能为空且长度不能超过 255"))]
+    pub name: String,
+
+    #[validate(length(max = 50, message = "标识(mark)长度不能超过 50"))]
+    pub mark: Option<String>,
+
+    // 不传时默认为启用
+    #[validate(range(min = 0, max = 1, message = "状态(status)只能是 0 或 1"))]
+    pub status: Option<i8>,
+
+    #[validate(length(max = 50, message = "描述(description)长度不能超过 50"))]
+    pub description: Option<String>,
+
+    #[validate(length(max = 255, message = "show_id 长度不能超过 255"))]
+    pub show_id: Option<String>,
+}
+
+/// PUT /app-access/{id} 的请求体 (全量更新，未传的可选字段会被置空)
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateAppAccessRequest {
+    pub access_info_id: i64,
+
+    #[validate(length(min = 1, max = 255, message = "应用名称(name)不能为空且长度不能超过 255"))]
+    pub name: String,
+
+    #[validate(length(max = 50, message = "标识(mark)长度不能超过 50"))]
+    pub mark: Option<String>,
+
+    #[validate(range(min = 0, max = 1, message = "状态(status)只能是 0 或 1"))]
+    pub status: i8,
+
+    #[validate(length(max = 50, message = "描述(description)长度不能超过 50"))]
+    pub description: Option<String>,
+
+    #[validate(length(max = 255, message = "show_id 长度不能超过 255"))]
+    pub show_id: Option<String>,
+}
+
+/// PATCH /app-access/{id} 的请求体 (部分更新，只修改传入的字段)
+///
+/// 对于可空列，`Some(None)` (即 JSON 中显式传 `null`) 表示置空，
+/// 字段缺失表示不修改。
+#[derive(Debug, Deserialize, Validate)]
+pub struct PatchAppAccessRequest {
+    pub access_info_id: Option<i64>,
+
+    #[validate(length(min = 1, max = 255, message = "应用名称(name)不能为空且长度不能超过 255"))]
+    pub name: Option<String>,
+
+    #[serde(default, deserialize_with = "deserialize_some")]
+    #[validate(length(max = 50, message = "标识(mark)长度不能超过 50"))]
+    pub mark: Option<Option<String>>,
+
+    #[validate(range(min = 0, max = 1, message = "状态(status)只能是 0 或 1"))]
+    pub status: Option<i8>,
+
+    #[serde(default, deserialize_with = "deserialize_some")]
+    #[validate(length(max = 50, message = "描述(description)长度不能超过 50"))]
+    pub description: Option<Option<String>>,
+
+    #[serde(default, deserialize_with = "deserialize_some")]
+    #[validate(length(max = 255, message = "show_id 长度不能超过 255"))]
+    pub show_id: Option<Option<String>>,
+}
+
+/// POST /app-access/{id}/rotate 的请求体
+#[derive(Debug, Deserialize, Validate)]
+pub struct RotateAppAccessKeyRequest {
+    // 旧密钥的宽限期 (秒)，期间新旧密钥都能通过校验；不传或为 0 表示旧密钥立即失效
+    // (上一次轮换的旧密钥仍在宽限期内时，只允许不带宽限期的轮换)
+    #[validate(range(max = 604800, message = "宽限期(grace_period_secs)不能超过 7 天"))]
+    pub grace_period_secs: Option<u64>,
+}
+
+/// 创建 / 轮换密钥时的响应体
+///
+/// `app_access_key` 为 *明文* 密钥，服务端不保存明文，这是调用方唯一一次拿到它的机会。
+#[derive(Debug, Serialize)]
+pub struct AppAccessWithKeyResponse {
+    #[serde(flatten)]
+    pub app_access: kms_app_access::Model,
+    pub app_access_key: String,
+}
+
+/// `name` 的匹配方式
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NameMatch {
+    /// 前缀匹配: `name LIKE 'xxx%'`
+    Prefix,
+    /// 包含匹配: `name LIKE '%xxx%'` (默认)
+    #[default]
+    Contains,
+}
+
+/// GET /app-access 的过滤条件 (分页参数见 `utils::pagination::PageQuery`)
+///
+/// 例如: `?name=order&name_match=prefix&status=1&create_time_from=2024-01-01T00:00:00Z`
+#[derive(Debug, Default, Deserialize, Validate)]
+pub struct AppAccessListQuery {
+    #[validate(length(min = 1, max = 255, message = "应用名称(name)长度必须在 1 到 255 之间"))]
+    pub name: Option<String>,
+
+    // 不传时默认为包含匹配
+    pub name_match: Option<NameMatch>,
+
+    #[validate(range(min = 0, max = 1, message = "状态(status)只能是 0 或 1"))]
+    pub status: Option<i8>,
+
+    pub access_info_id: Option<i64>,
+
+    // 创建时间范围 (RFC 3339)，闭区间
+    pub create_time_from: Option<DateTime<Utc>>,
+    pub create_time_to: Option<DateTime<Utc>>,
+}
+
+/// 辅助函数：让 "字段存在 (哪怕是 null)" 反序列化为 `Some(...)`
+/// 配合 `#[serde(default)]`，缺失字段仍然是 `None`
+fn deserialize_some<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: serde::Deserializer<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
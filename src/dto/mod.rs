@@ -0,0 +1,6 @@
+// src/dto/mod.rs
+// 声明 dto (Data Transfer Object) 模块的子模块
+// 类似于 Java 项目中的 dto 包：handler 负责接收/校验，service 负责把它转换为 ActiveModel
+
+// 声明 kms_app_access 相关的请求/响应 DTO
+pub mod kms_app_access_dto;
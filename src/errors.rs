@@ -3,6 +3,7 @@
 // 类似于 Spring Boot 中的 @ControllerAdvice 和 @ExceptionHandler
 
 use crate::response::ApiResponse; // <-- 导入我们统一的响应结构
+use crate::middleware::request_context;
 use axum::{
     Json,
     http::StatusCode,
@@ -14,6 +15,9 @@ use sea_orm::DbErr;
 use thiserror::Error;
 use tracing::error;
 
+use serde_json::Value;
+use std::fmt;
+
 // --- 新增：导入 Redis 相关的错误类型 ---
 use bb8_redis::bb8::RunError;
 use redis::RedisError;
@@ -37,13 +41,112 @@ pub enum ServiceError {
     Unauthorized,
 }
 
+/// 上游服务 (通过 service_client 调用的微服务) 返回的非 2xx 响应
+#[derive(Debug)]
+#[allow(dead_code)]
+pub struct UpstreamError {
+    // Nacos 服务名
+    pub service: String,
+    // 请求路径
+    pub path: String,
+    // 上游返回的 HTTP 状态码
+    pub status: StatusCode,
+    // 上游统一响应结构 (`{code, msg, data}`) 中的 code / msg，响应体不是该结构时为 None
+    pub code: Option<i64>,
+    pub msg: Option<String>,
+    // 响应体：JSON 时为解析后的值，否则为原始文本
+    pub body: Option<Value>,
+    // 没有拿到响应 (连接失败、超时等) 时的错误描述；此时 status 为 502 / 504
+    pub transport: Option<String>,
+}
+
+impl UpstreamError {
+    /// 根据上游响应构造，尽量从响应体中解析出 `code` / `msg`
+    pub fn from_body(service: &str, path: &str, status: StatusCode, body: &[u8]) -> Self {
+        let json = serde_json::from_slice::<Value>(body).ok();
+        let code = json.as_ref().and_then(|v| v.get("code")).and_then(Value::as_i64);
+        let msg = json
+            .as_ref()
+            .and_then(|v| v.get("msg").or_else(|| v.get("message")))
+            .and_then(Value::as_str)
+            .map(str::to_string);
+        let body = json.or_else(|| {
+            (!body.is_empty()).then(|| Value::String(String::from_utf8_lossy(body).into_owned()))
+        });
+
+        UpstreamError {
+            service: service.to_string(),
+            path: path.to_string(),
+            status,
+            code,
+            msg,
+            body,
+            transport: None,
+        }
+    }
+
+    /// 上游返回 2xx，但统一响应结构中的 `code` 不为 0 (业务失败)
+    pub fn business(service: &str, path: &str, code: i64, msg: Option<String>) -> Self {
+        UpstreamError {
+            service: service.to_string(),
+            path: path.to_string(),
+            status: StatusCode::OK,
+            code: Some(code),
+            msg,
+            body: None,
+            transport: None,
+        }
+    }
+
+    /// 没有拿到上游的响应：超时记为 504，连接失败等其他传输错误记为 502
+    pub fn transport(service: &str, path: &str, timed_out: bool, detail: String) -> Self {
+        UpstreamError {
+            service: service.to_string(),
+            path: path.to_string(),
+            status: if timed_out { StatusCode::GATEWAY_TIMEOUT } else { StatusCode::BAD_GATEWAY },
+            code: None,
+            msg: None,
+            body: None,
+            transport: Some(detail),
+        }
+    }
+
+    /// 是否为业务失败 (HTTP 2xx + 非 0 的 code)
+    pub fn is_business_error(&self) -> bool {
+        self.status.is_success() && self.code.is_some()
+    }
+}
+
+impl fmt::Display for UpstreamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(detail) = &self.transport {
+            return write!(
+                f,
+                "调用上游服务 '{}' (路径: '{}') 失败: {}",
+                self.service, self.path, detail
+            );
+        }
+        write!(
+            f,
+            "上游服务 '{}' (路径: '{}') 返回状态码 {}",
+            self.service, self.path, self.status
+        )?;
+        match (self.code, &self.msg) {
+            (Some(code), Some(msg)) => write!(f, " (code: {}, msg: {})", code, msg),
+            (None, Some(msg)) => write!(f, " (msg: {})", msg),
+            _ => Ok(()),
+        }
+    }
+}
+
 /// 统一的应用错误枚举
 /// 使用 thiserror 宏可以方便地将其他错误类型转换为 AppError
 #[derive(Error, Debug)]
 #[allow(dead_code)] // 暂时允许未使用
 pub enum AppError {
+    // Nacos SDK 的错误类型体积很大，装箱后避免拖大所有 Result<_, AppError>
     #[error("Nacos SDK 错误: {0}")]
-    Nacos(#[from] nacos_sdk::api::error::Error),
+    Nacos(Box<nacos_sdk::api::error::Error>),
 
     #[error("环境变量加载失败: {0}")]
     Config(#[from] crate::config::ConfigError),
@@ -63,12 +166,28 @@ pub enum AppError {
 
     #[error("内部错误: {0}")]
     InternalError(String), // 对应 20001
+
+    // --- 上游服务错误 (3xxxx) ---
+    // 上游服务返回非 2xx (4xx 透传，401/403 与 5xx 映射为 502、429 映射为 503，对应 30003)；
+    // 连接失败映射为 502、超时映射为 504
+    #[error("{0}")]
+    Upstream(Box<UpstreamError>),
+    // 下游服务熔断中，快速失败 (对应 30002)
+    #[error("下游服务 '{0}' 暂不可用 (熔断中)")]
+    CircuitOpen(String),
+
     // --- 业务错误 (1xxxx) ---
     // AppError::Service(ServiceError) 就等同于 Java 的 BusinessException(ErrorCode)
     #[error("{0}")] // 让 ServiceError 的 #[error] 消息透传出来
     Service(#[from] ServiceError),
 }
 
+impl From<nacos_sdk::api::error::Error> for AppError {
+    fn from(e: nacos_sdk::api::error::Error) -> Self {
+        AppError::Nacos(Box::new(e))
+    }
+}
+
 /// 实现 IntoResponse trait，让 Axum 知道如何将 AppError 转换为 HTTP 响应
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
@@ -131,6 +250,55 @@ impl IntoResponse for AppError {
                 30001,
                 format!("Nacos SDK 错误: {}", e),
             ),
+            // 没有拿到上游响应：超时 504，连接失败等 502
+            AppError::Upstream(e) if e.transport.is_some() => {
+                let msg = if e.status == StatusCode::GATEWAY_TIMEOUT {
+                    format!("上游服务 '{}' 响应超时", e.service)
+                } else {
+                    format!("无法连接上游服务 '{}'", e.service)
+                };
+                (e.status, 30003, msg)
+            }
+            // 401/403：上游拒绝的是 *本服务* 的凭证 (或透传的 Token)，不能让调用方误以为是自己的 Token 无效；
+            // 429：上游对本服务限流。两者对调用方来说都是上游问题
+            AppError::Upstream(e)
+                if matches!(e.status, StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN) =>
+            {
+                (
+                    StatusCode::BAD_GATEWAY,
+                    30003,
+                    format!("上游服务 '{}' 拒绝了本服务的调用 (HTTP {})", e.service, e.status.as_u16()),
+                )
+            }
+            AppError::Upstream(e) if e.status == StatusCode::TOO_MANY_REQUESTS => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                30003,
+                format!("上游服务 '{}' 繁忙 (限流)，请稍后重试", e.service),
+            ),
+            // 其他 4xx / 2xx + 非 0 code：上游的业务错误 (参数错误、资源不存在、冲突等)，
+            // 透传上游的 code / msg；2xx 的业务失败使用 400。错误响应中 code 为 0 (成功) 时改用 30003
+            AppError::Upstream(e) if e.status.is_client_error() || e.is_business_error() => {
+                let status = if e.status.is_client_error() { e.status } else { StatusCode::BAD_REQUEST };
+                let code = e
+                    .code
+                    .filter(|&c| c != 0)
+                    .and_then(|c| u16::try_from(c).ok())
+                    .unwrap_or(30003);
+                let msg = e.msg.clone().unwrap_or_else(|| e.to_string());
+                (status, code, msg)
+            }
+            // 对应 30003：5xx 等说明上游自身异常，对我们的调用方来说是 502
+            AppError::Upstream(e) => (
+                StatusCode::BAD_GATEWAY,
+                30003,
+                format!("上游服务 '{}' 异常 (HTTP {})", e.service, e.status.as_u16()),
+            ),
+            // 对应 30002
+            AppError::CircuitOpen(service) => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                30002,
+                format!("下游服务 '{}' 暂不可用 (熔断中)，请稍后重试", service),
+            ),
             AppError::InternalError(msg) => (StatusCode::INTERNAL_SERVER_ERROR, 20001, msg),
         };
 
@@ -140,10 +308,115 @@ impl IntoResponse for AppError {
             status_code, business_code, error_message
         );
 
-        // 2. 构建返回给客户端的统一 ApiResponse (data 为 None)
-        let body = Json(ApiResponse::<()>::error(business_code, error_message));
+        // 2. 构建返回给客户端的统一 ApiResponse (data 为 None，带上 request_id 便于排查)
+        let body = Json(
+            ApiResponse::<()>::error(business_code, error_message)
+                .with_request_id(request_context::current_request_id()),
+        );
 
         // 3. 返回最终的 HTTP 响应 (使用 HTTP 状态码 和 JSON body)
         (status_code, body).into_response()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 转换为响应后取出 (HTTP 状态码, 业务码, msg)
+    async fn render(error: AppError) -> (StatusCode, u64, String) {
+        let response = error.into_response();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: Value = serde_json::from_slice(&bytes).unwrap();
+        (
+            status,
+            body["code"].as_u64().unwrap(),
+            body["msg"].as_str().unwrap().to_string(),
+        )
+    }
+
+    fn upstream(status: StatusCode, body: &str) -> AppError {
+        AppError::Upstream(Box::new(UpstreamError::from_body(
+            "upms",
+            "/user/1",
+            status,
+            body.as_bytes(),
+        )))
+    }
+
+    #[test]
+    fn from_body_parses_envelope_and_plain_text() {
+        let e = UpstreamError::from_body("upms", "/x", StatusCode::CONFLICT, br#"{"code":10002,"msg":"exists"}"#);
+        assert_eq!((e.code, e.msg.as_deref()), (Some(10002), Some("exists")));
+
+        let e = UpstreamError::from_body("upms", "/x", StatusCode::NOT_FOUND, br#"{"message":"gone"}"#);
+        assert_eq!((e.code, e.msg.as_deref()), (None, Some("gone")));
+
+        let e = UpstreamError::from_body("upms", "/x", StatusCode::BAD_GATEWAY, b"oops");
+        assert_eq!((e.code, e.msg), (None, None));
+        assert_eq!(e.body, Some(Value::String("oops".to_string())));
+
+        let e = UpstreamError::from_body("upms", "/x", StatusCode::BAD_GATEWAY, b"");
+        assert!(e.body.is_none());
+    }
+
+    #[tokio::test]
+    async fn client_errors_pass_through() {
+        let (status, code, msg) = render(upstream(StatusCode::CONFLICT, r#"{"code":10002,"msg":"exists"}"#)).await;
+        assert_eq!((status, code, msg.as_str()), (StatusCode::CONFLICT, 10002, "exists"));
+
+        // 响应体不是统一结构时使用 30003 和描述信息
+        let (status, code, _) = render(upstream(StatusCode::NOT_FOUND, "not found")).await;
+        assert_eq!((status, code), (StatusCode::NOT_FOUND, 30003));
+    }
+
+    #[tokio::test]
+    async fn upstream_auth_failures_and_throttling_are_not_passed_through() {
+        for status in [StatusCode::UNAUTHORIZED, StatusCode::FORBIDDEN] {
+            let (status, code, _) = render(upstream(status, r#"{"code":10002,"msg":"token invalid"}"#)).await;
+            assert_eq!((status, code), (StatusCode::BAD_GATEWAY, 30003));
+        }
+        let (status, code, _) = render(upstream(StatusCode::TOO_MANY_REQUESTS, "")).await;
+        assert_eq!((status, code), (StatusCode::SERVICE_UNAVAILABLE, 30003));
+    }
+
+    #[tokio::test]
+    async fn success_code_is_never_passed_through_on_errors() {
+        let (status, code, msg) = render(upstream(StatusCode::CONFLICT, r#"{"code":0,"msg":"exists"}"#)).await;
+        assert_eq!((status, code, msg.as_str()), (StatusCode::CONFLICT, 30003, "exists"));
+    }
+
+    #[tokio::test]
+    async fn server_errors_map_to_bad_gateway() {
+        let (status, code, msg) =
+            render(upstream(StatusCode::INTERNAL_SERVER_ERROR, r#"{"code":20001,"msg":"db down"}"#)).await;
+        assert_eq!((status, code), (StatusCode::BAD_GATEWAY, 30003));
+        // 不透传上游的内部错误信息
+        assert!(!msg.contains("db down"));
+    }
+
+    #[tokio::test]
+    async fn business_errors_use_bad_request() {
+        let error = AppError::Upstream(Box::new(UpstreamError::business(
+            "upms",
+            "/user/1",
+            10001,
+            Some("bad".to_string()),
+        )));
+        let (status, code, msg) = render(error).await;
+        assert_eq!((status, code, msg.as_str()), (StatusCode::BAD_REQUEST, 10001, "bad"));
+    }
+
+    #[tokio::test]
+    async fn transport_errors_map_to_bad_gateway_or_gateway_timeout() {
+        let timeout = UpstreamError::transport("upms", "/user/1", true, "operation timed out".to_string());
+        let (status, code, _) = render(AppError::Upstream(Box::new(timeout))).await;
+        assert_eq!((status, code), (StatusCode::GATEWAY_TIMEOUT, 30003));
+
+        let connect = UpstreamError::transport("upms", "/user/1", false, "connection refused".to_string());
+        let (status, code, msg) = render(AppError::Upstream(Box::new(connect))).await;
+        assert_eq!((status, code), (StatusCode::BAD_GATEWAY, 30003));
+        assert!(!msg.contains("connection refused"));
+    }
+}
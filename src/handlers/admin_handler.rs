@@ -0,0 +1,46 @@
+// src/handlers/admin_handler.rs
+// 负责处理 /admin/* 相关的运维/审计 API 请求
+
+use crate::clients::circuit_breaker::CircuitBreakerSnapshot;
+use crate::middleware::permission::{GuardedRoute, GuardedRouterExt, PermissionRegistry, perm};
+use crate::response::ApiResponse; // 导入统一响应结构
+use crate::state::AppState;
+use axum::{Json, Router, extract::State, http::Method};
+
+/// 定义 /admin 相关的路由
+///
+/// # Arguments
+/// * `guards` - 审计清单作用域 (已带 `/admin` 前缀)
+pub fn routes(guards: &PermissionRegistry) -> Router<AppState> {
+    Router::<AppState>::new()
+        // 列出所有路由的权限要求 (审计)
+        .guarded_route(
+            guards,
+            Method::GET,
+            "/permissions",
+            list_guarded_routes_handler,
+            perm("sys_admin_audit"),
+        )
+        // 查看下游服务熔断器状态
+        .guarded_route(
+            guards,
+            Method::GET,
+            "/circuit-breakers",
+            list_circuit_breakers_handler,
+            perm("sys_admin_audit"),
+        )
+}
+
+/// GET /permissions 的处理器：列出所有通过 `guarded_route` 声明的路由权限
+async fn list_guarded_routes_handler(
+    State(state): State<AppState>,
+) -> Json<ApiResponse<Vec<GuardedRoute>>> {
+    Json(ApiResponse::success(state.permissions.routes()))
+}
+
+/// GET /circuit-breakers 的处理器：列出所有下游服务熔断器的当前状态
+async fn list_circuit_breakers_handler(
+    State(state): State<AppState>,
+) -> Json<ApiResponse<Vec<CircuitBreakerSnapshot>>> {
+    Json(ApiResponse::success(state.circuit_breakers.snapshot()))
+}
@@ -0,0 +1,39 @@
+// src/handlers/auth_handler.rs
+// 负责处理 /auth/* 相关的 API 请求
+
+use crate::errors::AppError;
+use crate::middleware::auth::{CurrentUser, extract_token};
+use crate::response::ApiResponse; // 导入统一响应结构
+use crate::services::token_cache_service;
+use crate::state::AppState;
+use axum::{
+    Extension, Json, Router,
+    extract::State,
+    http::HeaderMap,
+    routing::post,
+};
+use std::sync::Arc;
+use tracing::info;
+
+/// 定义 /auth 相关的路由
+pub fn routes() -> Router<AppState> {
+    Router::<AppState>::new()
+        // 登出：将当前 Token 从本地认证缓存中移除
+        .route("/logout", post(logout_handler))
+}
+
+/// POST /logout 的处理器
+///
+/// 只负责清理本服务的 Token 缓存 (Token 本身的吊销仍由 Auth 服务负责)，
+/// 避免登出后缓存中的认证结果在 TTL 内继续生效。
+async fn logout_handler(
+    State(state): State<AppState>,
+    Extension(user): Extension<Arc<CurrentUser>>,
+    headers: HeaderMap,
+) -> Result<Json<ApiResponse<()>>, AppError> {
+    let token = extract_token(&headers)?;
+    token_cache_service::evict(&state, &token).await?;
+
+    info!("Handler: 用户 {} (ID: {}) 已登出，Token 缓存已清理", user.username, user.id);
+    Ok(Json(ApiResponse::success(())))
+}
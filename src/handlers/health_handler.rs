@@ -1,8 +1,13 @@
 // src/handlers/health_handler.rs
-// 提供一个简单的健康检查端点。
+// 健康检查端点：
+// - GET /              简单的存活检查 (保持兼容)
+// - GET /health/live   liveness，不检查依赖
+// - GET /health/ready  readiness，检查 db / redis / nacos 等已注册组件，任一必需组件 DOWN 时返回 503
 
+use crate::health::{HealthReport, HealthStatus};
 use crate::response::ApiResponse; // <-- 导入 ApiResponse
-use axum::Json;
+use crate::state::AppState;
+use axum::{Json, Router, extract::State, http::StatusCode, routing::get};
 use serde::Serialize;
 
 // --- 新增：健康检查的响应体 ---
@@ -11,6 +16,13 @@ pub struct HealthCheckResponse {
     status: String,
 }
 
+/// 路由：/health/live、/health/ready
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/live", get(liveness))
+        .route("/ready", get(readiness))
+}
+
 /// 一个简单的健康检查处理器
 pub async fn health_check() -> Json<ApiResponse<HealthCheckResponse>> {
     let response = HealthCheckResponse {
@@ -19,3 +31,23 @@ pub async fn health_check() -> Json<ApiResponse<HealthCheckResponse>> {
     // 使用 ApiResponse::success 包装
     Json(ApiResponse::success(response))
 }
+
+/// liveness：进程能响应即为 UP
+///
+/// 探针只看状态码，响应体使用 Spring Boot Actuator 风格 (不包装 ApiResponse)。
+pub async fn liveness() -> Json<HealthReport> {
+    Json(HealthReport {
+        status: HealthStatus::Up,
+        components: Default::default(),
+    })
+}
+
+/// readiness：返回每个组件的状态与耗时，整体 DOWN 时返回 503
+pub async fn readiness(State(state): State<AppState>) -> (StatusCode, Json<HealthReport>) {
+    let report = state.health.check_all(&state).await;
+    let status = match report.status {
+        HealthStatus::Up => StatusCode::OK,
+        HealthStatus::Down => StatusCode::SERVICE_UNAVAILABLE,
+    };
+    (status, Json(report))
+}
@@ -7,31 +7,96 @@ use crate::state::AppState;
 use axum::{
     Json, Router,
     extract::{Path, State},
-    routing::{get, post},
+    http::Method,
 };
-use serde::{Deserialize};
 use tracing::info;
 
 use crate::middleware::auth::CurrentUser;
-use crate::response::ApiResponse; // 导入统一响应结构
+use crate::models::kms_app_access;
+use crate::response::{ApiResponse, PageResult}; // 导入统一响应结构
 use axum::Extension;
 use std::sync::Arc;
 
-use crate::middleware::auth::check_permission;
+use crate::middleware::permission::{GuardedRouterExt, PermissionRegistry, any_of, perm};
 
-// --- 核心修改点 (1)：导入 `ValidatedJson` 和 `Validate` ---
+// --- 核心修改点 (1)：导入 `ValidatedJson` 和请求 DTO ---
+use crate::dto::kms_app_access_dto::{
+    AppAccessListQuery, AppAccessWithKeyResponse, CreateAppAccessRequest, PatchAppAccessRequest,
+    RotateAppAccessKeyRequest, UpdateAppAccessRequest,
+};
+use crate::utils::pagination::PageQuery;
 use crate::utils::validated_json::ValidatedJson;
-use validator::Validate; // 导入 `Validate` trait 以使用 `#[derive(Validate)]`
+use crate::utils::validated_query::ValidatedQuery;
 
 
 /// 定义 /app-access 相关的路由
-/// 这个函数返回一个 Router<AppState>，它会被 main.rs 中的主 Router `nest` (嵌套) 进去
-pub fn routes() -> Router<AppState> {
+/// 这个函数返回一个 Router<AppState>，它会被 router.rs 中的主 Router `nest` (嵌套) 进去
+///
+/// # Arguments
+/// * `guards` - 审计清单作用域 (已带 `/app-access` 前缀)
+pub fn routes(guards: &PermissionRegistry) -> Router<AppState> {
+    // 每个路由通过 `guarded_route` 声明所需权限，守卫在 Handler 之前执行，并登记到审计清单
+    // 修改 / 删除类接口同时接受 `_own` 权限，是否为本人创建的记录由 Service 层检查
     Router::<AppState>::new()
-        // 映射 GET /:id 到 get_app_access_handler
-        .route("/{id}", get(get_app_access_handler))
-        .route("/", post(create_app_access_handler))
-    // 你可以在这里添加 POST, PUT, DELETE 等路由
+        .guarded_route(
+            guards,
+            Method::GET,
+            "/",
+            list_app_access_handler,
+            perm("kms_kmsAppAccess_view"),
+        )
+        .guarded_route(
+            guards,
+            Method::POST,
+            "/",
+            create_app_access_handler,
+            perm("kms_kmsAppAccess_add"),
+        )
+        // 映射 GET/PUT/PATCH/DELETE /{id}
+        .guarded_route(
+            guards,
+            Method::GET,
+            "/{id}",
+            get_app_access_handler,
+            perm("kms_kmsAppAccess_view"),
+        )
+        .guarded_route(
+            guards,
+            Method::PUT,
+            "/{id}",
+            update_app_access_handler,
+            any_of(["kms_kmsAppAccess_edit", "kms_kmsAppAccess_edit_own"]),
+        )
+        .guarded_route(
+            guards,
+            Method::PATCH,
+            "/{id}",
+            patch_app_access_handler,
+            any_of(["kms_kmsAppAccess_edit", "kms_kmsAppAccess_edit_own"]),
+        )
+        .guarded_route(
+            guards,
+            Method::DELETE,
+            "/{id}",
+            delete_app_access_handler,
+            any_of(["kms_kmsAppAccess_del", "kms_kmsAppAccess_del_own"]),
+        )
+        // 恢复已被逻辑删除的记录
+        .guarded_route(
+            guards,
+            Method::POST,
+            "/{id}/restore",
+            restore_app_access_handler,
+            any_of(["kms_kmsAppAccess_restore", "kms_kmsAppAccess_restore_own"]),
+        )
+        // 轮换访问密钥
+        .guarded_route(
+            guards,
+            Method::POST,
+            "/{id}/rotate",
+            rotate_app_access_key_handler,
+            any_of(["kms_kmsAppAccess_edit", "kms_kmsAppAccess_edit_own"]),
+        )
 }
 
 /// GET /:id 的处理器
@@ -43,9 +108,9 @@ async fn get_app_access_handler(
     State(state): State<AppState>,
     Path(id): Path<i64>,
     Extension(user): Extension<Arc<CurrentUser>>,
-) -> Result<Json<ApiResponse<crate::models::kms_app_access::Model>>, AppError> {
+) -> Result<Json<ApiResponse<kms_app_access::Model>>, AppError> {
 
-    check_permission(&user, "kms_kmsAppAccess_view")?; // 👈 检查权限
+    // 权限已由路由上的守卫检查 (kms_kmsAppAccess_view)
     // 你现在可以直接使用 `user` 了！
     info!(
         "Handler: 用户 {} (ID: {}) 正在访问 AppAccess ID: {}",
@@ -59,49 +124,110 @@ async fn get_app_access_handler(
     Ok(Json(ApiResponse::success(app_access)))
 }
 
-#[derive(Deserialize, Validate)] // <-- 2. 添加 `Validate`
-#[allow(dead_code)]
-struct CreateAppAccessRequest {
-    // 3. 添加验证注解 (属性)
-    // 类似于 Java 的 @NotNull 和 @Size(min=1)
-    #[validate(length(min = 1, message = "应用名称(name)不能为空"))]
-    name: String,
-
-    // 4. (示例) 添加其他验证
-    // 假设 description 最大长度为 2048
-    #[validate(length(max = 50, message = "描述(description)长度不能超过 50"))]
-    description: Option<String>,
+/// GET / 的处理器 (分页 + 过滤)
+///
+/// 例如: `GET /app-access?page=1&size=20&sort=-create_time&name=order&name_match=prefix`
+async fn list_app_access_handler(
+    State(state): State<AppState>,
+    Extension(user): Extension<Arc<CurrentUser>>,
+    // 分页参数与过滤条件分成两个提取器，两者都从同一个 query string 中解析
+    ValidatedQuery(page): ValidatedQuery<PageQuery>,
+    ValidatedQuery(filter): ValidatedQuery<AppAccessListQuery>,
+) -> Result<Json<ApiResponse<PageResult<kms_app_access::Model>>>, AppError> {
+    info!(
+        "Handler: 用户 {} 正在查询 AppAccess 列表, page: {:?}, filter: {:?}",
+        user.username, page, filter
+    );
+
+    let result = kms_app_access_service::list_app_accesses(&state, &filter, &page).await?;
+    Ok(Json(ApiResponse::success(result)))
 }
 
 /// POST / 的处理器
 async fn create_app_access_handler(
-    State(_state): State<AppState>, 
+    State(state): State<AppState>,
     Extension(user): Extension<Arc<CurrentUser>>,
     // --- 核心修改点 (3)：使用 `ValidatedJson` 替代 `Json` ---
     ValidatedJson(payload): ValidatedJson<CreateAppAccessRequest>, // 👈 使用我们自定义的提取器
-) -> Result<Json<ApiResponse<()>>, AppError> { // <-- 修改返回类型为 ()
-    
-    // --- 核心修改点 (4)：权限检查移到这里 ---
-    check_permission(&user, "kms_kmsAppAccess_add")?;
+) -> Result<Json<ApiResponse<AppAccessWithKeyResponse>>, AppError> {
 
     // ---
     // 如果代码能执行到这里，说明：
     // 1. 用户已认证 (mw_require_auth)
-    // 2. 权限已检查 (check_permission)
+    // 2. 权限已检查 (路由守卫 kms_kmsAppAccess_add)
     // 3. JSON body 已被成功反序列化
     // 4. JSON body 已通过了 *所有* `#[validate]` 规则
     // ---
-    
+
     info!("Handler: 用户 {} 正在创建 AppAccess... 名称: {}", user.username, payload.name);
-    
-    // --- TODO ---
-    // 1. 调用 services::kms_app_access_service::create_app_access(&state, payload)
-    // 2. service 会将 DTO 转换为 ActiveModel 并调用 repository
-    // --- 
-    
-    // 暂时返回一个模拟的错误，表示“未实现”
-    // Err(AppError::InternalError("创建功能尚未实现".to_string()))
-    
-    // 演示成功（返回一个空的 data 和 200 OK）
+
+    // service 会将 DTO 转换为 ActiveModel 并调用 repository
+    let created = kms_app_access_service::create_app_access(&state, &user, payload).await?;
+
+    Ok(Json(ApiResponse::success(created)))
+}
+
+/// PUT /{id} 的处理器 (全量更新)
+async fn update_app_access_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Extension(user): Extension<Arc<CurrentUser>>,
+    ValidatedJson(payload): ValidatedJson<UpdateAppAccessRequest>,
+) -> Result<Json<ApiResponse<kms_app_access::Model>>, AppError> {
+    info!("Handler: 用户 {} 正在更新 AppAccess ID: {}", user.username, id);
+
+    let updated = kms_app_access_service::update_app_access(&state, &user, id, payload).await?;
+    Ok(Json(ApiResponse::success(updated)))
+}
+
+/// PATCH /{id} 的处理器 (部分更新)
+async fn patch_app_access_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Extension(user): Extension<Arc<CurrentUser>>,
+    ValidatedJson(payload): ValidatedJson<PatchAppAccessRequest>,
+) -> Result<Json<ApiResponse<kms_app_access::Model>>, AppError> {
+    info!("Handler: 用户 {} 正在部分更新 AppAccess ID: {}", user.username, id);
+
+    let updated = kms_app_access_service::patch_app_access(&state, &user, id, payload).await?;
+    Ok(Json(ApiResponse::success(updated)))
+}
+
+/// DELETE /{id} 的处理器 (逻辑删除)
+async fn delete_app_access_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Extension(user): Extension<Arc<CurrentUser>>,
+) -> Result<Json<ApiResponse<()>>, AppError> {
+    info!("Handler: 用户 {} 正在删除 AppAccess ID: {}", user.username, id);
+
+    kms_app_access_service::delete_app_access(&state, &user, id).await?;
     Ok(Json(ApiResponse::success(())))
-}
\ No newline at end of file
+}
+
+/// POST /{id}/restore 的处理器 (恢复逻辑删除)
+async fn restore_app_access_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Extension(user): Extension<Arc<CurrentUser>>,
+) -> Result<Json<ApiResponse<kms_app_access::Model>>, AppError> {
+    info!("Handler: 用户 {} 正在恢复 AppAccess ID: {}", user.username, id);
+
+    let restored = kms_app_access_service::restore_app_access(&state, &user, id).await?;
+    Ok(Json(ApiResponse::success(restored)))
+}
+
+/// POST /{id}/rotate 的处理器 (轮换访问密钥)
+///
+/// 请求体示例: `{"grace_period_secs": 3600}`，不需要宽限期时传 `{}`
+async fn rotate_app_access_key_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Extension(user): Extension<Arc<CurrentUser>>,
+    ValidatedJson(payload): ValidatedJson<RotateAppAccessKeyRequest>,
+) -> Result<Json<ApiResponse<AppAccessWithKeyResponse>>, AppError> {
+    info!("Handler: 用户 {} 正在轮换 AppAccess ID: {} 的访问密钥", user.username, id);
+
+    let rotated = kms_app_access_service::rotate_app_access_key(&state, &user, id, payload).await?;
+    Ok(Json(ApiResponse::success(rotated)))
+}
@@ -0,0 +1,20 @@
+// src/handlers/metrics_handler.rs
+// Prometheus 指标端点 (GET /metrics)
+//
+// 只在独立的管理端口 (METRICS_ADDR，默认 0.0.0.0:9100) 上提供，不挂在业务端口上。
+
+use crate::state::AppState;
+use axum::{Router, extract::State, http::header, response::IntoResponse, routing::get};
+
+/// 路由：GET /metrics
+pub fn routes() -> Router<AppState> {
+    Router::new().route("/metrics", get(metrics))
+}
+
+/// 输出 Prometheus 文本格式 (不使用 ApiResponse 包装)
+pub async fn metrics(State(state): State<AppState>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, prometheus::TEXT_FORMAT)],
+        state.metrics.render(&state),
+    )
+}
@@ -11,6 +11,15 @@ pub mod kms_app_access_handler;
 
 pub mod redis_handler;
 
+// 认证相关 (登出清理 Token 缓存)
+pub mod auth_handler;
+
+// 运维/审计接口
+pub mod admin_handler;
+
+// Prometheus 指标 (GET /metrics)
+pub mod metrics_handler;
+
 
 // 为了方便 main.rs 调用，我们在这里重导出 health_check 函数
 pub use health_handler::health_check;
@@ -0,0 +1,321 @@
+// src/health.rs
+// 健康检查 (GET /health/live、GET /health/ready)
+//
+// - liveness：进程能处理请求即为 UP，不检查依赖 (依赖故障时不应让 K8s 重启 Pod)
+// - readiness：并发执行所有已注册的组件检查 (每项带超时)，任一 *必需* 组件 DOWN 时整体 DOWN (503)
+//
+// 内置 db / redis / nacos 三项检查；新的依赖实现 `HealthCheck` 后通过 `HealthRegistry::register` 注册即可。
+// 优雅停机开始后 readiness 直接返回 DOWN (`mark_shutting_down`)，让负载均衡尽快摘除本实例。
+
+use crate::state::AppState;
+use futures_core::future::BoxFuture;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tokio::task::{Id, JoinSet};
+use tracing::warn;
+
+/// 单项检查的默认超时
+const DEFAULT_CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// 组件健康检查
+pub trait HealthCheck: Send + Sync {
+    /// 组件名 (出现在响应的 components 中)
+    fn name(&self) -> &str;
+
+    /// 是否为必需组件 (必需组件 DOWN 时 readiness 返回 503)；可被 Nacos 配置 `health.optional` 覆盖
+    fn required(&self) -> bool {
+        true
+    }
+
+    /// 执行检查，失败时返回原因 (只写入日志，响应中只显示“检查失败”)
+    fn check<'a>(&'a self, state: &'a AppState) -> BoxFuture<'a, Result<(), String>>;
+}
+
+/// 组件状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum HealthStatus {
+    #[serde(rename = "UP")]
+    Up,
+    #[serde(rename = "DOWN")]
+    Down,
+}
+
+/// 单个组件的检查结果
+#[derive(Debug, Serialize)]
+pub struct ComponentHealth {
+    pub status: HealthStatus,
+    pub required: bool,
+    pub latency_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// readiness 检查结果
+#[derive(Debug, Serialize)]
+pub struct HealthReport {
+    pub status: HealthStatus,
+    pub components: BTreeMap<String, ComponentHealth>,
+}
+
+/// 已注册的健康检查 (存放在 AppState 中)
+pub struct HealthRegistry {
+    checks: RwLock<Vec<Arc<dyn HealthCheck>>>,
+    // 优雅停机已开始
+    shutting_down: AtomicBool,
+}
+
+impl Default for HealthRegistry {
+    /// 包含内置的 db / redis / nacos 检查
+    fn default() -> Self {
+        let registry = HealthRegistry {
+            checks: RwLock::new(Vec::new()),
+            shutting_down: AtomicBool::new(false),
+        };
+        registry.register(DbHealthCheck);
+        registry.register(RedisHealthCheck);
+        registry.register(NacosHealthCheck);
+        registry
+    }
+}
+
+impl HealthRegistry {
+    /// 注册一项检查 (同名检查会被替换)
+    pub fn register(&self, check: impl HealthCheck + 'static) {
+        let mut checks = self.checks.write().unwrap_or_else(|e| e.into_inner());
+        checks.retain(|existing| existing.name() != check.name());
+        checks.push(Arc::new(check));
+    }
+
+    /// 移除一项检查 (如离线模式下的 nacos)
+    pub fn unregister(&self, name: &str) {
+        let mut checks = self.checks.write().unwrap_or_else(|e| e.into_inner());
+        checks.retain(|existing| existing.name() != name);
+    }
+
+    /// 标记优雅停机已开始，之后 readiness 总是返回 DOWN
+    pub fn mark_shutting_down(&self) {
+        self.shutting_down.store(true, Ordering::Relaxed);
+    }
+
+    /// 并发执行所有检查 (停机中不再检查，直接返回 DOWN)
+    pub async fn check_all(&self, state: &AppState) -> HealthReport {
+        if self.shutting_down.load(Ordering::Relaxed) {
+            let component = ComponentHealth {
+                status: HealthStatus::Down,
+                required: true,
+                latency_ms: 0,
+                error: Some("服务正在停机".to_string()),
+            };
+            return HealthReport {
+                status: HealthStatus::Down,
+                components: BTreeMap::from([("shutdown".to_string(), component)]),
+            };
+        }
+
+        let checks = self.checks.read().unwrap_or_else(|e| e.into_inner()).clone();
+        let (timeout, optional) = {
+            let config = state.app_config.read().await;
+            let health = config.health.as_ref();
+            (
+                health
+                    .and_then(|h| h.timeout_ms)
+                    .map(Duration::from_millis)
+                    .unwrap_or(DEFAULT_CHECK_TIMEOUT),
+                health
+                    .map(|h| h.optional.iter().cloned().collect::<HashSet<_>>())
+                    .unwrap_or_default(),
+            )
+        };
+
+        // 任务 panic 时 JoinError 不带返回值，按任务 ID 记下组件名，保证每个组件都有结果
+        let mut tasks = JoinSet::new();
+        let mut pending = HashMap::new();
+        for check in checks {
+            let state = state.clone();
+            let name = check.name().to_string();
+            let required = check.required() && !optional.contains(&name);
+            let handle = tasks.spawn(async move {
+                let started = Instant::now();
+                let result = match tokio::time::timeout(timeout, check.check(&state)).await {
+                    Ok(result) => result.map_err(CheckFailure::Failed),
+                    Err(_) => Err(CheckFailure::TimedOut(timeout)),
+                };
+                (result, started.elapsed())
+            });
+            pending.insert(handle.id(), (name, required));
+        }
+        let components = collect_components(tasks, pending).await;
+
+        let down = components
+            .values()
+            .any(|c| c.required && c.status == HealthStatus::Down);
+        HealthReport {
+            status: if down { HealthStatus::Down } else { HealthStatus::Up },
+            components,
+        }
+    }
+}
+
+/// 一项检查失败的原因 (详细信息只写日志，不出现在公开的 /health/ready 响应中)
+enum CheckFailure {
+    Failed(String),
+    TimedOut(Duration),
+}
+
+/// 一个检查任务的输出：检查结果与耗时
+type CheckOutcome = (Result<(), CheckFailure>, Duration);
+
+/// 辅助函数：收集所有检查任务的结果
+///
+/// 任务 panic (或被取消) 时同样记为 DOWN，不会因为少了这个组件而让 readiness 误报 UP。
+async fn collect_components(
+    mut tasks: JoinSet<CheckOutcome>,
+    mut pending: HashMap<Id, (String, bool)>,
+) -> BTreeMap<String, ComponentHealth> {
+    let mut components = BTreeMap::new();
+    while let Some(joined) = tasks.join_next_with_id().await {
+        let (id, result, latency) = match joined {
+            Ok((id, (result, latency))) => (id, result, latency),
+            Err(e) => {
+                warn!("(Health) 检查任务异常退出: {}", e);
+                (e.id(), Err(CheckFailure::Failed(e.to_string())), Duration::ZERO)
+            }
+        };
+        let Some((name, required)) = pending.remove(&id) else {
+            continue;
+        };
+        let error = match result {
+            Ok(()) => None,
+            Err(CheckFailure::Failed(detail)) => {
+                warn!("(Health) 组件 {} 检查失败: {}", name, detail);
+                Some("检查失败".to_string())
+            }
+            Err(CheckFailure::TimedOut(timeout)) => {
+                warn!("(Health) 组件 {} 检查超时 ({:?})", name, timeout);
+                Some("检查超时".to_string())
+            }
+        };
+        let component = ComponentHealth {
+            status: if error.is_none() { HealthStatus::Up } else { HealthStatus::Down },
+            required,
+            latency_ms: latency.as_millis() as u64,
+            error,
+        };
+        components.insert(name, component);
+    }
+    components
+}
+
+// --- 内置检查 ---
+
+/// MySQL：执行一次 ping
+struct DbHealthCheck;
+
+impl HealthCheck for DbHealthCheck {
+    fn name(&self) -> &str {
+        "db"
+    }
+
+    fn check<'a>(&'a self, state: &'a AppState) -> BoxFuture<'a, Result<(), String>> {
+        Box::pin(async move { state.db_pool.ping().await.map_err(|e| e.to_string()) })
+    }
+}
+
+/// Redis：从连接池取连接并 PING
+struct RedisHealthCheck;
+
+impl HealthCheck for RedisHealthCheck {
+    fn name(&self) -> &str {
+        "redis"
+    }
+
+    fn check<'a>(&'a self, state: &'a AppState) -> BoxFuture<'a, Result<(), String>> {
+        Box::pin(async move {
+            let mut conn = state.redis_pool.get().await.map_err(|e| e.to_string())?;
+            let _: String = redis::cmd("PING")
+                .query_async(&mut *conn)
+                .await
+                .map_err(|e| e.to_string())?;
+            Ok(())
+        })
+    }
+}
+
+/// Nacos：通过 naming 客户端向服务端查询一次服务列表
+struct NacosHealthCheck;
+
+impl HealthCheck for NacosHealthCheck {
+    fn name(&self) -> &str {
+        "nacos"
+    }
+
+    fn check<'a>(&'a self, state: &'a AppState) -> BoxFuture<'a, Result<(), String>> {
+        Box::pin(async move {
+            let naming_client = state.naming_client.as_ref().ok_or("离线模式下没有 Nacos 客户端")?;
+            naming_client
+                .get_service_list(1, 1, None)
+                .await
+                .map(|_| ())
+                .map_err(|e| e.to_string())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Check = BoxFuture<'static, Result<(), CheckFailure>>;
+
+    /// 按 (组件名, 是否必需, 检查) 启动检查任务并收集结果
+    async fn collect(checks: Vec<(&str, bool, Check)>) -> BTreeMap<String, ComponentHealth> {
+        let mut tasks = JoinSet::new();
+        let mut pending = HashMap::new();
+        for (name, required, check) in checks {
+            let handle = tasks.spawn(async move { (check.await, Duration::ZERO) });
+            pending.insert(handle.id(), (name.to_string(), required));
+        }
+        collect_components(tasks, pending).await
+    }
+
+    #[tokio::test]
+    async fn panicking_check_is_reported_down() {
+        let components = collect(vec![
+            ("ok", true, Box::pin(async { Ok(()) })),
+            ("boom", true, Box::pin(async { panic!("check panicked") })),
+        ])
+        .await;
+
+        assert_eq!(components["ok"].status, HealthStatus::Up);
+        let boom = &components["boom"];
+        assert_eq!(boom.status, HealthStatus::Down);
+        assert!(boom.required);
+        assert_eq!(boom.error.as_deref(), Some("检查失败"));
+    }
+
+    #[tokio::test]
+    async fn failure_details_are_not_exposed() {
+        let components = collect(vec![
+            (
+                "db",
+                true,
+                Box::pin(async {
+                    Err(CheckFailure::Failed("Access denied for user 'root'@'10.0.0.1'".to_string()))
+                }),
+            ),
+            (
+                "redis",
+                false,
+                Box::pin(async { Err(CheckFailure::TimedOut(Duration::from_secs(2))) }),
+            ),
+        ])
+        .await;
+
+        assert_eq!(components["db"].error.as_deref(), Some("检查失败"));
+        assert_eq!(components["redis"].error.as_deref(), Some("检查超时"));
+        assert!(!components["redis"].required);
+    }
+}
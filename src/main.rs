@@ -4,6 +4,7 @@
 
 // 声明我们项目中的其他模块
 mod config; // <-- 这里声明顶层 config 模块
+mod dto;
 mod errors;
 mod handlers;
 mod middleware;
@@ -14,16 +15,22 @@ mod state;
 mod setup;
 mod router; // <-- 声明 router 模块
 mod response;
+mod metrics;
+mod health;
 mod clients;
 mod utils;
+#[cfg(test)]
+mod test_support;
 
 
 
 // 更新 use 语句以指向新的模块路径
 // 只需要导入最核心的 Config 和 setup, router 模块
 use crate::config::Config;
+use crate::setup::config_source::ConfigSource;
+use std::sync::Arc;
+use std::time::Duration;
 use tracing::{error, info};
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 // --- 修改点：导入 Tokio 信号处理 ---
 use tokio::signal;
 // --- 新增：导入 Unix 信号处理 (用于 SIGTERM) ---
@@ -35,25 +42,38 @@ use cfg_if::cfg_if;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    // 1. 初始化日志
-    tracing_subscriber::registry()
-        .with(tracing_subscriber::EnvFilter::new(
-            std::env::var("RUST_LOG")
-                .unwrap_or_else(|_| "info,axum_template=debug,nacos_sdk=info".into()),
-        ))
-        .with(tracing_subscriber::fmt::layer())
-        .init();
+    // 1. 初始化日志与链路追踪 (LOG_FORMAT=json 输出 JSON；级别与 OTLP 导出之后由 Nacos 配置动态调整)
+    let (log_level, telemetry) = setup::init_logging();
+    let (log_level, telemetry) = (Arc::new(log_level), Arc::new(telemetry));
 
     // 2. 加载基础配置 (来自 config/mod.rs)
     let config = Config::from_env()?;
 
     // 3. 初始化应用状态 (Nacos, 数据库, 配置监听)
     info!("正在初始化应用状态 (Nacos, 数据库, 配置...)");
-    let app_state = setup::setup_application_state(&config).await?;
+    let (app_state, config_source) =
+        setup::setup_application_state(&config, log_level, telemetry.clone()).await?;
     info!("应用状态初始化完成");
 
-    // 4. 注册服务实例到 Nacos
-    setup::register_nacos_instance(&config, &app_state.naming_client).await?;
+    // 4. 注册服务实例到 Nacos (离线模式下没有 Nacos 客户端)
+    match (config_source, app_state.naming_client.clone()) {
+        (ConfigSource::Nacos, Some(naming_client)) => {
+            setup::startup::retry(&config, "Nacos 服务注册", || {
+                setup::register_nacos_instance(&config, &naming_client)
+            })
+            .await?;
+        }
+        // 以本地快照启动时 Nacos 不可用，注册在后台等待 Nacos 恢复
+        (ConfigSource::Snapshot, Some(naming_client)) => {
+            let register_config = config.clone();
+            tokio::spawn(async move {
+                if let Err(e) = setup::register_nacos_instance(&register_config, &naming_client).await {
+                    error!("注册服务实例到 Nacos 失败: {}", e);
+                }
+            });
+        }
+        _ => info!("离线模式：跳过 Nacos 服务注册"),
+    }
 
     // 5. --- 修改点 ---
     // 创建 Axum 路由 (这是一个同步操作，移除 .await)
@@ -61,7 +81,9 @@ async fn main() -> anyhow::Result<()> {
     info!("Axum 路由组装完成");
 
     // 6. --- 修改点：定义更健壮的优雅停机信号 ---
-    let shutdown_naming_client = app_state.naming_client.clone();
+    // 停机顺序：readiness 置为 DOWN -> 从 Nacos 注销 -> 等待传播 -> 停止接收新连接并排空请求 (带时限)
+    // -> 关闭 DB / Redis 连接池 -> 导出剩余链路数据
+    let shutdown_state = app_state.clone();
     let shutdown_config = config.clone(); // Config 必须 derive(Clone)
 
     //核心修改点：使用 cfg_if! 宏 ---
@@ -91,23 +113,55 @@ async fn main() -> anyhow::Result<()> {
             }
         }
 
-        // --- 停机逻辑保持不变 ---
-
-        // 1. 主动从 Nacos 注销服务
-        info!("正在从 Nacos 注销服务...");
-        if let Err(e) = setup::deregister_nacos_instance(&shutdown_config, &shutdown_naming_client).await {
-            error!("从 Nacos 注销服务失败: {}", e);
-        } else {
-            info!("已成功从 Nacos 注销");
+        // 1. readiness 置为 DOWN，让 K8s / 负载均衡停止转发新流量
+        shutdown_state.health.mark_shutting_down();
+
+        // 2. 主动从 Nacos 注销服务 (离线模式下没有注册)
+        if let Some(naming_client) = &shutdown_state.naming_client {
+            info!("正在从 Nacos 注销服务...");
+            // Nacos 不可达时 SDK 的请求会一直等待，加上超时避免卡住停机流程
+            let deregister = setup::deregister_nacos_instance(&shutdown_config, naming_client);
+            match tokio::time::timeout(Duration::from_secs(5), deregister).await {
+                Ok(Ok(())) => info!("已成功从 Nacos 注销"),
+                Ok(Err(e)) => error!("从 Nacos 注销服务失败: {}", e),
+                Err(_) => error!("从 Nacos 注销服务超时"),
+            }
         }
-        
-        info!("Axum 服务正在关闭...");
+
+        // 3. 等待注销传播到调用方 (期间仍正常处理请求)
+        let (propagation_delay, drain_timeout) = setup::shutdown_timings(&shutdown_state).await;
+        info!("等待 {:?} 让调用方刷新实例列表...", propagation_delay);
+        tokio::time::sleep(propagation_delay).await;
+
+        // 4. 停止接收新连接，最多等待 drain_timeout 让进行中的请求完成
+        info!("Axum 服务正在关闭，最多等待 {:?} 处理完进行中的请求...", drain_timeout);
+        drain_timeout
     };
 
 
+    // 在独立的管理端口 (METRICS_ADDR，默认 0.0.0.0:9100) 上提供 /metrics，业务端口不暴露
+    {
+        let metrics_addr = config.metrics_addr.clone();
+        let admin_app = router::create_admin_router(app_state.clone());
+        tokio::spawn(async move {
+            if let Err(e) = setup::run_admin_server(admin_app, &metrics_addr).await {
+                error!("管理端口 {} 启动失败: {}", metrics_addr, e);
+            }
+        });
+    }
+
     // 6. 启动服务器 (逻辑仍在 setup 模块)
     info!("服务器即将启动在: {}", &config.server_addr);
-    setup::run_server(app, &config.server_addr, shutdown_signal).await?;
+    let served = setup::run_server(app, &config.server_addr, shutdown_signal).await;
+
+    // 5. 关闭连接池 (服务器异常退出时同样执行)
+    setup::close_pools(&app_state, matches!(served, Ok(true))).await;
+    drop(app_state);
+
+    // 6. 导出剩余的链路数据 (服务器异常退出时也要导出，便于排查)
+    telemetry.shutdown();
+    served?;
+    info!("优雅停机完成");
 
     Ok(())
 }
@@ -0,0 +1,221 @@
+// src/metrics.rs
+// Prometheus 指标 (GET /metrics，文本格式)
+//
+// - HTTP 入站请求：按 method / route (路由模板) / status 计数与耗时直方图 (middleware/metrics.rs)
+// - 出站调用 (service_client)：按 service / method / outcome 计数与耗时直方图，每次尝试 (含重试) 记一次
+// - 连接池：DB (sqlx) 与 Redis (bb8) 的连接数、空闲数、最大连接数，抓取时采样；
+//   Redis 另有累计的等待 / 超时次数与等待时长 (counter)。sqlx 与 bb8 都不暴露当前排队等待的数量，
+//   因此没有 waiters 指标，以 `in_use == max` 判断连接池已饱和
+// - Nacos 配置热更新次数 (按结果)
+// - 进程指标 (CPU、内存、文件句柄，仅 Linux)
+
+use crate::setup::redis::REDIS_POOL_MAX_SIZE;
+use crate::state::AppState;
+use prometheus::{
+    CounterVec, Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGaugeVec, Opts, Registry,
+    TextEncoder,
+};
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::error;
+
+/// 未匹配到路由时使用的 route 标签 (避免把任意 URI 当作标签值)
+pub const UNMATCHED_ROUTE: &str = "unmatched";
+
+/// 非标准 HTTP 方法使用的 method 标签 (避免把任意方法名当作标签值)
+pub const OTHER_METHOD: &str = "OTHER";
+
+/// 出站调用的结果分类
+#[derive(Debug, Clone, Copy)]
+pub enum OutboundOutcome {
+    Success,
+    ClientError,
+    ServerError,
+    TransportError,
+    CircuitOpen,
+}
+
+impl OutboundOutcome {
+    fn as_str(self) -> &'static str {
+        match self {
+            OutboundOutcome::Success => "success",
+            OutboundOutcome::ClientError => "client_error",
+            OutboundOutcome::ServerError => "server_error",
+            OutboundOutcome::TransportError => "transport_error",
+            OutboundOutcome::CircuitOpen => "circuit_open",
+        }
+    }
+}
+
+/// 所有指标 (存放在 AppState 中)
+pub struct Metrics {
+    registry: Registry,
+    http_requests_total: IntCounterVec,
+    http_request_duration_seconds: HistogramVec,
+    outbound_requests_total: IntCounterVec,
+    outbound_request_duration_seconds: HistogramVec,
+    pool_connections: IntGaugeVec,
+    pool_gets_waited_total: IntCounterVec,
+    pool_gets_timed_out_total: IntCounterVec,
+    pool_get_wait_seconds_total: CounterVec,
+    // 采样连接池时持有，避免并发抓取把同一段增量累加两次
+    pool_sample_lock: Mutex<()>,
+    nacos_config_reloads_total: IntCounterVec,
+}
+
+impl Metrics {
+    /// 创建并注册所有指标
+    pub fn new() -> Result<Self, prometheus::Error> {
+        let registry = Registry::new();
+
+        let http_requests_total = IntCounterVec::new(
+            Opts::new("http_requests_total", "入站 HTTP 请求数"),
+            &["method", "route", "status"],
+        )?;
+        let http_request_duration_seconds = HistogramVec::new(
+            HistogramOpts::new("http_request_duration_seconds", "入站 HTTP 请求耗时 (秒)"),
+            &["method", "route", "status"],
+        )?;
+        let outbound_requests_total = IntCounterVec::new(
+            Opts::new("outbound_requests_total", "下游服务调用次数 (每次尝试)"),
+            &["service", "method", "outcome"],
+        )?;
+        let outbound_request_duration_seconds = HistogramVec::new(
+            HistogramOpts::new("outbound_request_duration_seconds", "下游服务调用耗时 (秒，到收到响应头为止)"),
+            &["service", "method", "outcome"],
+        )?;
+        let pool_connections = IntGaugeVec::new(
+            Opts::new("pool_connections", "连接池连接数 (state: size / idle / in_use / max)"),
+            &["pool", "state"],
+        )?;
+        let pool_gets_waited_total = IntCounterVec::new(
+            Opts::new("pool_gets_waited_total", "获取连接时需要等待的次数"),
+            &["pool"],
+        )?;
+        let pool_gets_timed_out_total = IntCounterVec::new(
+            Opts::new("pool_gets_timed_out_total", "获取连接超时的次数"),
+            &["pool"],
+        )?;
+        let pool_get_wait_seconds_total = CounterVec::new(
+            Opts::new("pool_get_wait_seconds_total", "获取连接时等待的累计时长 (秒)"),
+            &["pool"],
+        )?;
+        let nacos_config_reloads_total = IntCounterVec::new(
+            Opts::new("nacos_config_reloads_total", "Nacos 配置热更新次数"),
+            &["result"],
+        )?;
+
+        registry.register(Box::new(http_requests_total.clone()))?;
+        registry.register(Box::new(http_request_duration_seconds.clone()))?;
+        registry.register(Box::new(outbound_requests_total.clone()))?;
+        registry.register(Box::new(outbound_request_duration_seconds.clone()))?;
+        registry.register(Box::new(pool_connections.clone()))?;
+        registry.register(Box::new(pool_gets_waited_total.clone()))?;
+        registry.register(Box::new(pool_gets_timed_out_total.clone()))?;
+        registry.register(Box::new(pool_get_wait_seconds_total.clone()))?;
+        registry.register(Box::new(nacos_config_reloads_total.clone()))?;
+
+        // 进程指标 (process_cpu_seconds_total、process_resident_memory_bytes 等)
+        #[cfg(target_os = "linux")]
+        registry.register(Box::new(prometheus::process_collector::ProcessCollector::for_self()))?;
+
+        Ok(Metrics {
+            registry,
+            http_requests_total,
+            http_request_duration_seconds,
+            outbound_requests_total,
+            outbound_request_duration_seconds,
+            pool_connections,
+            pool_gets_waited_total,
+            pool_gets_timed_out_total,
+            pool_get_wait_seconds_total,
+            pool_sample_lock: Mutex::new(()),
+            nacos_config_reloads_total,
+        })
+    }
+
+    /// 记录一次入站 HTTP 请求
+    pub fn observe_http(&self, method: &str, route: &str, status: u16, latency: Duration) {
+        let status = status.to_string();
+        let labels = [method, route, status.as_str()];
+        self.http_requests_total.with_label_values(&labels).inc();
+        self.http_request_duration_seconds
+            .with_label_values(&labels)
+            .observe(latency.as_secs_f64());
+    }
+
+    /// 记录一次下游服务调用 (熔断拒绝时 latency 为 0)
+    pub fn observe_outbound(
+        &self,
+        service: &str,
+        method: &str,
+        outcome: OutboundOutcome,
+        latency: Duration,
+    ) {
+        self.outbound_requests_total
+            .with_label_values(&[service, method, outcome.as_str()])
+            .inc();
+        self.outbound_request_duration_seconds
+            .with_label_values(&[service, method, outcome.as_str()])
+            .observe(latency.as_secs_f64());
+    }
+
+    /// 记录一次 Nacos 配置热更新
+    pub fn record_config_reload(&self, success: bool) {
+        let result = if success { "success" } else { "failure" };
+        self.nacos_config_reloads_total
+            .with_label_values(&[result])
+            .inc();
+    }
+
+    /// 采样连接池状态后，输出 Prometheus 文本格式
+    pub fn render(&self, state: &AppState) -> String {
+        self.sample_pools(state);
+
+        let mut buffer = Vec::new();
+        if let Err(e) = TextEncoder::new().encode(&self.registry.gather(), &mut buffer) {
+            error!("(Metrics) 编码指标失败: {}", e);
+        }
+        String::from_utf8(buffer).unwrap_or_default()
+    }
+
+    /// 辅助函数：采样 DB / Redis 连接池
+    ///
+    /// sqlx 与 bb8 都不暴露当前排队等待的数量，Redis 以累计等待 / 超时次数代替。
+    fn sample_pools(&self, state: &AppState) {
+        let _guard = self.pool_sample_lock.lock().unwrap_or_else(|e| e.into_inner());
+
+        let db = state.db_pool.get_mysql_connection_pool();
+        let db_size = i64::from(db.size());
+        let db_idle = db.num_idle() as i64;
+        self.set_pool("db", db_size, db_idle, i64::from(db.options().get_max_connections()));
+
+        let redis = state.redis_pool.state();
+        self.set_pool(
+            "redis",
+            i64::from(redis.connections),
+            i64::from(redis.idle_connections),
+            i64::from(REDIS_POOL_MAX_SIZE),
+        );
+        // bb8 的统计是累计值，按与上次采样的差值累加到 counter
+        let stats = &redis.statistics;
+        let waited = self.pool_gets_waited_total.with_label_values(&["redis"]);
+        waited.inc_by(stats.get_waited.saturating_sub(waited.get()));
+        let timed_out = self.pool_gets_timed_out_total.with_label_values(&["redis"]);
+        timed_out.inc_by(stats.get_timed_out.saturating_sub(timed_out.get()));
+        let wait_seconds = self.pool_get_wait_seconds_total.with_label_values(&["redis"]);
+        let delta = stats.get_wait_time.as_secs_f64() - wait_seconds.get();
+        if delta > 0.0 {
+            wait_seconds.inc_by(delta);
+        }
+    }
+
+    fn set_pool(&self, pool: &str, size: i64, idle: i64, max: i64) {
+        self.pool_connections.with_label_values(&[pool, "size"]).set(size);
+        self.pool_connections.with_label_values(&[pool, "idle"]).set(idle);
+        self.pool_connections
+            .with_label_values(&[pool, "in_use"])
+            .set((size - idle).max(0));
+        self.pool_connections.with_label_values(&[pool, "max"]).set(max);
+    }
+}
@@ -13,60 +13,177 @@ use axum::{
 };
 // --- 修改点 ---
 // 移除了 async_trait，因为我们不再需要它了
-// use async_trait::async_trait; 
-use crate::clients::auth_client; 
+// use async_trait::async_trait;
+use crate::middleware::permission;
+use crate::middleware::rbac::{self, PermissionSet};
+use crate::services::{auth_service, kms_app_access_service};
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
-use tracing::{warn};
+use tracing::{info, warn};
 
+/// 机器调用方使用的访问密钥 Header
+pub const APP_ACCESS_KEY_HEADER: &str = "X-App-Access-Key";
 
-// --- 2. 定义我们自己的 CurrentUser 结构体 (保持不变) ---
-#[derive(Debug, Clone)]
+/// 应用主体的 username 前缀：资源的 `create_by` 与 username 比较 (见 `rbac::authorize_owned`)，
+/// 加上前缀后，与某个用户同名的应用不会拿到该用户创建的资源的所有权
+pub const APP_PRINCIPAL_PREFIX: &str = "app:";
+
+// --- 1. 主体类型 ---
+/// 当前请求的调用方是“人”还是“应用”
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[allow(dead_code)]
+pub enum PrincipalKind {
+    // 通过 `Authorization: Bearer` 认证的用户
+    User,
+    // 通过 `X-App-Access-Key` 认证的应用 (kms_app_access)
+    App,
+}
+
+// --- 2. 定义我们自己的 CurrentUser 结构体 ---
+// (Serialize/Deserialize 用于把认证结果缓存到 Redis)
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[allow(dead_code)]
 pub struct CurrentUser {
     pub id: String,
     pub username: String,
-    pub permissions: Vec<String>,
+    // --- 修改点 ---
+    // 认证得到的原始 authorities (权限 + 角色，例如 `ROLE_admin`)
+    pub authorities: Vec<String>,
+    // --- 新增：主体类型 ---
+    pub kind: PrincipalKind,
+    // --- 新增：由 rbac::apply_roles 根据 Nacos `rbac` 配置展开，不缓存 ---
+    #[serde(skip)]
+    pub roles: Vec<String>,
+    #[serde(skip)]
+    pub permissions: PermissionSet,
+}
+
+impl CurrentUser {
+    /// 创建一个 CurrentUser，权限集合初始为 authorities 本身 (尚未展开角色)
+    pub fn new(id: String, username: String, authorities: Vec<String>, kind: PrincipalKind) -> Self {
+        let permissions = authorities.iter().collect();
+        CurrentUser {
+            id,
+            username,
+            authorities,
+            kind,
+            roles: Vec::new(),
+            permissions,
+        }
+    }
 }
 
 // --- 4. 认证中间件 (核心逻辑) ---
-/// 认证中间件 (mw_require_auth)
+/// 认证中间件 (mw_require_auth)：只接受 `Authorization: Bearer <token>`
 pub async fn mw_require_auth(
     State(state): State<AppState>, // <-- 注入 AppState
-    mut req: Request,
+    req: Request,
     next: Next,
 ) -> Result<Response, AppError> {
-    
+
     // 1. 提取 Token
     let token = extract_token(req.headers())?;
 
-    // 2. --- 核心修改点 ---
-    // 调用封装好的 Auth 客户端
-    let auth_response = auth_client::check_token(&state, &token).await?;
-    
-    // 3. 将 AuthResponse 转换为 CurrentUser
-    //    (auth_response 现在是 auth_client 内部定义的类型)
-    let permissions = auth_response.user_info.authorities
-        .into_iter()
-        .map(|auth| auth.authority)
-        .collect();
-
-    let current_user = CurrentUser {
-        id: auth_response.user_info.user_id,
-        username: auth_response.user_info.username,
-        permissions,
+    // 2. 校验 Token (远程 check_token 或本地 JWT)，并转换为 CurrentUser
+    let current_user = authenticate_bearer(&state, &token).await?;
+
+    // 3. 展开角色后存入 extensions，并放行
+    let current_user = resolve_roles(&state, current_user).await;
+    Ok(run_as(current_user, req, next).await)
+}
+
+/// 组合认证中间件 (mw_require_auth_or_app_key)：两种方式任选其一
+///
+/// - 带 `Authorization` Header 时按 Bearer Token 认证
+/// - 否则按 `X-App-Access-Key` 认证：密钥在 `kms_app_access` 表中校验 (必须已启用且未删除)，
+///   认证成功后同样注入 `CurrentUser`，因此 `check_permission` 照常工作
+pub async fn mw_require_auth_or_app_key(
+    State(state): State<AppState>,
+    req: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let current_user = if req.headers().contains_key("Authorization") {
+        let token = extract_token(req.headers())?;
+        authenticate_bearer(&state, &token).await?
+    } else if let Some(key) = extract_app_access_key(req.headers()) {
+        authenticate_app_key(&state, &key).await?
+    } else {
+        warn!(
+            "认证中间件：Header 'Authorization' 和 '{}' 均缺失",
+            APP_ACCESS_KEY_HEADER
+        );
+        return Err(ServiceError::Unauthorized.into());
     };
 
-    // 4. 存入 extensions (这部分保持不变)
-    req.extensions_mut().insert(Arc::new(current_user));
+    let current_user = resolve_roles(&state, current_user).await;
+    Ok(run_as(current_user, req, next).await)
+}
 
-    // 5. 放行
-    Ok(next.run(req).await)
+// --- 5. 辅助函数 ---
+
+/// 校验 Bearer Token，并转换为 CurrentUser
+///
+/// 具体走远程 check_token 还是本地 JWT 校验，由 Nacos 配置 `auth.mode` 决定。
+async fn authenticate_bearer(state: &AppState, token: &str) -> Result<CurrentUser, AppError> {
+    auth_service::authenticate_token(state, token).await
 }
 
-// --- 5. 辅助函数 (保持不变) ---
+/// 通过 kms_app_access 表校验应用密钥，并转换为 CurrentUser
+///
+/// 应用的权限来自 Nacos 配置 `app_access_auth`
+/// (`default_permissions` + 按应用名称配置的 `permissions`)。
+/// username 为 `app:<应用名称>`，与用户名区分开。
+async fn authenticate_app_key(state: &AppState, key: &str) -> Result<CurrentUser, AppError> {
+    let app = kms_app_access_service::authenticate_access_key(state, key)
+        .await
+        .inspect_err(|_| warn!("认证中间件：'{}' 校验失败", APP_ACCESS_KEY_HEADER))?;
+
+    let permissions = {
+        let config_guard = state.app_config.read().await;
+        config_guard
+            .app_access_auth
+            .as_ref()
+            .map(|auth| {
+                let mut permissions = auth.default_permissions.clone();
+                if let Some(extra) = auth.permissions.get(&app.name) {
+                    permissions.extend(extra.iter().cloned());
+                }
+                permissions
+            })
+            .unwrap_or_default()
+    };
+
+    info!("认证中间件：应用 {} (AppAccess ID: {}) 通过密钥认证", app.name, app.id);
+    Ok(CurrentUser::new(
+        app.id.to_string(),
+        format!("{}{}", APP_PRINCIPAL_PREFIX, app.name),
+        permissions,
+        PrincipalKind::App,
+    ))
+}
+
+/// 按 Nacos 配置 `rbac` 展开角色 -> 权限
+///
+/// 放在认证之后单独执行 (而不是缓存展开结果)，这样角色配置变更可以立即生效。
+async fn resolve_roles(state: &AppState, mut user: CurrentUser) -> Arc<CurrentUser> {
+    let config_guard = state.app_config.read().await;
+    rbac::apply_roles(&mut user, config_guard.rbac.as_ref());
+    Arc::new(user)
+}
+
+/// 以认证后的用户身份执行后续处理
+///
+/// CurrentUser 放入请求的 extensions 供 Handler 使用，同时放入响应的 extensions，
+/// 这样外层的访问日志中间件可以记录 user_id。
+async fn run_as(current_user: Arc<CurrentUser>, mut req: Request, next: Next) -> Response {
+    req.extensions_mut().insert(current_user.clone());
+    let mut response = next.run(req).await;
+    response.extensions_mut().insert(current_user);
+    response
+}
 
 /// 辅助函数：从 Headers 中提取 Bearer Token
-fn extract_token(headers: &HeaderMap) -> Result<String, AppError> {
+pub fn extract_token(headers: &HeaderMap) -> Result<String, AppError> {
     let header_value = headers
         .get("Authorization")
         .and_then(|value| value.to_str().ok())
@@ -79,29 +196,209 @@ fn extract_token(headers: &HeaderMap) -> Result<String, AppError> {
         warn!("认证中间件：Header 'Authorization' 格式错误，非 Bearer");
         return Err(ServiceError::Unauthorized.into());
     }
-    
+
     Ok(header_value[7..].to_string())
 }
 
+/// 辅助函数：从 Headers 中提取应用访问密钥 (空值视为缺失)
+fn extract_app_access_key(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(APP_ACCESS_KEY_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::to_string)
+}
+
+/// 在 Handler 内部手动检查权限 (支持通配符，如 `kms_*`)
+///
+/// 路由级别的检查优先使用声明式守卫 `middleware::permission::RequirePermissionExt::require`。
+#[allow(dead_code)]
 pub fn check_permission(
     user: &Arc<CurrentUser>,
     required_permission: &str,
 ) -> Result<(), AppError> {
-    
-    // (你可以用 wildmatch 库来支持通配符)
-    if user.permissions.contains(&required_permission.to_string()) {
-        // 权限通过！
-        Ok(())
-    } else {
-        // 权限不足！返回 403 Forbidden
-        warn!(
-            "权限拒绝：用户 {} (ID: {}) 缺少权限 '{}'",
-            user.username, user.id, required_permission
-        );
-        Err(ServiceError::Forbidden(format!(
-            "缺少权限: {}",
-            required_permission
-        ))
-        .into())
+    permission::perm(required_permission).authorize(user)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::app_specific::{
+        AppAccessAuthConfig, AppSpecificConfig, AuthConfig, AuthMode, JwtConfig,
+    };
+    use crate::dto::kms_app_access_dto::{CreateAppAccessRequest, PatchAppAccessRequest};
+    use crate::middleware::rbac::authorize_owned;
+    use crate::test_support;
+    use axum::{Extension, Json, Router, body::Body, http::StatusCode, middleware, routing::get};
+    use jsonwebtoken::{EncodingKey, Header, encode};
+    use serde_json::{Value, json};
+    use tower::ServiceExt;
+
+    const SECRET: &str = "test-secret";
+
+    async fn state() -> AppState {
+        let config = AppSpecificConfig {
+            auth: Some(AuthConfig {
+                mode: AuthMode::Jwt,
+                jwt: Some(JwtConfig {
+                    hs256_secret: Some(SECRET.to_string()),
+                    ..Default::default()
+                }),
+                token_cache: None,
+            }),
+            app_access_auth: Some(AppAccessAuthConfig {
+                default_permissions: vec!["app_default".to_string()],
+                permissions: [("order".to_string(), vec!["app_order".to_string()])].into(),
+            }),
+            ..Default::default()
+        };
+        test_support::app_state(config).await
+    }
+
+    /// 创建一个应用，返回 (ID, 明文密钥)
+    async fn create_app(state: &AppState, name: &str) -> (i64, String) {
+        let admin = test_support::user("admin", &["kms_kmsAppAccess_edit", "kms_kmsAppAccess_del"]);
+        let created = kms_app_access_service::create_app_access(
+            state,
+            &admin,
+            CreateAppAccessRequest {
+                access_info_id: 1,
+                name: name.to_string(),
+                mark: None,
+                status: None,
+                description: None,
+                show_id: None,
+            },
+        )
+        .await
+        .unwrap();
+        (created.app_access.id, created.app_access_key)
+    }
+
+    fn bearer(username: &str) -> String {
+        let claims = json!({"sub": username, "exp": chrono::Utc::now().timestamp() + 600});
+        let token = encode(&Header::default(), &claims, &EncodingKey::from_secret(SECRET.as_bytes())).unwrap();
+        format!("Bearer {}", token)
+    }
+
+    /// 经过 mw_require_auth_or_app_key 后，返回认证得到的主体
+    async fn call(state: &AppState, headers: &[(&str, &str)]) -> (StatusCode, Value) {
+        let app = Router::new()
+            .route(
+                "/whoami",
+                get(|Extension(user): Extension<Arc<CurrentUser>>| async move {
+                    let mut permissions = user.authorities.clone();
+                    permissions.sort();
+                    Json(json!({
+                        "username": user.username,
+                        "kind": format!("{:?}", user.kind),
+                        "permissions": permissions,
+                    }))
+                }),
+            )
+            .route_layer(middleware::from_fn_with_state(state.clone(), mw_require_auth_or_app_key))
+            .with_state(state.clone());
+        let mut request = Request::get("/whoami");
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        let response = app.oneshot(request.body(Body::empty()).unwrap()).await.unwrap();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap_or(Value::Null))
+    }
+
+    #[tokio::test]
+    async fn app_key_permissions_merge_defaults_and_per_app() {
+        let state = state().await;
+        let (_, order_key) = create_app(&state, "order").await;
+        let (_, billing_key) = create_app(&state, "billing").await;
+
+        let (status, body) = call(&state, &[(APP_ACCESS_KEY_HEADER, &order_key)]).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["username"], "app:order");
+        assert_eq!(body["kind"], "App");
+        assert_eq!(body["permissions"], json!(["app_default", "app_order"]));
+
+        let (_, body) = call(&state, &[(APP_ACCESS_KEY_HEADER, &billing_key)]).await;
+        assert_eq!(body["permissions"], json!(["app_default"]));
+
+        let (status, _) = call(&state, &[]).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _) = call(&state, &[(APP_ACCESS_KEY_HEADER, "ak_unknown")]).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn bearer_takes_precedence_over_app_key() {
+        let state = state().await;
+        let (_, key) = create_app(&state, "order").await;
+
+        let token = bearer("alice");
+        let (status, body) =
+            call(&state, &[("Authorization", &token), (APP_ACCESS_KEY_HEADER, &key)]).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!((body["username"].as_str(), body["kind"].as_str()), (Some("alice"), Some("User")));
+
+        // Bearer 无效时不会退回到应用密钥
+        let (status, _) = call(
+            &state,
+            &[("Authorization", "Bearer not-a-jwt"), (APP_ACCESS_KEY_HEADER, &key)],
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn disabled_or_deleted_app_keys_are_rejected() {
+        let state = state().await;
+        let admin = test_support::user("admin", &["kms_kmsAppAccess_edit", "kms_kmsAppAccess_del"]);
+        let (disabled_id, disabled_key) = create_app(&state, "disabled").await;
+        let (deleted_id, deleted_key) = create_app(&state, "deleted").await;
+
+        kms_app_access_service::patch_app_access(
+            &state,
+            &admin,
+            disabled_id,
+            PatchAppAccessRequest {
+                status: Some(0),
+                access_info_id: None,
+                name: None,
+                mark: None,
+                description: None,
+                show_id: None,
+            },
+        )
+        .await
+        .unwrap();
+        kms_app_access_service::delete_app_access(&state, &admin, deleted_id).await.unwrap();
+
+        for key in [&disabled_key, &deleted_key] {
+            let (status, _) = call(&state, &[(APP_ACCESS_KEY_HEADER, key)]).await;
+            assert_eq!(status, StatusCode::UNAUTHORIZED);
+        }
+    }
+
+    #[tokio::test]
+    async fn app_does_not_own_resources_of_a_user_with_the_same_name() {
+        let state = state().await;
+        let (_, key) = create_app(&state, "alice").await;
+        let app = authenticate_app_key(&state, &key).await.unwrap();
+        let mut with_own = app.clone();
+        with_own.permissions = ["kms_kmsAppAccess_edit_own"].iter().collect();
+
+        // 用户 alice 创建的记录
+        let alice = test_support::user("alice", &["kms_kmsAppAccess_edit_own"]);
+        let (id, _) = create_app(&state, "record").await;
+        let mut record = kms_app_access_service::get_app_access_by_id(&state, id).await.unwrap();
+        record.create_by = "alice".to_string();
+
+        assert!(authorize_owned(&alice, &record, "kms_kmsAppAccess_edit").is_ok());
+        assert!(authorize_owned(&with_own, &record, "kms_kmsAppAccess_edit").is_err());
+
+        // 应用自己创建的记录仍归它所有
+        record.create_by = with_own.username.clone();
+        assert!(authorize_owned(&with_own, &record, "kms_kmsAppAccess_edit").is_ok());
+    }
+}
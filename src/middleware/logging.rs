@@ -1,37 +1,298 @@
 // src/middleware/logging.rs
-// 定义一个简单的日志中间件。
+// 访问日志中间件：每个请求输出一条结构化日志 (target = "access_log")
+//
+// 字段：method、route (路由模板，如 `/app-access/{id}`)、uri (查询参数已脱敏)、status、latency_ms、
+// response_size、client_ip、user_agent、user_id；request_id 来自外层的 request span。
+// 配置见 Nacos `access_log` (采样、脱敏、是否信任 X-Forwarded-For)，规则按配置预处理一次后缓存。
 
+use crate::config::app_specific::AccessLogConfig;
+use crate::middleware::auth::CurrentUser;
+use crate::state::AppState;
 use axum::{
-    body::Body, // <-- 新增：导入 Body 类型
-    http::{Request},
+    body::{Body, HttpBody},
+    extract::{ConnectInfo, MatchedPath, State},
+    http::{HeaderMap, Request, header},
     middleware::Next,
     response::Response,
 };
+use percent_encoding::percent_decode_str;
+use rand::Rng;
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+use std::sync::{Arc, LazyLock};
+use std::time::Instant;
 use tracing::info;
+use wildmatch::WildMatch;
 
-/// 一个简单的日志中间件
+/// 默认脱敏的查询参数
+const DEFAULT_REDACT_QUERY_PARAMS: [&str; 3] = ["token", "access_token", "password"];
+/// 脱敏后的占位值
+const REDACTED: &str = "***";
+
+/// 访问日志中间件
 pub async fn log_requests(
-    // --- 修改点 ---
-    // `Request` 是一个泛型结构体，我们需要指定它的 Body 类型。
-    // 在 Axum 中，默认的 Body 类型是 `axum::body::Body`。
+    State(state): State<AppState>,
+    // `Request` 是一个泛型结构体，在 Axum 中默认的 Body 类型是 `axum::body::Body`。
     req: Request<Body>,
     next: Next,
 ) -> Response {
-    // 从请求中获取方法和 URI
+    let started = Instant::now();
+
+    // 1. 在请求到达处理器之前收集请求信息 (请求会被 move 进 next.run)
     let method = req.method().clone();
+    // 未匹配到路由 (404) 时没有 MatchedPath
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string());
     let uri = req.uri().clone();
-    
-    // 1. 在请求到达处理器之前执行的逻辑
-    info!("接收到请求 -> 方法: {}, URI: {}", method, uri);
+    let user_agent = header_str(req.headers(), header::USER_AGENT.as_str()).map(str::to_string);
+    let peer_ip = req
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip().to_string());
+    let forwarded_ip = forwarded_client_ip(req.headers());
 
-    // 2. 调用 `next.run(req).await` 来执行下一个中间件或处理器
-    //    并获取响应
+    // 2. 执行下一个中间件或处理器
     let response = next.run(req).await;
+    let latency = started.elapsed();
+
+    // 3. 按配置决定是否记录 (配置在请求结束后读取，避免长时间持有读锁)
+    let (trust_forwarded_for, rules) = {
+        let config = state.app_config.read().await;
+        let access_log = config.access_log.as_ref();
+        if access_log.and_then(|c| c.enabled) == Some(false) {
+            return response;
+        }
+        let rules = access_log.map_or_else(|| DEFAULT_RULES.clone(), AccessLogConfig::rules);
+        let status = response.status();
+        let rate = rules
+            .sample_rate(route.as_deref().unwrap_or(uri.path()))
+            .unwrap_or(1.0);
+        // 4xx / 5xx 总是记录
+        if !status.is_client_error() && !status.is_server_error() && !sampled(rate) {
+            return response;
+        }
+        (
+            // 默认不信任：X-Forwarded-For 可以被客户端伪造，只有部署在可信代理之后才应开启
+            access_log.and_then(|c| c.trust_forwarded_for).unwrap_or(false),
+            rules,
+        )
+    };
+
+    let client_ip = if trust_forwarded_for {
+        forwarded_ip.or(peer_ip)
+    } else {
+        peer_ip
+    };
+    let logged_uri = match uri.query() {
+        Some(query) => format!("{}?{}", uri.path(), rules.redact_query(query)),
+        None => uri.path().to_string(),
+    };
+    // 认证中间件会把 CurrentUser 同时放入响应的 extensions
+    let user_id = response
+        .extensions()
+        .get::<Arc<CurrentUser>>()
+        .map(|user| user.id.clone());
+    // 流式响应的大小未知
+    let response_size = response.body().size_hint().exact().or_else(|| {
+        header_str(response.headers(), header::CONTENT_LENGTH.as_str())
+            .and_then(|len| len.parse().ok())
+    });
 
-    // 3. 在响应返回给客户端之前执行的逻辑
-    info!("已发送响应 (状态码: {})", response.status());
+    info!(
+        target: "access_log",
+        method = %method,
+        route = route.as_deref().unwrap_or("-"),
+        uri = %logged_uri,
+        status = response.status().as_u16(),
+        latency_ms = latency.as_secs_f64() * 1000.0,
+        response_size,
+        client_ip = client_ip.as_deref().unwrap_or("-"),
+        user_agent = user_agent.as_deref().unwrap_or("-"),
+        user_id = user_id.as_deref().unwrap_or("-"),
+        "{} {} -> {} ({:.1}ms)",
+        method,
+        logged_uri,
+        response.status().as_u16(),
+        latency.as_secs_f64() * 1000.0,
+    );
 
-    // 4. 返回响应
     response
 }
 
+/// 辅助函数：读取字符串 Header
+fn header_str<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name).and_then(|value| value.to_str().ok())
+}
+
+/// 辅助函数：从 X-Forwarded-For (第一个地址) 或 X-Real-IP 中取客户端 IP
+fn forwarded_client_ip(headers: &HeaderMap) -> Option<String> {
+    header_str(headers, "x-forwarded-for")
+        .and_then(|value| value.split(',').next())
+        .or_else(|| header_str(headers, "x-real-ip"))
+        .map(str::trim)
+        .filter(|ip| !ip.is_empty())
+        .map(str::to_string)
+}
+
+/// 辅助函数：按采样率决定是否记录
+fn sampled(rate: f64) -> bool {
+    if rate >= 1.0 {
+        true
+    } else if rate <= 0.0 {
+        false
+    } else {
+        rand::rng().random_bool(rate)
+    }
+}
+
+/// 预处理后的访问日志规则 (见 `AccessLogConfig::rules`)
+#[derive(Debug)]
+pub struct AccessLogRules {
+    // 需要脱敏的参数名 (小写，默认参数 + 配置中的参数)
+    redact_params: HashSet<String>,
+    // 精确匹配的采样率
+    exact_sampling: HashMap<String, f64>,
+    // 通配符采样规则，按模式长度从长到短排列
+    wildcard_sampling: Vec<(WildMatch, f64)>,
+}
+
+/// 未配置 `access_log` 时使用的规则
+static DEFAULT_RULES: LazyLock<Arc<AccessLogRules>> =
+    LazyLock::new(|| Arc::new(AccessLogRules::new(&AccessLogConfig::default())));
+
+impl AccessLogRules {
+    pub fn new(config: &AccessLogConfig) -> Self {
+        // 配置的参数追加在默认参数之后，不会让默认参数失去脱敏
+        let redact_params = DEFAULT_REDACT_QUERY_PARAMS
+            .iter()
+            .map(|p| p.to_string())
+            .chain(config.redact_query_params.iter().flatten().cloned())
+            .map(|p| p.to_ascii_lowercase())
+            .collect();
+        let mut wildcard_sampling: Vec<(&String, f64)> =
+            config.sampling.iter().map(|(pattern, rate)| (pattern, *rate)).collect();
+        wildcard_sampling.sort_by(|a, b| b.0.len().cmp(&a.0.len()).then_with(|| a.0.cmp(b.0)));
+        Self {
+            redact_params,
+            exact_sampling: config.sampling.clone(),
+            wildcard_sampling: wildcard_sampling
+                .into_iter()
+                .map(|(pattern, rate)| (WildMatch::new(pattern), rate))
+                .collect(),
+        }
+    }
+
+    /// 查找路由的采样率 (精确匹配优先，其次是最长的通配符模式)
+    fn sample_rate(&self, route: &str) -> Option<f64> {
+        if let Some(rate) = self.exact_sampling.get(route) {
+            return Some(*rate);
+        }
+        self.wildcard_sampling
+            .iter()
+            .find(|(pattern, _)| pattern.matches(route))
+            .map(|(_, rate)| *rate)
+    }
+
+    /// 隐藏查询字符串中敏感参数的值
+    ///
+    /// 参数名先做百分号解码再比较 (不区分大小写)，`%74oken=` 与 `token=` 同样被脱敏。
+    fn redact_query(&self, query: &str) -> String {
+        query
+            .split('&')
+            .map(|pair| match pair.split_once('=') {
+                Some((name, _)) if self.is_sensitive(name) => format!("{}={}", name, REDACTED),
+                _ => pair.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("&")
+    }
+
+    fn is_sensitive(&self, name: &str) -> bool {
+        let decoded = percent_decode_str(name).decode_utf8_lossy();
+        self.redact_params.contains(&decoded.to_ascii_lowercase())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn config(redact: Option<&[&str]>, sampling: &[(&str, f64)]) -> AccessLogConfig {
+        let mut config = AccessLogConfig::default();
+        config.redact_query_params =
+            redact.map(|params| params.iter().map(|p| p.to_string()).collect());
+        config.sampling = sampling.iter().map(|(p, r)| (p.to_string(), *r)).collect();
+        config
+    }
+
+    #[test]
+    fn redact_query_hides_default_params() {
+        let rules = AccessLogRules::new(&AccessLogConfig::default());
+        assert_eq!(
+            rules.redact_query("page=1&TOKEN=abc&password=p&flag"),
+            "page=1&TOKEN=***&password=***&flag"
+        );
+    }
+
+    #[test]
+    fn configured_params_are_added_to_defaults() {
+        let rules = AccessLogRules::new(&config(Some(&["api_key"]), &[]));
+        assert_eq!(
+            rules.redact_query("api_key=k&access_token=t&name=n"),
+            "api_key=***&access_token=***&name=n"
+        );
+    }
+
+    #[test]
+    fn param_names_are_decoded_before_matching() {
+        let rules = AccessLogRules::new(&AccessLogConfig::default());
+        assert_eq!(rules.redact_query("%74oken=abc"), "%74oken=***");
+        assert_eq!(rules.redact_query("access%5Ftoken=abc"), "access%5Ftoken=***");
+        assert_eq!(rules.redact_query("tokens=abc"), "tokens=abc");
+    }
+
+    #[test]
+    fn sample_rate_prefers_exact_then_longest_wildcard() {
+        let rules = AccessLogRules::new(&config(
+            None,
+            &[("/health", 0.5), ("/health*", 0.0), ("/*", 0.1), ("/app-access/*", 0.2)],
+        ));
+        assert_eq!(rules.sample_rate("/health"), Some(0.5));
+        assert_eq!(rules.sample_rate("/health/ready"), Some(0.0));
+        assert_eq!(rules.sample_rate("/app-access/{id}"), Some(0.2));
+        assert_eq!(rules.sample_rate("/hello"), Some(0.1));
+        assert_eq!(AccessLogRules::new(&AccessLogConfig::default()).sample_rate("/hello"), None);
+    }
+
+    #[test]
+    fn rules_are_compiled_once_per_config() {
+        let config = config(None, &[("/*", 0.1)]);
+        assert!(Arc::ptr_eq(&config.rules(), &config.rules()));
+    }
+
+    #[test]
+    fn sampled_respects_bounds() {
+        assert!(sampled(1.0));
+        assert!(sampled(2.0));
+        assert!(!sampled(0.0));
+        assert!(!sampled(-1.0));
+    }
+
+    #[test]
+    fn forwarded_client_ip_prefers_first_forwarded_for() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(forwarded_client_ip(&headers), None);
+
+        headers.insert("x-real-ip", HeaderValue::from_static("10.0.0.2"));
+        assert_eq!(forwarded_client_ip(&headers).as_deref(), Some("10.0.0.2"));
+
+        headers.insert("x-forwarded-for", HeaderValue::from_static(" 1.2.3.4 , 10.0.0.1"));
+        assert_eq!(forwarded_client_ip(&headers).as_deref(), Some("1.2.3.4"));
+
+        headers.insert("x-forwarded-for", HeaderValue::from_static(""));
+        assert_eq!(forwarded_client_ip(&headers), None);
+    }
+}
@@ -0,0 +1,63 @@
+// src/middleware/metrics.rs
+// 入站 HTTP 请求指标中间件 (http_requests_total / http_request_duration_seconds)
+
+use crate::metrics::{OTHER_METHOD, UNMATCHED_ROUTE};
+use crate::state::AppState;
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::Method,
+    middleware::Next,
+    response::Response,
+};
+use std::time::Instant;
+
+/// 按 method / route (路由模板) / status 记录请求数与耗时
+pub async fn track_metrics(State(state): State<AppState>, req: Request, next: Next) -> Response {
+    let started = Instant::now();
+    let method = method_label(req.method());
+    // 使用路由模板而不是具体 URI，避免标签基数爆炸
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string());
+
+    let response = next.run(req).await;
+
+    state.metrics.observe_http(
+        method,
+        route.as_deref().unwrap_or(UNMATCHED_ROUTE),
+        response.status().as_u16(),
+        started.elapsed(),
+    );
+    response
+}
+
+/// 辅助函数：method 标签只使用标准方法，其余 (任意 token，会得到 405) 统一记为 `OTHER`
+fn method_label(method: &Method) -> &'static str {
+    match *method {
+        Method::GET => "GET",
+        Method::POST => "POST",
+        Method::PUT => "PUT",
+        Method::DELETE => "DELETE",
+        Method::PATCH => "PATCH",
+        Method::HEAD => "HEAD",
+        Method::OPTIONS => "OPTIONS",
+        Method::CONNECT => "CONNECT",
+        Method::TRACE => "TRACE",
+        _ => OTHER_METHOD,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn non_standard_methods_share_one_label() {
+        assert_eq!(method_label(&Method::GET), "GET");
+        assert_eq!(method_label(&Method::PATCH), "PATCH");
+        for token in ["PROPFIND", "FOO", "X1"] {
+            assert_eq!(method_label(&Method::from_bytes(token.as_bytes()).unwrap()), OTHER_METHOD);
+        }
+    }
+}
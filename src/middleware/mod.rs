@@ -3,4 +3,16 @@
 
 pub mod logging;
 
-pub mod auth;
\ No newline at end of file
+// 入站 HTTP 请求指标
+pub mod metrics;
+
+pub mod auth;
+
+// 声明式权限守卫
+pub mod permission;
+
+// 角色 / 权限模型 (PermissionSet、角色展开、资源级检查)
+pub mod rbac;
+
+// 入站请求上下文 (出站调用透传 request id / trace context 时使用)
+pub mod request_context;
@@ -0,0 +1,429 @@
+// src/middleware/permission.rs
+// 声明式权限守卫 (类似 Spring Security 的 @PreAuthorize)
+//
+// 用法：
+// ```ignore
+// Router::new()
+//     .guarded_route(guards, Method::GET, "/{id}", handler, perm("kms_kmsAppAccess_view"))
+//     .guarded_route(guards, Method::POST, "/", handler, any_of(["kms_kmsAppAccess_add", "kms_admin"]))
+// ```
+// - 守卫在 Handler 之前执行，权限不足时直接返回 `ServiceError::Forbidden` (403)
+// - 支持 any-of / all-of 任意嵌套，以及用户权限中的通配符 (如 `kms_*`)
+// - 通过 `guarded_route` 声明的路由在组装时登记到审计清单 (`AppState.permissions`)，
+//   可通过 `GET /admin/permissions` 查看；nest 前缀由 `nest_guarded` 传给子路由，与实际路径保持一致
+
+use crate::errors::{AppError, ServiceError};
+use crate::middleware::auth::CurrentUser;
+use axum::{
+    Router,
+    extract::Request,
+    handler::Handler,
+    http::Method,
+    response::{IntoResponse, Response},
+    routing::{MethodFilter, MethodRouter, on},
+};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, RwLock};
+use std::task::{Context, Poll};
+use tower::{Layer, Service};
+use tracing::warn;
+
+// --- 1. 权限要求 ---
+
+/// 一个权限要求，可以是单个权限，也可以是 any-of / all-of 的组合
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Requirement {
+    // 需要拥有这个权限
+    Perm(String),
+    // 满足其中任意一个即可
+    AnyOf(Vec<Requirement>),
+    // 必须全部满足
+    AllOf(Vec<Requirement>),
+}
+
+/// 单个权限
+pub fn perm(permission: &str) -> Requirement {
+    Requirement::Perm(permission.to_string())
+}
+
+/// 满足任意一个即可
+pub fn any_of<I, R>(requirements: I) -> Requirement
+where
+    I: IntoIterator<Item = R>,
+    R: Into<Requirement>,
+{
+    Requirement::AnyOf(requirements.into_iter().map(Into::into).collect())
+}
+
+/// 必须全部满足
+#[allow(dead_code)]
+pub fn all_of<I, R>(requirements: I) -> Requirement
+where
+    I: IntoIterator<Item = R>,
+    R: Into<Requirement>,
+{
+    Requirement::AllOf(requirements.into_iter().map(Into::into).collect())
+}
+
+impl From<&str> for Requirement {
+    fn from(permission: &str) -> Self {
+        perm(permission)
+    }
+}
+
+impl fmt::Display for Requirement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let join = |f: &mut fmt::Formatter<'_>, name: &str, items: &[Requirement]| {
+            write!(f, "{}(", name)?;
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    write!(f, ", ")?;
+                }
+                write!(f, "{}", item)?;
+            }
+            write!(f, ")")
+        };
+        match self {
+            Requirement::Perm(p) => write!(f, "{}", p),
+            Requirement::AnyOf(items) => join(f, "any_of", items),
+            Requirement::AllOf(items) => join(f, "all_of", items),
+        }
+    }
+}
+
+impl Requirement {
+    /// 检查用户是否满足要求；不满足时返回缺少的权限描述
+    pub fn check(&self, user: &CurrentUser) -> Result<(), String> {
+        match self {
+            Requirement::Perm(p) => {
+                if has_permission(user, p) {
+                    Ok(())
+                } else {
+                    Err(p.clone())
+                }
+            }
+            Requirement::AnyOf(items) => {
+                if items.iter().any(|item| item.check(user).is_ok()) {
+                    Ok(())
+                } else {
+                    Err(self.to_string())
+                }
+            }
+            Requirement::AllOf(items) => items.iter().try_for_each(|item| item.check(user)),
+        }
+    }
+
+    /// 检查用户是否满足要求，不满足时返回 403 业务错误
+    pub fn authorize(&self, user: &CurrentUser) -> Result<(), AppError> {
+        self.check(user).map_err(|missing| {
+            warn!(
+                "权限拒绝：用户 {} (ID: {}) 缺少权限 '{}'",
+                user.username, user.id, missing
+            );
+            ServiceError::Forbidden(format!("缺少权限: {}", missing)).into()
+        })
+    }
+}
+
+/// 判断用户是否拥有某个权限
+///
+/// 用户的权限中可以包含通配符：`kms_*` 匹配所有以 `kms_` 开头的权限，`*` 匹配任意权限。
+/// 角色已在认证阶段按 `rbac` 配置展开 (见 `middleware::rbac`)。
+pub fn has_permission(user: &CurrentUser, required: &str) -> bool {
+    user.permissions.contains(required)
+}
+
+// --- 2. 审计清单 ---
+
+/// 审计清单中的一项：哪个路由需要什么权限
+#[derive(Debug, Clone, Serialize)]
+pub struct GuardedRoute {
+    pub route: String,
+    pub requirement: String,
+}
+
+/// 权限守卫的审计清单 (保存在 `AppState.permissions` 中)
+///
+/// 路由在声明时 (`guarded_route`) 就登记完整路径，启动后即可审计，不依赖是否收到过请求。
+/// 以 `METHOD /path` 为键，重复组装路由不会产生重复条目。
+#[derive(Debug, Clone, Default)]
+pub struct PermissionRegistry {
+    // 当前作用域的 nest 前缀 (根作用域为空)
+    prefix: String,
+    routes: Arc<RwLock<BTreeMap<String, String>>>,
+}
+
+impl PermissionRegistry {
+    /// 返回 nest 到 `path` 下的子作用域 (与父作用域共享同一份清单)
+    pub fn nest(&self, path: &str) -> Self {
+        Self {
+            prefix: format!("{}{}", self.prefix, path.trim_end_matches('/')),
+            routes: self.routes.clone(),
+        }
+    }
+
+    /// 登记一个路由的权限要求
+    fn register(&self, method: &Method, path: &str, requirement: &Requirement) {
+        let path = match (self.prefix.as_str(), path) {
+            ("", path) => path.to_string(),
+            (prefix, "/") => prefix.to_string(),
+            (prefix, path) => format!("{}{}", prefix, path),
+        };
+        if let Ok(mut routes) = self.routes.write() {
+            routes.insert(format!("{} {}", method, path), requirement.to_string());
+        }
+    }
+
+    /// 列出所有登记的权限守卫 (按路由排序)
+    pub fn routes(&self) -> Vec<GuardedRoute> {
+        let Ok(routes) = self.routes.read() else {
+            return Vec::new();
+        };
+        routes
+            .iter()
+            .map(|(route, requirement)| GuardedRoute {
+                route: route.clone(),
+                requirement: requirement.clone(),
+            })
+            .collect()
+    }
+}
+
+// --- 3. Layer / Service ---
+
+/// 权限守卫 Layer (配合 `route_layer` 使用)
+#[derive(Debug, Clone)]
+pub struct RequirePermissionLayer {
+    requirement: Arc<Requirement>,
+}
+
+/// 创建一个权限守卫 Layer (不会登记到审计清单，推荐使用 `guarded_route`)
+pub fn require(requirement: Requirement) -> RequirePermissionLayer {
+    RequirePermissionLayer {
+        requirement: Arc::new(requirement),
+    }
+}
+
+/// 创建一个只需要单个权限的守卫 Layer
+#[allow(dead_code)]
+pub fn require_perm(permission: &str) -> RequirePermissionLayer {
+    require(perm(permission))
+}
+
+impl<S> Layer<S> for RequirePermissionLayer {
+    type Service = RequirePermission<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RequirePermission {
+            inner,
+            requirement: self.requirement.clone(),
+        }
+    }
+}
+
+/// 权限守卫 Service：从请求 extensions 中取出认证中间件放入的 `CurrentUser` 并检查权限
+#[derive(Debug, Clone)]
+pub struct RequirePermission<S> {
+    inner: S,
+    requirement: Arc<Requirement>,
+}
+
+impl<S> Service<Request> for RequirePermission<S>
+where
+    S: Service<Request, Response = Response, Error = Infallible> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, Infallible>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        let result = match req.extensions().get::<Arc<CurrentUser>>() {
+            Some(user) => self.requirement.authorize(user),
+            // 守卫必须挂在认证中间件之后，拿不到用户说明请求未认证
+            None => Err(ServiceError::Unauthorized.into()),
+        };
+
+        if let Err(e) = result {
+            return Box::pin(async move { Ok(e.into_response()) });
+        }
+
+        // 标准做法：用 clone 出来的 inner 替换已 ready 的 inner
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        Box::pin(async move { inner.call(req).await })
+    }
+}
+
+// --- 4. MethodRouter / Router 扩展 ---
+
+/// 为 `MethodRouter` 增加 `.require(requirement)`：只挂载权限守卫，不登记到审计清单
+pub trait RequirePermissionExt {
+    /// # Arguments
+    /// * `requirement` - 权限要求
+    fn require(self, requirement: Requirement) -> Self;
+}
+
+impl<S> RequirePermissionExt for MethodRouter<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    fn require(self, requirement: Requirement) -> Self {
+        self.route_layer(require(requirement))
+    }
+}
+
+/// 为 `Router` 增加带审计登记的路由声明
+pub trait GuardedRouterExt<S> {
+    /// 声明一个需要权限的路由：挂载守卫，并把 `METHOD /完整路径` 登记到审计清单
+    ///
+    /// # Arguments
+    /// * `guards` - 当前作用域的审计清单 (由 `nest_guarded` 传入，带 nest 前缀)
+    /// * `method` - HTTP 方法
+    /// * `path` - 路由路径 (相对当前作用域)
+    /// * `handler` - 处理器
+    /// * `requirement` - 权限要求
+    fn guarded_route<H, T>(
+        self,
+        guards: &PermissionRegistry,
+        method: Method,
+        path: &str,
+        handler: H,
+        requirement: Requirement,
+    ) -> Self
+    where
+        H: Handler<T, S>,
+        T: 'static;
+
+    /// 与 `Router::nest` 相同，同时把 nest 前缀传给子路由的审计清单作用域
+    fn nest_guarded<F>(self, path: &str, guards: &PermissionRegistry, routes: F) -> Self
+    where
+        F: FnOnce(&PermissionRegistry) -> Router<S>;
+}
+
+impl<S> GuardedRouterExt<S> for Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    fn guarded_route<H, T>(
+        self,
+        guards: &PermissionRegistry,
+        method: Method,
+        path: &str,
+        handler: H,
+        requirement: Requirement,
+    ) -> Self
+    where
+        H: Handler<T, S>,
+        T: 'static,
+    {
+        let filter = MethodFilter::try_from(method.clone())
+            .unwrap_or_else(|_| panic!("guarded_route 不支持的 HTTP 方法: {}", method));
+        guards.register(&method, path, &requirement);
+        self.route(path, on(filter, handler).require(requirement))
+    }
+
+    fn nest_guarded<F>(self, path: &str, guards: &PermissionRegistry, routes: F) -> Self
+    where
+        F: FnOnce(&PermissionRegistry) -> Router<S>,
+    {
+        self.nest(path, routes(&guards.nest(path)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::auth::PrincipalKind;
+    use axum::{Extension, body::Body};
+    use tower::ServiceExt;
+
+    fn user(authorities: &[&str]) -> CurrentUser {
+        CurrentUser::new(
+            "1".to_string(),
+            "alice".to_string(),
+            authorities.iter().map(|a| a.to_string()).collect(),
+            PrincipalKind::User,
+        )
+    }
+
+    #[test]
+    fn perm_matches_exact_and_wildcard() {
+        assert!(perm("kms_view").check(&user(&["kms_view"])).is_ok());
+        assert!(perm("kms_view").check(&user(&["kms_*"])).is_ok());
+        assert_eq!(perm("kms_view").check(&user(&["kms_add"])), Err("kms_view".to_string()));
+    }
+
+    #[test]
+    fn any_of_needs_one_and_all_of_needs_every() {
+        let u = user(&["kms_add"]);
+        assert!(any_of(["kms_admin", "kms_add"]).check(&u).is_ok());
+        assert_eq!(
+            any_of(["kms_admin", "kms_edit"]).check(&u),
+            Err("any_of(kms_admin, kms_edit)".to_string())
+        );
+        assert!(all_of(["kms_add"]).check(&u).is_ok());
+        assert_eq!(all_of(["kms_add", "kms_edit"]).check(&u), Err("kms_edit".to_string()));
+    }
+
+    #[tokio::test]
+    async fn guarded_route_is_registered_at_declaration() {
+        let registry = PermissionRegistry::default();
+        let build = || {
+            Router::new().nest_guarded("/items", &registry, |guards| {
+                Router::new()
+                    .guarded_route(guards, Method::GET, "/", || async { "list" }, perm("test_view"))
+                    .guarded_route(guards, Method::GET, "/{id}", || async { "ok" }, perm("test_view"))
+                    .guarded_route(
+                        guards,
+                        Method::DELETE,
+                        "/{id}",
+                        || async { "deleted" },
+                        any_of(["test_del", "test_del_own"]),
+                    )
+            })
+        };
+        let app = build();
+        // 重复组装不会产生重复条目
+        let _ = build();
+
+        // 尚未收到任何请求，清单中已经有完整路径
+        let routes: Vec<(String, String)> = registry
+            .routes()
+            .into_iter()
+            .map(|r| (r.route, r.requirement))
+            .collect();
+        assert_eq!(
+            routes,
+            vec![
+                ("DELETE /items/{id}".to_string(), "any_of(test_del, test_del_own)".to_string()),
+                ("GET /items".to_string(), "test_view".to_string()),
+                ("GET /items/{id}".to_string(), "test_view".to_string()),
+            ]
+        );
+
+        // 守卫照常生效：同一路径的不同方法各自检查
+        let app = app.layer(Extension(Arc::new(user(&["test_view"]))));
+        let response = app
+            .clone()
+            .oneshot(Request::get("/items/42").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert!(response.status().is_success());
+        let response = app
+            .oneshot(Request::delete("/items/42").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), axum::http::StatusCode::FORBIDDEN);
+    }
+}
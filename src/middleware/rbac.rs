@@ -0,0 +1,210 @@
+// src/middleware/rbac.rs
+// 角色 / 权限模型
+//
+// - `PermissionSet`: HashSet 支撑的权限集合 (精确匹配 O(1)，通配符单独存放)
+// - 角色展开：认证得到的 authorities 中的角色 (如 `ROLE_admin`) 按 Nacos 配置 `rbac.roles` 展开为权限
+// - 资源级检查：例如“只能编辑自己创建的 app access”，基于资源的 `create_by`
+
+use crate::config::app_specific::RbacConfig;
+use crate::errors::{AppError, ServiceError};
+use crate::middleware::auth::CurrentUser;
+use crate::models::kms_app_access;
+use std::collections::HashSet;
+use tracing::warn;
+use wildmatch::WildMatch;
+
+/// Spring Security 约定的角色前缀
+pub const ROLE_PREFIX: &str = "ROLE_";
+/// “仅限本人创建的资源”权限后缀，例如 `kms_kmsAppAccess_edit_own`
+pub const OWN_SUFFIX: &str = "_own";
+
+// --- 1. 权限集合 ---
+
+/// 权限集合：精确权限放在 HashSet 中，带通配符的权限 (如 `kms_*`) 单独匹配
+#[derive(Debug, Clone, Default)]
+pub struct PermissionSet {
+    exact: HashSet<String>,
+    wildcards: Vec<WildMatch>,
+}
+
+impl PermissionSet {
+    /// 加入一个权限
+    pub fn insert(&mut self, permission: &str) {
+        if permission.contains(['*', '?']) {
+            self.wildcards.push(WildMatch::new(permission));
+        } else {
+            self.exact.insert(permission.to_string());
+        }
+    }
+
+    /// 是否拥有某个权限 (先查 HashSet，再尝试通配符)
+    pub fn contains(&self, required: &str) -> bool {
+        self.exact.contains(required) || self.wildcards.iter().any(|w| w.matches(required))
+    }
+}
+
+impl<S: AsRef<str>> FromIterator<S> for PermissionSet {
+    fn from_iter<I: IntoIterator<Item = S>>(iter: I) -> Self {
+        let mut set = PermissionSet::default();
+        for permission in iter {
+            set.insert(permission.as_ref());
+        }
+        set
+    }
+}
+
+// --- 2. 角色展开 ---
+
+/// 根据 `rbac.roles` 配置展开用户的角色和权限
+///
+/// 以下 authority 被视为角色：
+/// - 以 `ROLE_` 开头的 (Spring Security 约定)
+/// - 在 `rbac.roles` 中有配置的
+///
+/// 角色在配置中既可以写成 `ROLE_admin` 也可以写成 `admin`。
+/// authority 本身总是会保留在权限集合中。
+pub fn apply_roles(user: &mut CurrentUser, config: Option<&RbacConfig>) {
+    let mut roles = Vec::new();
+    let mut permissions: PermissionSet = user.authorities.iter().collect();
+
+    for authority in &user.authorities {
+        let role_name = authority.strip_prefix(ROLE_PREFIX).unwrap_or(authority);
+        let granted = config.and_then(|c| {
+            c.roles
+                .get(authority.as_str())
+                .or_else(|| c.roles.get(role_name))
+        });
+
+        if authority.starts_with(ROLE_PREFIX) || granted.is_some() {
+            roles.push(role_name.to_string());
+        }
+        for permission in granted.into_iter().flatten() {
+            permissions.insert(permission);
+        }
+    }
+
+    user.roles = roles;
+    user.permissions = permissions;
+}
+
+// --- 3. 资源级检查 ---
+
+/// 有“创建人”的资源
+pub trait OwnedResource {
+    /// 资源的创建人 (与 `CurrentUser.username` 比较)
+    fn owner(&self) -> &str;
+}
+
+impl OwnedResource for kms_app_access::Model {
+    fn owner(&self) -> &str {
+        &self.create_by
+    }
+}
+
+/// 资源级权限检查
+///
+/// - 拥有 `permission` (例如 `kms_kmsAppAccess_edit`)：可以操作任意资源
+/// - 只拥有 `permission` + `_own` (例如 `kms_kmsAppAccess_edit_own`)：只能操作自己创建的资源
+///
+/// 拒绝时返回 `ServiceError::Forbidden`，消息中带上缺少的权限。
+pub fn authorize_owned<R: OwnedResource>(
+    user: &CurrentUser,
+    resource: &R,
+    permission: &str,
+) -> Result<(), AppError> {
+    if user.permissions.contains(permission) {
+        return Ok(());
+    }
+
+    let own_permission = format!("{}{}", permission, OWN_SUFFIX);
+    if user.permissions.contains(&own_permission) && resource.owner() == user.username {
+        return Ok(());
+    }
+
+    warn!(
+        "权限拒绝：用户 {} (ID: {}) 缺少权限 '{}' (资源创建人: {})",
+        user.username,
+        user.id,
+        permission,
+        resource.owner()
+    );
+    Err(ServiceError::Forbidden(format!("缺少权限: {}", permission)).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::auth::PrincipalKind;
+    use std::collections::HashMap;
+
+    struct Doc(&'static str);
+
+    impl OwnedResource for Doc {
+        fn owner(&self) -> &str {
+            self.0
+        }
+    }
+
+    fn user(authorities: &[&str]) -> CurrentUser {
+        CurrentUser::new(
+            "1".to_string(),
+            "alice".to_string(),
+            authorities.iter().map(|a| a.to_string()).collect(),
+            PrincipalKind::User,
+        )
+    }
+
+    #[test]
+    fn permission_set_matches_exact_and_wildcards() {
+        let set: PermissionSet = ["kms_view", "sys_*", "app_?"].into_iter().collect();
+        assert!(set.contains("kms_view"));
+        assert!(!set.contains("kms_edit"));
+        assert!(set.contains("sys_admin_audit"));
+        assert!(set.contains("app_1"));
+        assert!(!set.contains("app_12"));
+        assert!(!PermissionSet::default().contains("kms_view"));
+    }
+
+    #[test]
+    fn apply_roles_expands_configured_roles() {
+        let config = RbacConfig {
+            roles: HashMap::from([
+                ("admin".to_string(), vec!["kms_*".to_string()]),
+                ("ROLE_auditor".to_string(), vec!["sys_admin_audit".to_string()]),
+                ("ops".to_string(), vec!["ops_restart".to_string()]),
+            ]),
+        };
+        let mut u = user(&["ROLE_admin", "ROLE_auditor", "ops", "ROLE_guest", "kms_view"]);
+        apply_roles(&mut u, Some(&config));
+
+        assert_eq!(u.roles, vec!["admin", "auditor", "ops", "guest"]);
+        assert!(u.permissions.contains("kms_kmsAppAccess_add"));
+        assert!(u.permissions.contains("sys_admin_audit"));
+        assert!(u.permissions.contains("ops_restart"));
+        // authority 本身保留
+        assert!(u.permissions.contains("ROLE_guest"));
+        assert!(!u.permissions.contains("sys_other"));
+    }
+
+    #[test]
+    fn apply_roles_without_config_keeps_authorities() {
+        let mut u = user(&["ROLE_admin", "kms_view"]);
+        apply_roles(&mut u, None);
+        assert_eq!(u.roles, vec!["admin"]);
+        assert!(u.permissions.contains("kms_view"));
+        assert!(!u.permissions.contains("kms_edit"));
+    }
+
+    #[test]
+    fn authorize_owned_checks_owner_for_own_permission() {
+        let own = user(&["kms_edit_own"]);
+        assert!(authorize_owned(&own, &Doc("alice"), "kms_edit").is_ok());
+        assert!(authorize_owned(&own, &Doc("bob"), "kms_edit").is_err());
+
+        let any = user(&["kms_edit"]);
+        assert!(authorize_owned(&any, &Doc("bob"), "kms_edit").is_ok());
+
+        let none = user(&["kms_view"]);
+        assert!(authorize_owned(&none, &Doc("alice"), "kms_edit").is_err());
+    }
+}
@@ -0,0 +1,131 @@
+// src/middleware/request_context.rs
+// 入站请求上下文 (request id、W3C trace context、调用方的 Authorization)
+//
+// - request id：沿用调用方传入的 `X-Request-Id` (格式不合法时忽略)，没有则生成一个；
+//   整个请求都在带 `request_id` 字段的 tracing span 中处理，响应头会回写 `X-Request-Id`，
+//   错误响应体 (`AppError::into_response`) 中也会带上 `request_id`。
+// - 该 span 同时是 OpenTelemetry 的 server span，父 span 取自入站的 traceparent (见 setup/telemetry.rs)。
+// - 中间件把上下文放进 tokio task-local，处理该请求期间发起的出站调用
+//   (`clients::service_client`) 可以通过 `request_context::current()` 读取并透传。
+//   注意：`tokio::spawn` 出去的任务不会继承上下文。
+
+use crate::setup::telemetry;
+use axum::{
+    extract::{MatchedPath, Request},
+    http::{HeaderMap, HeaderName, HeaderValue, header::AUTHORIZATION},
+    middleware::Next,
+    response::Response,
+};
+use rand::Rng;
+use tracing::{Instrument, field::Empty};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+/// 请求 ID Header
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+/// W3C Trace Context Header
+pub const TRACEPARENT_HEADER: &str = "traceparent";
+pub const TRACESTATE_HEADER: &str = "tracestate";
+
+/// 接受的调用方 request id 最大长度
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+tokio::task_local! {
+    static REQUEST_CONTEXT: RequestContext;
+}
+
+/// 当前入站请求的上下文
+#[derive(Debug, Clone, Default)]
+pub struct RequestContext {
+    pub request_id: Option<String>,
+    pub traceparent: Option<String>,
+    pub tracestate: Option<String>,
+    // 调用方的 `Authorization: Bearer ...` (原样保存)
+    pub authorization: Option<String>,
+}
+
+impl RequestContext {
+    /// 从入站请求的 Header 中提取上下文
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let get = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .filter(|value| !value.is_empty())
+                .map(str::to_string)
+        };
+
+        RequestContext {
+            request_id: get(REQUEST_ID_HEADER),
+            traceparent: get(TRACEPARENT_HEADER),
+            tracestate: get(TRACESTATE_HEADER),
+            authorization: get(AUTHORIZATION.as_str()).filter(|value| value.starts_with("Bearer ")),
+        }
+    }
+}
+
+/// 调用方传入的 request id 是否可用 (非空、不超长、只包含可见 ASCII 字符)
+fn is_valid_request_id(id: &str) -> bool {
+    !id.is_empty() && id.len() <= MAX_REQUEST_ID_LEN && id.bytes().all(|b| b.is_ascii_graphic())
+}
+
+/// 生成新的 request id (128 位随机数，32 位十六进制)
+fn generate_request_id() -> String {
+    hex::encode(rand::rng().random::<[u8; 16]>())
+}
+
+/// 获取当前请求的上下文 (不在请求处理流程中时返回 None)
+pub fn current() -> Option<RequestContext> {
+    REQUEST_CONTEXT.try_with(Clone::clone).ok()
+}
+
+/// 获取当前请求的 request id
+pub fn current_request_id() -> Option<String> {
+    current().and_then(|context| context.request_id)
+}
+
+/// 请求上下文中间件：确定 request id，并在整个请求处理期间设置 task-local 上下文和 tracing span
+pub async fn mw_request_context(req: Request, next: Next) -> Response {
+    let mut context = RequestContext::from_headers(req.headers());
+    let request_id = context
+        .request_id
+        .take()
+        .filter(|id| is_valid_request_id(id))
+        .unwrap_or_else(generate_request_id);
+    context.request_id = Some(request_id.clone());
+
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string());
+    let span = tracing::info_span!(
+        "request",
+        request_id = %request_id,
+        otel.name = %format!("{} {}", req.method(), route.as_deref().unwrap_or(req.uri().path())),
+        otel.kind = "server",
+        http.request.method = %req.method(),
+        http.route = route.as_deref(),
+        url.path = req.uri().path(),
+        http.response.status_code = Empty,
+        otel.status_code = Empty,
+    );
+    // 接上游的链路 (没有 traceparent 时为新的根 span)
+    let _ = span.set_parent(telemetry::extract_parent(req.headers()));
+
+    let mut response = REQUEST_CONTEXT
+        .scope(context, next.run(req))
+        .instrument(span.clone())
+        .await;
+
+    span.record("http.response.status_code", response.status().as_u16());
+    if response.status().is_server_error() {
+        span.record("otel.status_code", "ERROR");
+    }
+
+    // 回写 X-Request-Id (已校验为可见 ASCII，不会失败)
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response
+            .headers_mut()
+            .insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+    }
+    response
+}
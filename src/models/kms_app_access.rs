@@ -18,6 +18,9 @@ pub struct Model {
     #[serde(skip_deserializing)] // ID 通常由数据库生成
     pub id: i64, // 对应 bigint(20) NOT NULL
     pub access_info_id: i64, // 对应 bigint(20) NOT NULL
+    // --- 修改点 ---
+    // 只保存访问密钥的 SHA-256 摘要，明文只在创建/轮换时返回一次，因此不序列化
+    #[serde(skip_serializing)]
     pub app_access_key: String, // 对应 varchar(255) NOT NULL
     pub name: String, // 对应 varchar(255) NOT NULL
     pub mark: Option<String>, // 对应 varchar(50) DEFAULT NULL
@@ -34,8 +37,30 @@ pub struct Model {
 
     pub del_flag: String, // 对应 char(1) NOT NULL
     pub show_id: Option<String>, // 对应 varchar(255) DEFAULT NULL
+
+    // --- 新增：访问密钥轮换相关字段 ---
+    // 明文密钥的展示前缀 (例如 `ak_3f9a1c2e`)，用于在界面上辨认密钥
+    pub key_prefix: Option<String>, // 对应 varchar(32) DEFAULT NULL
+    // 轮换前旧密钥的摘要，在宽限期内旧密钥仍然有效
+    #[serde(skip_serializing)]
+    pub prev_app_access_key: Option<String>, // 对应 varchar(255) DEFAULT NULL
+    // 旧密钥的失效时间
+    pub prev_key_expire_time: Option<DateTime<Utc>>, // 对应 timestamp NULL DEFAULT NULL
 }
 
+// --- 新增：del_flag 取值 (逻辑删除标记) ---
+/// 正常 (未删除)
+pub const DEL_FLAG_NORMAL: &str = "0";
+/// 已删除 (逻辑删除)
+pub const DEL_FLAG_DELETED: &str = "1";
+
+// --- 新增：status 取值 ---
+/// 启用
+pub const STATUS_ENABLED: i8 = 1;
+/// 停用
+#[allow(dead_code)]
+pub const STATUS_DISABLED: i8 = 0;
+
 /// SeaORM 相关的 ActiveModel 行为
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {}
@@ -1,10 +1,18 @@
 // src/repository/kms_app_access_repo.rs
 // 负责 `kms_app_access` 表的数据库访问逻辑
 
-use crate::models::kms_app_access::{self, Entity as KmsAppAccess}; // 导入实体
-use sea_orm::{DatabaseConnection, DbErr, EntityTrait, QueryFilter, ColumnTrait};
+use crate::dto::kms_app_access_dto::{AppAccessListQuery, NameMatch};
+use crate::models::kms_app_access::{
+    self, Entity as KmsAppAccess, DEL_FLAG_DELETED, DEL_FLAG_NORMAL, STATUS_ENABLED,
+}; // 导入实体
+use chrono::{DateTime, Utc};
+use sea_orm::sea_query::LikeExpr;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, Condition, DatabaseConnection, DbErr, EntityTrait, QueryFilter,
+    Select,
+};
 
-/// 根据主键 ID 查找 KmsAppAccess
+/// 根据主键 ID 查找 KmsAppAccess (已逻辑删除的记录不会被返回)
 ///
 /// # Arguments
 /// * `db` - `DatabaseConnection` (数据库连接池)
@@ -13,7 +21,25 @@ pub async fn find_by_id(
     db: &DatabaseConnection,
     id: i64,
 ) -> Result<Option<kms_app_access::Model>, DbErr> {
-    KmsAppAccess::find_by_id(id).one(db).await
+    KmsAppAccess::find_by_id(id)
+        .filter(kms_app_access::Column::DelFlag.eq(DEL_FLAG_NORMAL))
+        .one(db)
+        .await
+}
+
+/// 根据主键 ID 查找 *已被逻辑删除* 的 KmsAppAccess (用于恢复)
+///
+/// # Arguments
+/// * `db` - `DatabaseConnection`
+/// * `id` - 要查找的主键 ID
+pub async fn find_deleted_by_id(
+    db: &DatabaseConnection,
+    id: i64,
+) -> Result<Option<kms_app_access::Model>, DbErr> {
+    KmsAppAccess::find_by_id(id)
+        .filter(kms_app_access::Column::DelFlag.eq(DEL_FLAG_DELETED))
+        .one(db)
+        .await
 }
 
 /// (示例) 根据 name 查找 KmsAppAccess
@@ -21,13 +47,166 @@ pub async fn find_by_id(
 /// # Arguments
 /// * `db` - `DatabaseConnection`
 /// * `name` - 要查找的应用名称
-#[allow(dead_code)] 
+#[allow(dead_code)]
 pub async fn find_by_name(
     db: &DatabaseConnection,
     name: &str,
 ) -> Result<Option<kms_app_access::Model>, DbErr> {
     KmsAppAccess::find()
         .filter(kms_app_access::Column::Name.eq(name))
+        .filter(kms_app_access::Column::DelFlag.eq(DEL_FLAG_NORMAL))
         .one(db)
         .await
 }
+
+/// 根据访问密钥摘要查找 *可用于认证* 的 KmsAppAccess
+///
+/// 命中条件：未删除、已启用，且摘要等于当前密钥，
+/// 或等于仍在宽限期内 (`prev_key_expire_time > now`) 的旧密钥。
+///
+/// # Arguments
+/// * `db` - `DatabaseConnection`
+/// * `key_hash` - 明文密钥的摘要
+/// * `now` - 当前时间 (用于判断旧密钥是否过期)
+pub async fn find_active_by_key_hash(
+    db: &DatabaseConnection,
+    key_hash: &str,
+    now: DateTime<Utc>,
+) -> Result<Option<kms_app_access::Model>, DbErr> {
+    KmsAppAccess::find()
+        .filter(kms_app_access::Column::DelFlag.eq(DEL_FLAG_NORMAL))
+        .filter(kms_app_access::Column::Status.eq(STATUS_ENABLED))
+        .filter(
+            Condition::any()
+                .add(kms_app_access::Column::AppAccessKey.eq(key_hash))
+                .add(
+                    Condition::all()
+                        .add(kms_app_access::Column::PrevAppAccessKey.eq(key_hash))
+                        .add(kms_app_access::Column::PrevKeyExpireTime.gt(now)),
+                ),
+        )
+        .one(db)
+        .await
+}
+
+/// 列表允许排序的列 (密钥摘要等敏感列不在其中)
+pub const SORTABLE_COLUMNS: &[kms_app_access::Column] = &[
+    kms_app_access::Column::Id,
+    kms_app_access::Column::AccessInfoId,
+    kms_app_access::Column::Name,
+    kms_app_access::Column::Status,
+    kms_app_access::Column::CreateTime,
+    kms_app_access::Column::UpdateTime,
+];
+
+/// LIKE 的转义字符 (不用 `\`：MySQL 字符串字面量中它本身还需要转义)
+const LIKE_ESCAPE: char = '!';
+
+/// 辅助函数：转义 LIKE 模式中的通配符 (`%`、`_`) 和转义字符本身
+fn escape_like(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '%' | '_') || c == LIKE_ESCAPE {
+            escaped.push(LIKE_ESCAPE);
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// 根据列表过滤条件构建查询 (不含分页/排序，交给 `utils::pagination::paginate`)
+///
+/// 已逻辑删除的记录总是被排除。
+///
+/// # Arguments
+/// * `filter` - 列表过滤条件
+pub fn select_by_filter(filter: &AppAccessListQuery) -> Select<KmsAppAccess> {
+    let mut select = KmsAppAccess::find().filter(kms_app_access::Column::DelFlag.eq(DEL_FLAG_NORMAL));
+
+    if let Some(name) = filter.name.as_deref() {
+        // 用户输入中的 `%` / `_` 按字面匹配，不作为通配符
+        let name = escape_like(name);
+        let pattern = match filter.name_match.unwrap_or_default() {
+            NameMatch::Prefix => format!("{}%", name),
+            NameMatch::Contains => format!("%{}%", name),
+        };
+        select = select.filter(
+            kms_app_access::Column::Name.like(LikeExpr::new(pattern).escape(LIKE_ESCAPE)),
+        );
+    }
+    if let Some(status) = filter.status {
+        select = select.filter(kms_app_access::Column::Status.eq(status));
+    }
+    if let Some(access_info_id) = filter.access_info_id {
+        select = select.filter(kms_app_access::Column::AccessInfoId.eq(access_info_id));
+    }
+    if let Some(from) = filter.create_time_from {
+        select = select.filter(kms_app_access::Column::CreateTime.gte(from));
+    }
+    if let Some(to) = filter.create_time_to {
+        select = select.filter(kms_app_access::Column::CreateTime.lte(to));
+    }
+
+    select
+}
+
+/// 插入一条新的 KmsAppAccess，并返回插入后的完整记录 (包含数据库生成的 ID)
+///
+/// # Arguments
+/// * `db` - `DatabaseConnection`
+/// * `model` - 待插入的 ActiveModel
+pub async fn insert(
+    db: &DatabaseConnection,
+    model: kms_app_access::ActiveModel,
+) -> Result<kms_app_access::Model, DbErr> {
+    model.insert(db).await
+}
+
+/// 更新一条 KmsAppAccess (只会更新 ActiveModel 中被 `Set` 的字段)
+///
+/// 逻辑删除 / 恢复也是通过这个函数修改 `del_flag` 来实现的，
+/// 本仓库 *不会* 物理删除任何记录。
+///
+/// # Arguments
+/// * `db` - `DatabaseConnection`
+/// * `model` - 待更新的 ActiveModel (主键必须存在)
+pub async fn update(
+    db: &DatabaseConnection,
+    model: kms_app_access::ActiveModel,
+) -> Result<kms_app_access::Model, DbErr> {
+    model.update(db).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sea_orm::{DbBackend, QueryTrait};
+
+    fn where_clause(name: &str, name_match: NameMatch) -> String {
+        let filter = AppAccessListQuery {
+            name: Some(name.to_string()),
+            name_match: Some(name_match),
+            ..Default::default()
+        };
+        let sql = select_by_filter(&filter).build(DbBackend::MySql).to_string();
+        sql.split_once(" WHERE ").unwrap().1.to_string()
+    }
+
+    #[test]
+    fn escape_like_escapes_wildcards() {
+        assert_eq!(escape_like("order"), "order");
+        assert_eq!(escape_like("100%_a!b"), "100!%!_a!!b");
+    }
+
+    #[test]
+    fn name_filter_matches_wildcards_literally() {
+        assert!(
+            where_clause("order_", NameMatch::Prefix)
+                .ends_with("`kms_app_access`.`name` LIKE 'order!_%' ESCAPE '!'")
+        );
+        assert!(
+            where_clause("%", NameMatch::Contains)
+                .ends_with("`kms_app_access`.`name` LIKE '%!%%' ESCAPE '!'")
+        );
+    }
+}
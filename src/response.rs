@@ -22,6 +22,10 @@ where
     // 从 JSON 序列化中完全省略 data 字段，更干净。
     #[serde(skip_serializing_if = "Option::is_none")]
     data: Option<T>,
+
+    // --- 新增：请求 ID (只在错误响应中返回，便于按 ID 检索服务端日志) ---
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request_id: Option<String>,
 }
 
 // --- 构造函数 ---
@@ -33,6 +37,7 @@ impl<T: Serialize> ApiResponse<T> {
             code: 0, // 默认成功码 (你也可以用 0 或其他)
             msg: "success".to_string(),
             data: Some(data),
+            request_id: None,
         }
     }
 
@@ -44,6 +49,32 @@ impl<T: Serialize> ApiResponse<T> {
             code, // 使用传入的自定义业务码
             msg,
             data: None,
+            request_id: None,
         }
     }
-}
\ No newline at end of file
+
+    /// 附带请求 ID
+    pub fn with_request_id(mut self, request_id: Option<String>) -> Self {
+        self.request_id = request_id;
+        self
+    }
+}
+
+// --- 新增：通用分页结构 ---
+
+/// 统一的分页响应结构 (放在 ApiResponse 的 data 中返回)
+/// 与具体实体无关，任何 `models` 下的实体都可以复用
+#[derive(Debug, Serialize)]
+pub struct PageResult<T>
+where
+    T: Serialize,
+{
+    // 当前页的数据
+    pub items: Vec<T>,
+    // 满足条件的总记录数
+    pub total: u64,
+    // 当前页码 (从 1 开始)
+    pub page: u64,
+    // 每页大小
+    pub size: u64,
+}
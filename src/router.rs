@@ -8,20 +8,27 @@ use axum::{
     Router,
 };
 // 导入我们自定义的认证中间件
-use crate::middleware::auth::mw_require_auth;
+use crate::middleware::auth::{mw_require_auth, mw_require_auth_or_app_key};
+use crate::middleware::permission::GuardedRouterExt;
 
 
 /// 创建并组装所有的 Axum 路由
 pub fn create_router(app_state: AppState) -> Router {
     // --- 1. 构建需要“认证”的路由 ---
-    // 这些路由会先经过 mw_require_auth 中间件
+    // 这些路由会先经过 mw_require_auth 中间件 (只接受用户的 Bearer Token)
     let protected_routes = Router::new()
-        .nest("/hello", crate::handlers::hello_handler::routes())
-        .nest(
+        // 带权限守卫的路由通过 nest_guarded 嵌套，审计清单中登记的是带前缀的完整路径
+        .nest_guarded(
             "/app-access",
-            crate::handlers::kms_app_access_handler::routes(),
+            &app_state.permissions,
+            crate::handlers::kms_app_access_handler::routes,
+        )
+        .nest("/auth", crate::handlers::auth_handler::routes())
+        .nest_guarded(
+            "/admin",
+            &app_state.permissions,
+            crate::handlers::admin_handler::routes,
         )
-        .nest("/redis-test", crate::handlers::redis_handler::routes())
         // (将来所有需要登录的业务路由都加在这里)
         
         // --- 核心修改点 ---
@@ -31,26 +38,58 @@ pub fn create_router(app_state: AppState) -> Router {
             mw_require_auth    // 👈 2. 传递我们的中间件函数
         ));
 
+    // --- 1.1 同时接受“用户 Token”和“应用密钥”的路由 ---
+    // 这些路由会经过 mw_require_auth_or_app_key 中间件：
+    // 带 `Authorization: Bearer` 时按用户认证，否则按 `X-App-Access-Key` 认证
+    let machine_accessible_routes = Router::new()
+        .nest("/hello", crate::handlers::hello_handler::routes())
+        .nest("/redis-test", crate::handlers::redis_handler::routes())
+        // (将来允许服务间调用的路由加在这里)
+        .route_layer(axum_middleware::from_fn_with_state(
+            app_state.clone(),
+            mw_require_auth_or_app_key,
+        ));
+
 
     // --- 2. 构建“公共”路由 ---
     // 这些路由 *不* 需要认证
+    // /metrics 不在这里：它只在独立的管理端口 (METRICS_ADDR) 上提供，见 create_admin_router
     let public_routes = Router::new()
-        .route("/", get(crate::handlers::health_check));
-        // (将来比如 /login, /metrics, /docs 等路由放这里)
+        .route("/", get(crate::handlers::health_check))
+        // 存活 / 就绪探针 (K8s liveness / readiness)
+        .nest("/health", crate::handlers::health_handler::routes());
+        // (将来比如 /login, /docs 等路由放这里)
 
 
     // --- 3. 组装总路由 ---
     Router::new()
         .merge(public_routes) // 合并公共路由
         .merge(protected_routes) // 合并受保护的路由
+        .merge(machine_accessible_routes) // 合并同时接受应用密钥的路由
         
         // 注入共享状态 (对所有路由生效)
         // (这个 .with_state() 负责将 AppState 注入给 *Handler*，
         //  而 .route_layer(from_fn_with_state...) 负责将其注入给 *Middleware*)
-        .with_state(app_state)
+        .with_state(app_state.clone())
         
-        // 应用全局日志中间件 (对所有路由生效)
-        .layer(axum_middleware::from_fn(
+        // 应用全局访问日志中间件 (对所有路由生效；通过 Router::layer 挂载才能拿到 MatchedPath)
+        .layer(axum_middleware::from_fn_with_state(
+            app_state.clone(),
             crate::middleware::logging::log_requests,
         ))
-}
\ No newline at end of file
+
+        // 应用全局 HTTP 指标中间件 (同样需要 MatchedPath)
+        .layer(axum_middleware::from_fn_with_state(
+            app_state,
+            crate::middleware::metrics::track_metrics,
+        ))
+
+        // 请求 ID 与入站请求上下文 (最外层：日志与错误响应都能拿到 request_id，出站调用透传 Header)
+        .layer(axum_middleware::from_fn(
+            crate::middleware::request_context::mw_request_context,
+        ))
+}
+/// 创建独立管理端口 (METRICS_ADDR) 上的路由
+pub fn create_admin_router(app_state: AppState) -> Router {
+    crate::handlers::metrics_handler::routes().with_state(app_state)
+}
@@ -0,0 +1,81 @@
+// src/services/auth_service.rs
+// Bearer Token 的认证逻辑：根据 Nacos 配置 `auth.mode` 选择
+// 远程校验 (调用 Auth 服务 check_token) 或本地 JWT 校验 (JWKS)。
+
+use crate::clients::auth_client;
+use crate::config::app_specific::{AuthMode, TokenCacheConfig};
+use crate::errors::{AppError, ServiceError};
+use crate::middleware::auth::{CurrentUser, PrincipalKind};
+use crate::services::jwt_service;
+use crate::services::token_cache_service::{self, CachedIntrospection};
+use crate::state::AppState;
+
+/// 校验 Bearer Token，并转换为 CurrentUser
+///
+/// 每次调用都会读取最新的 `auth` 配置，因此在 Nacos 中切换 `mode` 无需重启。
+pub async fn authenticate_token(state: &AppState, token: &str) -> Result<CurrentUser, AppError> {
+    let auth_config = state.app_config.read().await.auth.clone().unwrap_or_default();
+
+    match auth_config.mode {
+        AuthMode::Remote => match auth_config.token_cache.filter(|cache| cache.enabled) {
+            Some(cache_config) => authenticate_remote_cached(state, &cache_config, token).await,
+            None => authenticate_remote(state, token).await,
+        },
+        AuthMode::Jwt => {
+            let jwt_config = auth_config.jwt.unwrap_or_default();
+            jwt_service::verify_token(state, &jwt_config, token).await
+        }
+    }
+}
+
+/// 先查 Redis 缓存，未命中再调用 Auth 服务，并把结果写回缓存
+///
+/// 只有“Token 无效” (Unauthorized) 会被负缓存；Auth 服务不可用等错误不缓存。
+async fn authenticate_remote_cached(
+    state: &AppState,
+    cache_config: &TokenCacheConfig,
+    token: &str,
+) -> Result<CurrentUser, AppError> {
+    match token_cache_service::get(state, token).await {
+        Some(CachedIntrospection::Valid { user }) => return Ok(user),
+        Some(CachedIntrospection::Invalid) => return Err(ServiceError::Unauthorized.into()),
+        None => {}
+    }
+
+    match introspect(state, token).await {
+        Ok((user, expires_at)) => {
+            token_cache_service::put_valid(state, cache_config, token, &user, expires_at).await;
+            Ok(user)
+        }
+        Err(AppError::Service(ServiceError::Unauthorized)) => {
+            token_cache_service::put_invalid(state, cache_config, token).await;
+            Err(ServiceError::Unauthorized.into())
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// 通过 Auth 服务的 check_token 接口校验 Token
+async fn authenticate_remote(state: &AppState, token: &str) -> Result<CurrentUser, AppError> {
+    Ok(introspect(state, token).await?.0)
+}
+
+/// 调用 check_token，返回 CurrentUser 与 Token 的过期时间 (Unix 秒，Auth 服务未返回时为 None)
+async fn introspect(state: &AppState, token: &str) -> Result<(CurrentUser, Option<i64>), AppError> {
+    // 调用封装好的 Auth 客户端
+    let auth_response = auth_client::check_token(state, token).await?;
+
+    // 将 AuthResponse 转换为 CurrentUser
+    let authorities = auth_response.user_info.authorities
+        .into_iter()
+        .map(|auth| auth.authority)
+        .collect();
+
+    let user = CurrentUser::new(
+        auth_response.user_info.user_id,
+        auth_response.user_info.username,
+        authorities,
+        PrincipalKind::User,
+    );
+    Ok((user, auth_response.exp))
+}
@@ -0,0 +1,527 @@
+// src/services/jwt_service.rs
+// 本地 JWT 校验：使用 Auth 服务发布的 JWKS (带缓存与刷新) 校验签名，
+// 并把 claims 映射为 CurrentUser。
+
+use crate::clients::auth_client;
+use crate::config::app_specific::{AppSpecificConfig, AuthMode, JwtConfig};
+use crate::errors::{AppError, ServiceError};
+use crate::middleware::auth::{CurrentUser, PrincipalKind};
+use crate::state::AppState;
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{Algorithm, DecodingKey, Validation, decode, decode_header};
+use serde_json::Value;
+use std::future::Future;
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, RwLock};
+use tracing::{info, warn};
+
+/// JWKS 默认刷新间隔
+const DEFAULT_JWKS_REFRESH_SECS: u64 = 300;
+/// 遇到未知 kid 时，两次强制刷新之间的最小间隔 (防止伪造 kid 打爆 Auth 服务)
+const MIN_FORCED_REFRESH_INTERVAL: Duration = Duration::from_secs(10);
+/// 刷新失败后，下次重试之前的间隔 (期间继续使用旧缓存，不再请求 Auth 服务)
+const FAILED_REFRESH_RETRY_INTERVAL: Duration = Duration::from_secs(30);
+/// 默认允许的时钟偏差
+const DEFAULT_LEEWAY_SECS: u64 = 30;
+/// 允许的签名算法
+const SUPPORTED_ALGORITHMS: [Algorithm; 3] = [Algorithm::RS256, Algorithm::ES256, Algorithm::HS256];
+
+/// 已缓存的 JWKS
+struct CachedJwks {
+    keys: JwkSet,
+    fetched_at: Instant,
+}
+
+/// JWKS 缓存 (存放在 AppState 中，所有请求共享)
+#[derive(Default)]
+pub struct JwksCache {
+    cached: RwLock<Option<CachedJwks>>,
+    // 保证同一时间只有一个请求在刷新 JWKS
+    refresh_lock: Mutex<()>,
+    // 最近一次刷新失败的时间 (不跨 await 持有)
+    last_failure: std::sync::Mutex<Option<Instant>>,
+}
+
+impl JwksCache {
+    /// 根据 kid 获取解码密钥
+    ///
+    /// 1. 缓存新鲜且命中 kid：直接返回
+    /// 2. 缓存过期：刷新后再查；最近刷新失败过或其他请求正在刷新时，直接使用旧缓存
+    /// 3. 未命中 kid (Auth 服务可能刚轮换了密钥)：限频强制刷新一次
+    ///
+    /// `fetch` 只在需要刷新时调用 (从 Auth 服务获取 JWKS)。
+    async fn decoding_key<F, Fut>(
+        &self,
+        config: &JwtConfig,
+        kid: Option<&str>,
+        fetch: F,
+    ) -> Result<DecodingKey, AppError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<JwkSet, AppError>>,
+    {
+        let refresh_interval =
+            Duration::from_secs(config.jwks_refresh_secs.unwrap_or(DEFAULT_JWKS_REFRESH_SECS));
+
+        {
+            let guard = self.cached.read().await;
+            if let Some(cached) = guard.as_ref()
+                && let Some(key) = find_key(&cached.keys, kid)?
+                && (cached.fetched_at.elapsed() < refresh_interval
+                    || self.recently_failed()
+                    || self.refresh_lock.try_lock().is_err())
+            {
+                return Ok(key);
+            }
+        }
+
+        self.refresh(refresh_interval, fetch).await?;
+
+        let guard = self.cached.read().await;
+        let cached = guard.as_ref().ok_or(ServiceError::Unauthorized)?;
+        find_key(&cached.keys, kid)?.ok_or_else(|| {
+            warn!("(JWT) JWKS 中找不到 kid: {:?}", kid);
+            ServiceError::Unauthorized.into()
+        })
+    }
+
+    /// 刷新 JWKS；刷新失败时如果有旧缓存则继续使用旧缓存
+    async fn refresh<F, Fut>(&self, refresh_interval: Duration, fetch: F) -> Result<(), AppError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<JwkSet, AppError>>,
+    {
+        let _refreshing = self.refresh_lock.lock().await;
+
+        // 拿到锁之后再检查一次：其他请求可能刚刚刷新过
+        if let Some(cached) = self.cached.read().await.as_ref() {
+            let age = cached.fetched_at.elapsed();
+            if age < MIN_FORCED_REFRESH_INTERVAL.min(refresh_interval) {
+                return Ok(());
+            }
+        }
+        // 刚失败过：不再请求 Auth 服务 (避免故障期间每个请求都排队等待 HTTP 超时)
+        if self.recently_failed() {
+            return Ok(());
+        }
+
+        match fetch().await {
+            Ok(keys) => {
+                info!("(JWT) JWKS 已刷新, 共 {} 个密钥", keys.keys.len());
+                self.set_last_failure(None);
+                *self.cached.write().await = Some(CachedJwks {
+                    keys,
+                    fetched_at: Instant::now(),
+                });
+                Ok(())
+            }
+            Err(e) => {
+                self.set_last_failure(Some(Instant::now()));
+                if self.cached.read().await.is_none() {
+                    return Err(e);
+                }
+                warn!(
+                    "(JWT) 刷新 JWKS 失败，{:?} 内继续使用旧缓存: {}",
+                    FAILED_REFRESH_RETRY_INTERVAL, e
+                );
+                Ok(())
+            }
+        }
+    }
+
+    /// 最近一次刷新失败是否还在重试间隔内
+    fn recently_failed(&self) -> bool {
+        self.last_failure
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .is_some_and(|at| at.elapsed() < FAILED_REFRESH_RETRY_INTERVAL)
+    }
+
+    fn set_last_failure(&self, at: Option<Instant>) {
+        *self.last_failure.lock().unwrap_or_else(|e| e.into_inner()) = at;
+    }
+}
+
+/// jwt 模式下未配置 iss / aud 时记录警告 (此时不校验对应的 claim)
+///
+/// 启动与 Nacos 配置变更时调用。
+pub fn warn_if_claims_unchecked(config: &AppSpecificConfig) {
+    let Some(auth) = config.auth.as_ref().filter(|auth| auth.mode == AuthMode::Jwt) else {
+        return;
+    };
+    let jwt = auth.jwt.as_ref();
+    if jwt.and_then(|j| j.issuer.as_deref()).is_none() {
+        warn!("(JWT) 未配置 auth.jwt.issuer，不校验 Token 的 iss");
+    }
+    if jwt.and_then(|j| j.audience.as_deref()).is_none() {
+        warn!("(JWT) 未配置 auth.jwt.audience，不校验 Token 的 aud");
+    }
+}
+
+/// 在本地校验 JWT，并将 claims 映射为 CurrentUser
+///
+/// 支持 RS256 / ES256 (JWKS) 和 HS256 (共享密钥)。
+pub async fn verify_token(
+    state: &AppState,
+    config: &JwtConfig,
+    token: &str,
+) -> Result<CurrentUser, AppError> {
+    let fetch = || auth_client::fetch_jwks(state, config.jwks_url.as_deref(), config.jwks_path.as_deref());
+    verify_with_cache(&state.jwks_cache, config, token, fetch).await
+}
+
+/// 辅助函数：`verify_token` 的实现，JWKS 的获取方式由 `fetch` 决定
+async fn verify_with_cache<F, Fut>(
+    cache: &JwksCache,
+    config: &JwtConfig,
+    token: &str,
+    fetch: F,
+) -> Result<CurrentUser, AppError>
+where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<JwkSet, AppError>>,
+{
+    let header = decode_header(token).map_err(|e| {
+        warn!("(JWT) Token 头解析失败: {}", e);
+        ServiceError::Unauthorized
+    })?;
+
+    if !SUPPORTED_ALGORITHMS.contains(&header.alg) {
+        warn!("(JWT) 不支持的签名算法: {:?}", header.alg);
+        return Err(ServiceError::Unauthorized.into());
+    }
+
+    let key = match header.alg {
+        Algorithm::HS256 => {
+            let secret = config.hs256_secret.as_deref().ok_or_else(|| {
+                warn!("(JWT) 收到 HS256 Token，但未配置 auth.jwt.hs256_secret");
+                ServiceError::Unauthorized
+            })?;
+            DecodingKey::from_secret(secret.as_bytes())
+        }
+        _ => cache.decoding_key(config, header.kid.as_deref(), fetch).await?,
+    };
+
+    let mut validation = Validation::new(header.alg);
+    validation.leeway = config.leeway_secs.unwrap_or(DEFAULT_LEEWAY_SECS);
+    match config.issuer.as_deref() {
+        Some(issuer) => validation.set_issuer(&[issuer]),
+        None => validation.iss = None,
+    }
+    match config.audience.as_deref() {
+        Some(audience) => validation.set_audience(&[audience]),
+        None => validation.validate_aud = false,
+    }
+
+    let claims = decode::<Value>(token, &key, &validation)
+        .map_err(|e| {
+            warn!("(JWT) Token 校验失败: {}", e);
+            ServiceError::Unauthorized
+        })?
+        .claims;
+
+    claims_to_user(&claims)
+}
+
+/// 辅助函数：在 JWKS 中按 kid 查找密钥；Token 没有 kid 且 JWKS 只有一个密钥时使用该密钥
+fn find_key(keys: &JwkSet, kid: Option<&str>) -> Result<Option<DecodingKey>, AppError> {
+    let jwk = match kid {
+        Some(kid) => keys.find(kid),
+        None if keys.keys.len() == 1 => keys.keys.first(),
+        None => None,
+    };
+
+    jwk.map(|jwk| {
+        DecodingKey::from_jwk(jwk).map_err(|e| {
+            warn!("(JWT) JWKS 中的密钥无法使用: {}", e);
+            AppError::from(ServiceError::Unauthorized)
+        })
+    })
+    .transpose()
+}
+
+/// 辅助函数：将 claims 映射为 CurrentUser
+///
+/// - id: `user_id` / `id` / `sub`
+/// - username: `username` / `user_name` / `preferred_username` / `sub`
+/// - 权限: `authorities` (字符串数组或 `{authority}` 对象数组)，否则取 `scope` / `scp`
+fn claims_to_user(claims: &Value) -> Result<CurrentUser, AppError> {
+    let id = first_string(claims, &["user_id", "id", "sub"]).ok_or_else(|| {
+        warn!("(JWT) Token 中缺少用户 ID (user_id / id / sub)");
+        ServiceError::Unauthorized
+    })?;
+    let username = first_string(claims, &["username", "user_name", "preferred_username", "sub"])
+        .unwrap_or_else(|| id.clone());
+
+    let authorities = match claims.get("authorities") {
+        Some(Value::Array(items)) => items
+            .iter()
+            .filter_map(|item| match item {
+                Value::String(s) => Some(s.clone()),
+                Value::Object(obj) => obj.get("authority").and_then(Value::as_str).map(str::to_string),
+                _ => None,
+            })
+            .collect(),
+        _ => match (claims.get("scope"), claims.get("scp")) {
+            (Some(Value::String(scope)), _) => scope.split_whitespace().map(str::to_string).collect(),
+            (_, Some(Value::Array(scp))) => scp
+                .iter()
+                .filter_map(Value::as_str)
+                .map(str::to_string)
+                .collect(),
+            _ => Vec::new(),
+        },
+    };
+
+    Ok(CurrentUser::new(id, username, authorities, PrincipalKind::User))
+}
+
+/// 辅助函数：按顺序取第一个存在的 claim (数字会被转换为字符串)
+fn first_string(claims: &Value, names: &[&str]) -> Option<String> {
+    names.iter().find_map(|name| match claims.get(*name) {
+        Some(Value::String(s)) if !s.is_empty() => Some(s.clone()),
+        Some(Value::Number(n)) => Some(n.to_string()),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonwebtoken::{EncodingKey, Header, encode};
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // 测试用 RSA 密钥 (只用于单元测试)
+    const PRIVATE_KEY: &[u8] = include_bytes!("testdata/jwt_rs256.pem");
+    const PUBLIC_KEY: &[u8] = include_bytes!("testdata/jwt_rs256.pub.pem");
+    const MODULUS: &str = "np0NI_T3PKUo5U6sWbCb2PuRRxKy2KyTGZmV6IcoqjHcEKmxEHKrbtQXLFs69_CgggM2t4s0RJzCYhpVFBNFlcOvndYbMFbJior64id6nTdhnHyBy1HzkN5Cp_TCFIBfCKQzuV3lC2lEGt814Yoi5ygv-r0huGoUkeDPCXsjmoqddQqj-wI3QTs49ptrCij0nhEVLcQmRKk4R7UuB1pTuTLzwN6R7AWDh-mlmuvjP1tYCMvEyD4Y9-evUOcPf6w0XG2qQmoBnSREpeWUgaF5GdAPm6Z2cIiEGxx19xmENjSxYdGlPx7CT9RZjphPweeGl-fZhNxPdj9KPsimrYiEuw";
+
+    fn jwks(kids: &[&str]) -> JwkSet {
+        let keys: Vec<Value> = kids
+            .iter()
+            .map(|kid| json!({"kty": "RSA", "kid": kid, "alg": "RS256", "use": "sig", "n": MODULUS, "e": "AQAB"}))
+            .collect();
+        serde_json::from_value(json!({ "keys": keys })).unwrap()
+    }
+
+    fn config() -> JwtConfig {
+        JwtConfig {
+            issuer: Some("https://auth.example.com".to_string()),
+            audience: Some("axum-template".to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn now() -> i64 {
+        chrono::Utc::now().timestamp()
+    }
+
+    fn claims() -> Value {
+        json!({
+            "sub": "42",
+            "username": "alice",
+            "authorities": ["kms_kmsAppAccess_view"],
+            "iss": "https://auth.example.com",
+            "aud": "axum-template",
+            "exp": now() + 600,
+        })
+    }
+
+    fn sign_rs256(kid: &str, claims: &Value) -> String {
+        let mut header = Header::new(Algorithm::RS256);
+        header.kid = Some(kid.to_string());
+        encode(&header, claims, &EncodingKey::from_rsa_pem(PRIVATE_KEY).unwrap()).unwrap()
+    }
+
+    fn sign_hs256(secret: &[u8], claims: &Value) -> String {
+        encode(&Header::new(Algorithm::HS256), claims, &EncodingKey::from_secret(secret)).unwrap()
+    }
+
+    /// 缓存一份指定时间之前获取的 JWKS
+    fn cache_with(kids: &[&str], age: Duration) -> JwksCache {
+        let cache = JwksCache::default();
+        *cache.cached.try_write().unwrap() = Some(CachedJwks {
+            keys: jwks(kids),
+            fetched_at: Instant::now().checked_sub(age).unwrap(),
+        });
+        cache
+    }
+
+    /// 记录被调用的次数，返回 `kids` 组成的 JWKS (为空时模拟 Auth 服务不可用)
+    async fn verify(
+        cache: &JwksCache,
+        config: &JwtConfig,
+        token: &str,
+        fetches: &AtomicUsize,
+        kids: &[&str],
+    ) -> Result<CurrentUser, AppError> {
+        let fetch = || {
+            fetches.fetch_add(1, Ordering::SeqCst);
+            let result = if kids.is_empty() {
+                Err(AppError::InternalError("auth service down".to_string()))
+            } else {
+                Ok(jwks(kids))
+            };
+            async move { result }
+        };
+        verify_with_cache(cache, config, token, fetch).await
+    }
+
+    fn is_unauthorized<T>(result: &Result<T, AppError>) -> bool {
+        matches!(result, Err(AppError::Service(ServiceError::Unauthorized)))
+    }
+
+    #[tokio::test]
+    async fn accepts_valid_token_and_fetches_jwks_once() {
+        let (cache, fetches) = (JwksCache::default(), AtomicUsize::new(0));
+        let token = sign_rs256("k1", &claims());
+
+        let user = verify(&cache, &config(), &token, &fetches, &["k1"]).await.unwrap();
+        assert_eq!((user.id.as_str(), user.username.as_str()), ("42", "alice"));
+        assert_eq!(user.authorities, vec!["kms_kmsAppAccess_view".to_string()]);
+
+        // 缓存新鲜时不再请求 Auth 服务
+        verify(&cache, &config(), &token, &fetches, &["k1"]).await.unwrap();
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn rejects_algorithm_confusion() {
+        let cache = cache_with(&["k1"], Duration::ZERO);
+        let fetches = AtomicUsize::new(0);
+        // 用 RSA 公钥当作 HMAC 密钥伪造的 HS256 Token
+        let forged = sign_hs256(PUBLIC_KEY, &claims());
+
+        assert!(is_unauthorized(&verify(&cache, &config(), &forged, &fetches, &["k1"]).await));
+        let with_secret = JwtConfig {
+            hs256_secret: Some("another-secret".to_string()),
+            ..config()
+        };
+        assert!(is_unauthorized(&verify(&cache, &with_secret, &forged, &fetches, &["k1"]).await));
+
+        // 不在允许列表中的算法 / alg=none
+        let mut header = Header::new(Algorithm::RS384);
+        header.kid = Some("k1".to_string());
+        let rs384 = encode(&header, &claims(), &EncodingKey::from_rsa_pem(PRIVATE_KEY).unwrap()).unwrap();
+        assert!(is_unauthorized(&verify(&cache, &config(), &rs384, &fetches, &["k1"]).await));
+        let none = "eyJhbGciOiJub25lIiwidHlwIjoiSldUIn0.eyJzdWIiOiI0MiJ9.";
+        assert!(is_unauthorized(&verify(&cache, &config(), none, &fetches, &["k1"]).await));
+
+        assert_eq!(fetches.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn hs256_uses_the_configured_secret() {
+        let (cache, fetches) = (JwksCache::default(), AtomicUsize::new(0));
+        let config = JwtConfig {
+            hs256_secret: Some("shared-secret".to_string()),
+            ..config()
+        };
+        let token = sign_hs256(b"shared-secret", &claims());
+        assert!(verify(&cache, &config, &token, &fetches, &[]).await.is_ok());
+        assert_eq!(fetches.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn rejects_expired_tokens_and_wrong_claims() {
+        let cache = cache_with(&["k1"], Duration::ZERO);
+        let fetches = AtomicUsize::new(0);
+        let with = |key: &str, value: Value| {
+            let mut claims = claims();
+            claims[key] = value;
+            sign_rs256("k1", &claims)
+        };
+
+        // 超过允许的时钟偏差 (默认 30 秒)
+        let expired = with("exp", json!(now() - 120));
+        assert!(is_unauthorized(&verify(&cache, &config(), &expired, &fetches, &["k1"]).await));
+        let within_leeway = with("exp", json!(now() - 5));
+        assert!(verify(&cache, &config(), &within_leeway, &fetches, &["k1"]).await.is_ok());
+
+        let wrong_iss = with("iss", json!("https://evil.example.com"));
+        assert!(is_unauthorized(&verify(&cache, &config(), &wrong_iss, &fetches, &["k1"]).await));
+        let wrong_aud = with("aud", json!("another-service"));
+        assert!(is_unauthorized(&verify(&cache, &config(), &wrong_aud, &fetches, &["k1"]).await));
+
+        // 签名被篡改
+        let token = sign_rs256("k1", &claims());
+        let tampered = format!("{}x", token);
+        assert!(is_unauthorized(&verify(&cache, &config(), &tampered, &fetches, &["k1"]).await));
+    }
+
+    #[tokio::test]
+    async fn unknown_kid_forces_a_rate_limited_refresh() {
+        // 缓存还新鲜 (刷新间隔 300 秒)，但距上次获取已超过强制刷新的最小间隔
+        let cache = cache_with(&["k0"], Duration::from_secs(60));
+        let fetches = AtomicUsize::new(0);
+
+        // Auth 服务轮换了密钥：未知 kid 触发一次刷新
+        let token = sign_rs256("k1", &claims());
+        assert!(verify(&cache, &config(), &token, &fetches, &["k0", "k1"]).await.is_ok());
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+
+        // 刚刷新过，伪造的 kid 不会再次触发刷新
+        let forged = sign_rs256("k9", &claims());
+        assert!(is_unauthorized(&verify(&cache, &config(), &forged, &fetches, &["k0", "k1"]).await));
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn failed_refresh_keeps_old_keys_and_backs_off() {
+        // 缓存已过期
+        let cache = cache_with(&["k1"], Duration::from_secs(600));
+        let fetches = AtomicUsize::new(0);
+        let token = sign_rs256("k1", &claims());
+
+        // Auth 服务不可用：继续使用旧缓存
+        assert!(verify(&cache, &config(), &token, &fetches, &[]).await.is_ok());
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+
+        // 失败后的重试间隔内，过期缓存与未知 kid 都不会再请求 Auth 服务
+        assert!(verify(&cache, &config(), &token, &fetches, &[]).await.is_ok());
+        let unknown = sign_rs256("k9", &claims());
+        assert!(is_unauthorized(&verify(&cache, &config(), &unknown, &fetches, &[]).await));
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn fails_without_any_cached_keys() {
+        let (cache, fetches) = (JwksCache::default(), AtomicUsize::new(0));
+        let token = sign_rs256("k1", &claims());
+        assert!(matches!(
+            verify(&cache, &config(), &token, &fetches, &[]).await,
+            Err(AppError::InternalError(_))
+        ));
+    }
+
+    #[test]
+    fn find_key_selects_by_kid() {
+        assert!(find_key(&jwks(&["k1", "k2"]), Some("k2")).unwrap().is_some());
+        assert!(find_key(&jwks(&["k1", "k2"]), Some("k3")).unwrap().is_none());
+        // 没有 kid 时只有 JWKS 中只有一个密钥才使用它
+        assert!(find_key(&jwks(&["k1"]), None).unwrap().is_some());
+        assert!(find_key(&jwks(&["k1", "k2"]), None).unwrap().is_none());
+    }
+
+    #[test]
+    fn claims_to_user_maps_ids_and_authorities() {
+        let user = claims_to_user(&json!({
+            "user_id": 7,
+            "preferred_username": "bob",
+            "authorities": [{"authority": "a"}, "b", 1],
+        }))
+        .unwrap();
+        assert_eq!((user.id.as_str(), user.username.as_str()), ("7", "bob"));
+        assert_eq!(user.authorities, vec!["a".to_string(), "b".to_string()]);
+
+        let user = claims_to_user(&json!({"sub": "s1", "scope": "read write"})).unwrap();
+        assert_eq!((user.id.as_str(), user.username.as_str()), ("s1", "s1"));
+        assert_eq!(user.authorities, vec!["read".to_string(), "write".to_string()]);
+
+        let user = claims_to_user(&json!({"sub": "s1", "scp": ["read"]})).unwrap();
+        assert_eq!(user.authorities, vec!["read".to_string()]);
+
+        assert!(is_unauthorized(&claims_to_user(&json!({"username": "x", "sub": ""}))));
+    }
+}
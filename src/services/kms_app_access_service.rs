@@ -1,10 +1,28 @@
 // src/services/kms_app_access_service.rs
 // `kms_app_access` 相关的业务逻辑
 
+use crate::dto::kms_app_access_dto::{
+    AppAccessListQuery, AppAccessWithKeyResponse, CreateAppAccessRequest, PatchAppAccessRequest,
+    RotateAppAccessKeyRequest, UpdateAppAccessRequest,
+};
 use crate::errors::{AppError,ServiceError}; // 导入我们统一的错误类型
-use crate::models::kms_app_access; // 导入实体模型
+use crate::middleware::auth::CurrentUser;
+use crate::middleware::rbac;
+use crate::models::kms_app_access::{self, DEL_FLAG_DELETED, DEL_FLAG_NORMAL, STATUS_ENABLED}; // 导入实体模型
 use crate::repository::kms_app_access_repo; // 导入 repository
+use crate::response::PageResult;
 use crate::state::AppState; // 导入共享状态
+use crate::utils::access_key;
+use crate::utils::pagination::{self, PageQuery};
+use chrono::{Duration, Utc};
+use sea_orm::{ActiveValue::Set, IntoActiveModel};
+use tracing::info;
+
+// 资源级权限 (带 `_own` 后缀的版本只允许操作自己创建的记录，见 `middleware::rbac`)
+const PERM_EDIT: &str = "kms_kmsAppAccess_edit";
+const PERM_DEL: &str = "kms_kmsAppAccess_del";
+// 恢复会让记录 (及其访问密钥) 重新生效，与删除分开授权
+const PERM_RESTORE: &str = "kms_kmsAppAccess_restore";
 
 /// 根据 ID 获取 App Access
 ///
@@ -29,3 +47,365 @@ pub async fn get_app_access_by_id(
         None => Err(ServiceError::ResourceNotFound.into()),
     }
 }
+
+/// 分页查询 App Access 列表
+///
+/// # Arguments
+/// * `filter` - 过滤条件 (name / status / access_info_id / create_time 范围)
+/// * `page` - 分页与排序参数
+pub async fn list_app_accesses(
+    state: &AppState,
+    filter: &AppAccessListQuery,
+    page: &PageQuery,
+) -> Result<PageResult<kms_app_access::Model>, AppError> {
+    if let (Some(from), Some(to)) = (filter.create_time_from, filter.create_time_to)
+        && from > to
+    {
+        return Err(ServiceError::InvalidArgument(
+            "create_time_from 不能晚于 create_time_to".to_string(),
+        )
+        .into());
+    }
+
+    let select = kms_app_access_repo::select_by_filter(filter);
+    pagination::paginate(&state.db_pool, select, page, kms_app_access_repo::SORTABLE_COLUMNS).await
+}
+
+/// 创建 App Access
+///
+/// `create_by`/`update_by` 取自当前用户，`create_time`/`update_time` 取当前时间。
+/// 访问密钥由服务端随机生成，返回值中携带 *唯一一次* 可见的明文密钥。
+pub async fn create_app_access(
+    state: &AppState,
+    user: &CurrentUser,
+    req: CreateAppAccessRequest,
+) -> Result<AppAccessWithKeyResponse, AppError> {
+    let now = Utc::now();
+    let key = access_key::generate();
+
+    let model = kms_app_access::ActiveModel {
+        access_info_id: Set(req.access_info_id),
+        // 访问密钥由服务端生成，不接受客户端传入；只落库摘要
+        app_access_key: Set(key.hash),
+        key_prefix: Set(Some(key.display_prefix)),
+        name: Set(req.name),
+        mark: Set(req.mark),
+        status: Set(req.status.unwrap_or(STATUS_ENABLED)),
+        description: Set(req.description),
+        create_time: Set(now),
+        create_by: Set(user.username.clone()),
+        update_time: Set(now),
+        update_by: Set(user.username.clone()),
+        del_flag: Set(DEL_FLAG_NORMAL.to_string()),
+        show_id: Set(req.show_id),
+        ..Default::default() // id 由数据库生成
+    };
+
+    let created = kms_app_access_repo::insert(&state.db_pool, model).await?;
+    info!("AppAccess (ID: {}) 已由用户 {} 创建", created.id, user.username);
+    Ok(AppAccessWithKeyResponse {
+        app_access: created,
+        app_access_key: key.plaintext,
+    })
+}
+
+/// 轮换 App Access 的访问密钥
+///
+/// 生成新密钥替换当前密钥；若指定了宽限期，旧密钥在宽限期内仍然有效。
+/// 返回值中携带 *唯一一次* 可见的新明文密钥。
+///
+/// 只保留一个旧密钥：上一次轮换的旧密钥仍在宽限期内时，再次带宽限期轮换会被拒绝
+/// (否则那个旧密钥会被静默作废)；不带宽限期的轮换总是允许，新密钥之外的所有旧密钥立即失效。
+pub async fn rotate_app_access_key(
+    state: &AppState,
+    user: &CurrentUser,
+    id: i64,
+    req: RotateAppAccessKeyRequest,
+) -> Result<AppAccessWithKeyResponse, AppError> {
+    let current = get_app_access_by_id(state, id).await?;
+    rbac::authorize_owned(user, &current, PERM_EDIT)?;
+    let grace_period_secs = req.grace_period_secs.unwrap_or(0);
+    let now = Utc::now();
+    if grace_period_secs > 0 && current.prev_key_expire_time.is_some_and(|expire| expire > now) {
+        return Err(ServiceError::InvalidArgument(
+            "上一次轮换的旧密钥仍在宽限期内，请等其失效后再轮换，或以 grace_period_secs = 0 立即轮换".to_string(),
+        )
+        .into());
+    }
+    let key = access_key::generate();
+
+    let mut model = current.clone().into_active_model();
+    if grace_period_secs > 0 {
+        // 保留旧密钥摘要，直到宽限期结束
+        model.prev_app_access_key = Set(Some(current.app_access_key));
+        model.prev_key_expire_time = Set(Some(now + Duration::seconds(grace_period_secs as i64)));
+    } else {
+        model.prev_app_access_key = Set(None);
+        model.prev_key_expire_time = Set(None);
+    }
+    model.app_access_key = Set(key.hash);
+    model.key_prefix = Set(Some(key.display_prefix));
+    touch(&mut model, user);
+
+    let rotated = kms_app_access_repo::update(&state.db_pool, model).await?;
+    info!(
+        "AppAccess (ID: {}) 的访问密钥已由用户 {} 轮换, 旧密钥宽限期: {} 秒",
+        id, user.username, grace_period_secs
+    );
+    Ok(AppAccessWithKeyResponse {
+        app_access: rotated,
+        app_access_key: key.plaintext,
+    })
+}
+
+/// 全量更新 App Access (PUT)
+pub async fn update_app_access(
+    state: &AppState,
+    user: &CurrentUser,
+    id: i64,
+    req: UpdateAppAccessRequest,
+) -> Result<kms_app_access::Model, AppError> {
+    // 先查出 (未删除的) 记录，不存在则返回 404
+    let current = get_app_access_by_id(state, id).await?;
+    rbac::authorize_owned(user, &current, PERM_EDIT)?;
+    let mut model = current.into_active_model();
+
+    model.access_info_id = Set(req.access_info_id);
+    model.name = Set(req.name);
+    model.mark = Set(req.mark);
+    model.status = Set(req.status);
+    model.description = Set(req.description);
+    model.show_id = Set(req.show_id);
+    touch(&mut model, user);
+
+    Ok(kms_app_access_repo::update(&state.db_pool, model).await?)
+}
+
+/// 部分更新 App Access (PATCH)
+pub async fn patch_app_access(
+    state: &AppState,
+    user: &CurrentUser,
+    id: i64,
+    req: PatchAppAccessRequest,
+) -> Result<kms_app_access::Model, AppError> {
+    let current = get_app_access_by_id(state, id).await?;
+    rbac::authorize_owned(user, &current, PERM_EDIT)?;
+    let mut model = current.into_active_model();
+
+    if let Some(access_info_id) = req.access_info_id {
+        model.access_info_id = Set(access_info_id);
+    }
+    if let Some(name) = req.name {
+        model.name = Set(name);
+    }
+    if let Some(mark) = req.mark {
+        model.mark = Set(mark);
+    }
+    if let Some(status) = req.status {
+        model.status = Set(status);
+    }
+    if let Some(description) = req.description {
+        model.description = Set(description);
+    }
+    if let Some(show_id) = req.show_id {
+        model.show_id = Set(show_id);
+    }
+    touch(&mut model, user);
+
+    Ok(kms_app_access_repo::update(&state.db_pool, model).await?)
+}
+
+/// 逻辑删除 App Access (将 `del_flag` 置为已删除，不会物理删除)
+pub async fn delete_app_access(
+    state: &AppState,
+    user: &CurrentUser,
+    id: i64,
+) -> Result<(), AppError> {
+    let current = get_app_access_by_id(state, id).await?;
+    rbac::authorize_owned(user, &current, PERM_DEL)?;
+    let mut model = current.into_active_model();
+
+    model.del_flag = Set(DEL_FLAG_DELETED.to_string());
+    touch(&mut model, user);
+
+    kms_app_access_repo::update(&state.db_pool, model).await?;
+    info!("AppAccess (ID: {}) 已由用户 {} 逻辑删除", id, user.username);
+    Ok(())
+}
+
+/// 恢复一条已被逻辑删除的 App Access
+///
+/// 需要 `kms_kmsAppAccess_restore` (或只能恢复自己创建的记录的 `_own` 版本)；记录不存在或未被删除时返回 404。
+pub async fn restore_app_access(
+    state: &AppState,
+    user: &CurrentUser,
+    id: i64,
+) -> Result<kms_app_access::Model, AppError> {
+    let deleted = kms_app_access_repo::find_deleted_by_id(&state.db_pool, id)
+        .await?
+        .ok_or(ServiceError::ResourceNotFound)?;
+    rbac::authorize_owned(user, &deleted, PERM_RESTORE)?;
+    let mut model = deleted.into_active_model();
+
+    model.del_flag = Set(DEL_FLAG_NORMAL.to_string());
+    touch(&mut model, user);
+
+    let restored = kms_app_access_repo::update(&state.db_pool, model).await?;
+    info!("AppAccess (ID: {}) 已由用户 {} 恢复", id, user.username);
+    Ok(restored)
+}
+
+/// 校验明文访问密钥，返回对应的 App Access
+///
+/// 密钥不存在、已停用、已删除或旧密钥已过宽限期时，统一返回 `Unauthorized`，
+/// 不向调用方透露具体原因。
+///
+/// 只按摘要查找：改为摘要存储之前的明文记录需要先执行
+/// `migrations/001_kms_app_access_key_rotation.sql` 改写为摘要。
+pub async fn authenticate_access_key(
+    state: &AppState,
+    plaintext_key: &str,
+) -> Result<kms_app_access::Model, AppError> {
+    let key_hash = access_key::hash(plaintext_key);
+    kms_app_access_repo::find_active_by_key_hash(&state.db_pool, &key_hash, Utc::now())
+        .await?
+        .ok_or_else(|| ServiceError::Unauthorized.into())
+}
+
+/// 辅助函数：填充审计字段 `update_by` / `update_time`
+fn touch(model: &mut kms_app_access::ActiveModel, user: &CurrentUser) {
+    model.update_by = Set(user.username.clone());
+    model.update_time = Set(Utc::now());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::app_specific::AppSpecificConfig;
+    use crate::test_support;
+
+    const ADMIN: &[&str] = &[
+        "kms_kmsAppAccess_edit",
+        "kms_kmsAppAccess_del",
+        "kms_kmsAppAccess_restore",
+    ];
+
+    fn create_request(name: &str) -> CreateAppAccessRequest {
+        CreateAppAccessRequest {
+            access_info_id: 7,
+            name: name.to_string(),
+            mark: None,
+            status: None,
+            description: None,
+            show_id: None,
+        }
+    }
+
+    async fn names(state: &AppState) -> Vec<String> {
+        let page = list_app_accesses(state, &AppAccessListQuery::default(), &PageQuery::default())
+            .await
+            .unwrap();
+        let mut names: Vec<String> = page.items.into_iter().map(|app| app.name).collect();
+        names.sort();
+        names
+    }
+
+    fn is_not_found(result: Result<impl std::fmt::Debug, AppError>) -> bool {
+        matches!(result, Err(AppError::Service(ServiceError::ResourceNotFound)))
+    }
+
+    #[tokio::test]
+    async fn create_update_and_patch() {
+        let state = test_support::app_state(AppSpecificConfig::default()).await;
+        let alice = test_support::user("alice", ADMIN);
+
+        let created = create_app_access(&state, &alice, create_request("order")).await.unwrap();
+        let app = created.app_access;
+        assert_eq!((app.status, app.create_by.as_str()), (STATUS_ENABLED, "alice"));
+        // 只落库摘要，明文只返回一次
+        assert_eq!(app.app_access_key, access_key::hash(&created.app_access_key));
+
+        let patched = patch_app_access(
+            &state,
+            &alice,
+            app.id,
+            PatchAppAccessRequest {
+                name: Some("order-v2".to_string()),
+                access_info_id: None,
+                mark: None,
+                status: None,
+                description: None,
+                show_id: None,
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!((patched.name.as_str(), patched.access_info_id), ("order-v2", 7));
+
+        let updated = update_app_access(
+            &state,
+            &alice,
+            app.id,
+            UpdateAppAccessRequest {
+                access_info_id: 8,
+                name: "order-v3".to_string(),
+                mark: None,
+                status: 0,
+                description: None,
+                show_id: None,
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!((updated.access_info_id, updated.status), (8, 0));
+        assert_eq!(get_app_access_by_id(&state, app.id).await.unwrap(), updated);
+    }
+
+    #[tokio::test]
+    async fn deleted_rows_are_hidden_and_can_be_restored() {
+        let state = test_support::app_state(AppSpecificConfig::default()).await;
+        let alice = test_support::user("alice", ADMIN);
+        let kept = create_app_access(&state, &alice, create_request("kept")).await.unwrap();
+        let gone = create_app_access(&state, &alice, create_request("gone")).await.unwrap();
+        let id = gone.app_access.id;
+
+        delete_app_access(&state, &alice, id).await.unwrap();
+        // 删除后 get / list / 修改都看不到该记录，认证也不再接受它的密钥
+        assert!(is_not_found(get_app_access_by_id(&state, id).await));
+        assert_eq!(names(&state).await, vec!["kept"]);
+        assert!(authenticate_access_key(&state, &gone.app_access_key).await.is_err());
+        assert!(authenticate_access_key(&state, &kept.app_access_key).await.is_ok());
+
+        // 重复删除被拒绝
+        assert!(is_not_found(delete_app_access(&state, &alice, id).await));
+
+        let restored = restore_app_access(&state, &alice, id).await.unwrap();
+        assert_eq!(restored.del_flag, DEL_FLAG_NORMAL);
+        assert_eq!(names(&state).await, vec!["gone", "kept"]);
+        assert!(authenticate_access_key(&state, &gone.app_access_key).await.is_ok());
+
+        // 未删除的记录不能恢复
+        assert!(is_not_found(restore_app_access(&state, &alice, id).await));
+        assert!(is_not_found(restore_app_access(&state, &alice, 404).await));
+    }
+
+    #[tokio::test]
+    async fn restore_needs_its_own_permission() {
+        let state = test_support::app_state(AppSpecificConfig::default()).await;
+        let alice = test_support::user("alice", ADMIN);
+        let created = create_app_access(&state, &alice, create_request("order")).await.unwrap();
+        let id = created.app_access.id;
+        delete_app_access(&state, &alice, id).await.unwrap();
+
+        // 只有删除权限不能恢复
+        let deleter = test_support::user("bob", &["kms_kmsAppAccess_del"]);
+        let result = restore_app_access(&state, &deleter, id).await;
+        assert!(matches!(result, Err(AppError::Service(ServiceError::Forbidden(_)))));
+
+        // `_own` 只能恢复自己创建的记录
+        let other = test_support::user("bob", &["kms_kmsAppAccess_restore_own"]);
+        assert!(restore_app_access(&state, &other, id).await.is_err());
+        let owner = test_support::user("alice", &["kms_kmsAppAccess_restore_own"]);
+        assert!(restore_app_access(&state, &owner, id).await.is_ok());
+    }
+}
@@ -4,5 +4,14 @@
 // 声明 kms_app_access 的 service
 pub mod kms_app_access_service;
 
+// Bearer Token 认证 (远程 check_token / 本地 JWT)
+pub mod auth_service;
+
+// 本地 JWT 校验 (JWKS 缓存)
+pub mod jwt_service;
+
 // pub mod user_service; // 移除旧的 user 占位符
 
+
+// check_token 结果的 Redis 缓存
+pub mod token_cache_service;
@@ -0,0 +1,186 @@
+// src/services/token_cache_service.rs
+// remote 模式下 check_token 结果的 Redis 缓存
+//
+// - key: `auth:token:{sha256(token)}`，Redis 中不保存 Token 明文
+// - 校验成功：缓存 CurrentUser，TTL 取 `auth.token_cache.ttl_secs`，且不超过 Token 的剩余有效期 (`exp`)
+// - 校验失败：短时间负缓存，TTL 取 `auth.token_cache.negative_ttl_secs`
+// - Redis 不可用时只记录日志，认证流程退化为直接调用 Auth 服务
+
+use crate::config::app_specific::TokenCacheConfig;
+use crate::errors::AppError;
+use crate::middleware::auth::CurrentUser;
+use crate::state::AppState;
+use crate::utils::digest::sha256_hex;
+use redis::AsyncCommands;
+use chrono::Utc;
+use jsonwebtoken::{DecodingKey, Validation, decode, decode_header};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::{debug, info, warn};
+
+/// Redis key 前缀
+const KEY_PREFIX: &str = "auth:token:";
+/// 默认正缓存时间 (秒)
+const DEFAULT_TTL_SECS: u64 = 300;
+/// 默认负缓存时间 (秒)
+const DEFAULT_NEGATIVE_TTL_SECS: u64 = 10;
+
+/// 缓存中保存的校验结果
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum CachedIntrospection {
+    // Token 有效
+    Valid { user: CurrentUser },
+    // Token 无效 (负缓存)
+    Invalid,
+}
+
+/// 读取缓存的校验结果；未命中或 Redis 出错时返回 None
+pub async fn get(state: &AppState, token: &str) -> Option<CachedIntrospection> {
+    let key = cache_key(token);
+    let result: Result<Option<String>, AppError> = async {
+        let mut conn = state.redis_pool.get().await?;
+        Ok(conn.get(&key).await?)
+    }
+    .await;
+
+    match result {
+        Ok(Some(value)) => match serde_json::from_str(&value) {
+            Ok(cached) => {
+                debug!("(TokenCache) 命中缓存: {}", key);
+                Some(cached)
+            }
+            Err(e) => {
+                warn!("(TokenCache) 缓存内容无法解析，忽略: {}", e);
+                None
+            }
+        },
+        Ok(None) => None,
+        Err(e) => {
+            warn!("(TokenCache) 读取缓存失败，直接调用 Auth 服务: {}", e);
+            None
+        }
+    }
+}
+
+/// 缓存一个校验成功的结果
+///
+/// `expires_at` 为 check_token 返回的过期时间 (Unix 秒)；没有时尝试读取 Token 本身 (JWT) 的 `exp`。
+/// 剩余有效期不足 1 秒时不缓存。
+pub async fn put_valid(
+    state: &AppState,
+    config: &TokenCacheConfig,
+    token: &str,
+    user: &CurrentUser,
+    expires_at: Option<i64>,
+) {
+    let ttl = config.ttl_secs.unwrap_or(DEFAULT_TTL_SECS);
+    let expires_at = expires_at.or_else(|| jwt_exp(token));
+    let Some(ttl) = capped_ttl(ttl, expires_at, Utc::now().timestamp()) else {
+        debug!("(TokenCache) Token 即将过期，不缓存");
+        return;
+    };
+    let value = CachedIntrospection::Valid { user: user.clone() };
+    put(state, token, &value, ttl).await;
+}
+
+/// 缓存一个校验失败的结果 (负缓存)
+pub async fn put_invalid(state: &AppState, config: &TokenCacheConfig, token: &str) {
+    let ttl = config.negative_ttl_secs.unwrap_or(DEFAULT_NEGATIVE_TTL_SECS);
+    put(state, token, &CachedIntrospection::Invalid, ttl).await;
+}
+
+/// 从缓存中移除一个 Token (例如登出时)
+pub async fn evict(state: &AppState, token: &str) -> Result<(), AppError> {
+    let key = cache_key(token);
+    let mut conn = state.redis_pool.get().await?;
+    let _: () = conn.del(&key).await?;
+    info!("(TokenCache) 已移除缓存: {}", key);
+    Ok(())
+}
+
+/// 辅助函数：写入缓存 (TTL 为 0 时不写)，失败只记录日志
+async fn put(state: &AppState, token: &str, value: &CachedIntrospection, ttl_secs: u64) {
+    if ttl_secs == 0 {
+        return;
+    }
+    let key = cache_key(token);
+    let result: Result<(), AppError> = async {
+        let json = serde_json::to_string(value)
+            .map_err(|e| AppError::InternalError(format!("序列化缓存内容失败: {}", e)))?;
+        let mut conn = state.redis_pool.get().await?;
+        let _: () = conn.set_ex(&key, json, ttl_secs).await?;
+        Ok(())
+    }
+    .await;
+
+    if let Err(e) = result {
+        warn!("(TokenCache) 写入缓存失败: {}", e);
+    }
+}
+
+/// 辅助函数：缓存时间不超过 Token 的剩余有效期，剩余有效期 <= 0 时返回 None
+fn capped_ttl(ttl_secs: u64, expires_at: Option<i64>, now: i64) -> Option<u64> {
+    match expires_at {
+        Some(exp) => u64::try_from(exp - now)
+            .ok()
+            .filter(|remaining| *remaining > 0)
+            .map(|remaining| remaining.min(ttl_secs)),
+        None => Some(ttl_secs),
+    }
+}
+
+/// 辅助函数：读取 JWT 的 `exp` (不校验签名，只用于缩短缓存时间)；不是 JWT 时返回 None
+fn jwt_exp(token: &str) -> Option<i64> {
+    let header = decode_header(token).ok()?;
+    let mut validation = Validation::new(header.alg);
+    validation.insecure_disable_signature_validation();
+    validation.validate_exp = false;
+    validation.validate_aud = false;
+    validation.required_spec_claims.clear();
+
+    let claims = decode::<Value>(token, &DecodingKey::from_secret(&[]), &validation)
+        .ok()?
+        .claims;
+    claims.get("exp").and_then(Value::as_i64)
+}
+
+/// 辅助函数：Token 的缓存 key (使用摘要，避免 Token 明文落入 Redis)
+fn cache_key(token: &str) -> String {
+    format!("{}{}", KEY_PREFIX, sha256_hex(token))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capped_ttl_uses_configured_ttl_without_exp() {
+        assert_eq!(capped_ttl(300, None, 1_000), Some(300));
+    }
+
+    #[test]
+    fn capped_ttl_is_limited_by_remaining_lifetime() {
+        assert_eq!(capped_ttl(300, Some(1_060), 1_000), Some(60));
+        assert_eq!(capped_ttl(300, Some(2_000), 1_000), Some(300));
+    }
+
+    #[test]
+    fn capped_ttl_skips_expired_tokens() {
+        assert_eq!(capped_ttl(300, Some(1_000), 1_000), None);
+        assert_eq!(capped_ttl(300, Some(900), 1_000), None);
+    }
+
+    #[test]
+    fn jwt_exp_reads_unverified_claim() {
+        let token = jsonwebtoken::encode(
+            &jsonwebtoken::Header::default(),
+            &serde_json::json!({ "sub": "1", "exp": 1_700_000_000 }),
+            &jsonwebtoken::EncodingKey::from_secret(b"secret"),
+        )
+        .unwrap();
+
+        assert_eq!(jwt_exp(&token), Some(1_700_000_000));
+        assert_eq!(jwt_exp("opaque-token"), None);
+    }
+}
@@ -0,0 +1,151 @@
+// src/setup/config_source.rs
+// 业务配置 (AppSpecificConfig) 的来源
+//
+// - Nacos (默认)：配置了 `APP_CONFIG_SNAPSHOT` 时，每次成功获取并解析后把原始 YAML 写入该快照文件
+// - 快照：启动时 Nacos 不可用且快照存在，只尝试一次 (不走完整的退避重试) 就使用上次的快照启动；
+//   Nacos 恢复后由监听器推送最新配置
+// - 本地文件 (离线模式)：设置 `APP_CONFIG_FILE` 时直接读取该 YAML，不创建 Nacos 客户端 (不注册服务实例，
+//   也不能通过服务发现调用其他服务)
+
+use super::startup;
+use crate::config::Config;
+use crate::config::app_specific::{AppSpecificConfig, parse_nacos_config};
+use anyhow::Context;
+use nacos_sdk::api::config::ConfigService;
+use std::io::Write;
+use std::path::Path;
+use tracing::{info, warn};
+
+/// 初始配置的来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigSource {
+    Nacos,
+    Snapshot,
+    File,
+}
+
+/// 加载启动时的业务配置
+///
+/// `config_client` 为 None 表示离线模式 (设置了 `APP_CONFIG_FILE`)。
+pub async fn load_initial_config(
+    config: &Config,
+    config_client: Option<&ConfigService>,
+) -> anyhow::Result<(AppSpecificConfig, ConfigSource)> {
+    // 离线模式：只读本地文件
+    let Some(config_client) = config_client else {
+        let path = config
+            .app_config_file
+            .as_deref()
+            .context("未设置 APP_CONFIG_FILE 时必须创建 Nacos 配置客户端")?;
+        let app_config = read_config_file(path)?;
+        info!("(Config) 离线模式：已从本地文件 {} 加载配置", path);
+        return Ok((app_config, ConfigSource::File));
+    };
+
+    let snapshot = config
+        .config_snapshot_file
+        .as_deref()
+        .filter(|path| Path::new(path).exists());
+    let fetch = || async {
+        Ok(config_client
+            .get_config(
+                config.nacos_config_data_id.clone(),
+                config.nacos_config_group.clone(),
+            )
+            .await?)
+    };
+    // 获取初始 Nacos 配置：有快照时只尝试一次，失败立即回退到快照；
+    // 没有快照时 Nacos 是必需依赖，带退避重试 (见 startup.rs)
+    let fetched = match snapshot {
+        Some(_) => startup::attempt_once("Nacos 配置中心", fetch()).await,
+        None => startup::retry(config, "Nacos 配置中心", fetch).await,
+    };
+
+    match fetched {
+        Ok(config_resp) => {
+            info!("从 Nacos 获取到初始 ConfigResponse: {:?}", config_resp);
+            // 格式错误时返回带上下文的错误 (重试无意义，也不回退到快照，避免掩盖错误的配置)
+            let app_config = parse_nacos_config(config_resp.content()).with_context(|| {
+                format!(
+                    "无法解析初始 Nacos 配置 {} ({})，请检查 Nacos 中的配置格式",
+                    config.nacos_config_data_id, config.nacos_config_group
+                )
+            })?;
+            save_snapshot(config, config_resp.content());
+            Ok((app_config, ConfigSource::Nacos))
+        }
+        Err(e) => {
+            let Some(path) = snapshot else {
+                return Err(e);
+            };
+            warn!("(Config) {:#}；使用本地快照 {} 启动", e, path);
+            let app_config = read_config_file(path)?;
+            Ok((app_config, ConfigSource::Snapshot))
+        }
+    }
+}
+
+/// 把成功解析的 Nacos 配置写入本地快照 (失败只记录警告)
+///
+/// 配置中可能含有数据库密码等敏感信息，Unix 下文件权限为 0600。
+pub fn save_snapshot(config: &Config, content: &str) {
+    let Some(path) = config.config_snapshot_file.as_deref() else {
+        return;
+    };
+    if let Err(e) = write_atomically(path, content) {
+        warn!("(Config) 写入配置快照 {} 失败: {}", path, e);
+    }
+}
+
+/// 辅助函数：读取并解析本地 YAML 配置
+fn read_config_file(path: &str) -> anyhow::Result<AppSpecificConfig> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("无法读取配置文件 {}", path))?;
+    parse_nacos_config(&content).with_context(|| format!("无法解析配置文件 {}", path))
+}
+
+/// 辅助函数：先写临时文件再重命名，避免进程中途退出留下不完整的快照
+fn write_atomically(path: &str, content: &str) -> std::io::Result<()> {
+    let tmp_path = format!("{}.tmp", path);
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    let mut file = options.open(&tmp_path)?;
+    // `mode` 只在创建文件时生效，上次残留的临时文件会保留原来的权限，这里显式设置
+    #[cfg(unix)]
+    file.set_permissions(std::os::unix::fs::PermissionsExt::from_mode(0o600))?;
+    file.write_all(content.as_bytes())?;
+    file.sync_all()?;
+    std::fs::rename(&tmp_path, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn write_atomically_resets_permissions_of_stale_tmp_file() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("config-snapshot-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("snapshot.yaml");
+        let path = path.to_str().unwrap();
+
+        // 上次残留的临时文件权限过宽
+        let tmp_path = format!("{}.tmp", path);
+        std::fs::write(&tmp_path, "stale").unwrap();
+        std::fs::set_permissions(&tmp_path, std::fs::Permissions::from_mode(0o644)).unwrap();
+
+        write_atomically(path, "log_level: info\n").unwrap();
+
+        let mode = std::fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode, 0o600);
+        assert_eq!(std::fs::read_to_string(path).unwrap(), "log_level: info\n");
+        assert!(!Path::new(&tmp_path).exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
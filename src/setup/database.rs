@@ -7,8 +7,11 @@ use tracing::info;
 
 
 /// 构建数据库连接池，仅使用 Nacos 配置
+///
+/// `lazy` 为 true 时不在启动时建立连接 (降级启动，见 setup/startup.rs)
 pub async fn build_db_pool(
     nacos_config: &AppSpecificConfig, // Nacos 配置 (用于获取 URL)
+    lazy: bool,
 ) -> anyhow::Result<DatabaseConnection> {
     
     // 1. 从 Nacos 配置中获取 URL
@@ -33,12 +36,16 @@ pub async fn build_db_pool(
        .min_connections(1)
        .connect_timeout(Duration::from_secs(8))
        .idle_timeout(Duration::from_secs(8))
+       .connect_lazy(lazy)
        .sqlx_logging(true)
        .sqlx_logging_level(tracing::log::LevelFilter::Debug);
 
     info!("正在连接数据库, 最大连接数: {}", max_connections);
     // (日志中不再打印 db_url，因为它可能包含明文密码)
-    let pool = Database::connect(opt).await?;
+    let mut pool = Database::connect(opt).await?;
+
+    // 每条 SQL 生成一个链路追踪 span (未启用链路追踪时直接返回)
+    pool.set_metric_callback(crate::setup::telemetry::record_db_query);
     
     Ok(pool)
 }
\ No newline at end of file
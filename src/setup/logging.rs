@@ -0,0 +1,205 @@
+// src/setup/logging.rs
+// 初始化 tracing 日志
+//
+// - 输出格式由环境变量 `LOG_FORMAT` 决定：`text` (默认) 或 `json` (每行一个 JSON 对象，方便日志采集)
+// - 过滤规则通过 reload handle 挂载：启动时使用 `RUST_LOG`，之后 Nacos 配置中的 `log_level`
+//   (支持 `info,axum_template=debug` 这样的按 target 指令) 变更时立即生效，删除后恢复为 `RUST_LOG`
+// - 上述过滤规则只作用于日志输出 (per-layer filter)；同时挂载的 OpenTelemetry layer (见 telemetry.rs)
+//   使用独立的过滤规则 `OTEL_SPAN_FILTER` (默认 `info`)，是否导出由 Nacos 配置 `tracing` 决定
+
+use super::telemetry::TelemetryHandle;
+use tracing::{info, warn};
+use tracing_subscriber::{
+    EnvFilter, Layer, Registry,
+    filter::Filtered,
+    layer::{Layered, SubscriberExt},
+    reload,
+    util::SubscriberInitExt,
+};
+
+/// 未设置 RUST_LOG 时的默认过滤规则
+const DEFAULT_LOG_FILTER: &str = "info,axum_template=debug,nacos_sdk=info";
+
+/// 未设置 OTEL_SPAN_FILTER 时，链路追踪 layer 的过滤规则 (不受 log_level 影响)
+const DEFAULT_OTEL_FILTER: &str = "info";
+
+/// 运行时调整日志过滤规则的句柄
+pub struct LogLevelHandle {
+    handle: reload::Handle<EnvFilter, Registry>,
+    // 启动时的过滤规则 (Nacos 未配置 log_level 时使用)
+    default_directives: String,
+}
+
+impl LogLevelHandle {
+    /// 应用 Nacos 配置中的 log_level (None 或空字符串时恢复为启动时的规则)
+    ///
+    /// 规则解析失败时保留当前规则，只记录警告。
+    pub fn apply(&self, log_level: Option<&str>) {
+        let directives = log_level
+            .map(str::trim)
+            .filter(|level| !level.is_empty())
+            .unwrap_or(&self.default_directives);
+
+        let filter = match EnvFilter::try_new(directives) {
+            Ok(filter) => filter,
+            Err(e) => {
+                warn!("(Logging) 无效的 log_level '{}'，保持当前日志级别: {}", directives, e);
+                return;
+            }
+        };
+
+        let changed = self
+            .handle
+            .with_current(|current| current.to_string() != filter.to_string())
+            .unwrap_or(true);
+        if !changed {
+            return;
+        }
+        match self.handle.reload(filter) {
+            Ok(()) => info!("(Logging) 日志级别已更新为: {}", directives),
+            Err(e) => warn!("(Logging) 更新日志级别失败: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+impl LogLevelHandle {
+    /// 不挂载到任何 subscriber 的句柄 (测试中构造 AppState 用)
+    pub fn detached() -> Self {
+        let (_, handle) = reload::Layer::new(EnvFilter::new(DEFAULT_LOG_FILTER));
+        LogLevelHandle {
+            handle,
+            default_directives: DEFAULT_LOG_FILTER.to_string(),
+        }
+    }
+}
+
+/// 初始化全局日志，返回用于运行时调整日志级别与链路追踪的句柄
+pub fn init_logging() -> (LogLevelHandle, TelemetryHandle) {
+    // 此时基础配置尚未加载，先加载 .env 以便读取 RUST_LOG / LOG_FORMAT
+    dotenvy::dotenv().ok();
+
+    let default_directives =
+        std::env::var("RUST_LOG").unwrap_or_else(|_| DEFAULT_LOG_FILTER.to_string());
+    let filter = EnvFilter::try_new(&default_directives).unwrap_or_else(|e| {
+        eprintln!("RUST_LOG '{}' 无效 ({})，使用默认规则", default_directives, e);
+        EnvFilter::new(DEFAULT_LOG_FILTER)
+    });
+
+    let json = std::env::var("LOG_FORMAT").is_ok_and(|format| format.eq_ignore_ascii_case("json"));
+    let fmt_layer: FmtLayer = if json {
+        tracing_subscriber::fmt::layer()
+            .json()
+            .flatten_event(true)
+            .with_current_span(true)
+            .with_span_list(false)
+            .boxed()
+    } else {
+        tracing_subscriber::fmt::layer().boxed()
+    };
+
+    // 服务名作为 OTel resource 的 service.name
+    let service_name = std::env::var("APP_NAME").unwrap_or_else(|_| "axum-template".to_string());
+    let telemetry = TelemetryHandle::new(service_name);
+    let otel_layer = tracing_opentelemetry::OpenTelemetryLayer::new(telemetry.tracer());
+    let otel_directives =
+        std::env::var("OTEL_SPAN_FILTER").unwrap_or_else(|_| DEFAULT_OTEL_FILTER.to_string());
+    let otel_filter = EnvFilter::try_new(&otel_directives).unwrap_or_else(|e| {
+        eprintln!("OTEL_SPAN_FILTER '{}' 无效 ({})，使用默认规则", otel_directives, e);
+        EnvFilter::new(DEFAULT_OTEL_FILTER)
+    });
+
+    let (subscriber, handle) = build_subscriber(fmt_layer, filter, otel_layer, otel_filter);
+    subscriber.init();
+
+    let log_level = LogLevelHandle {
+        handle,
+        default_directives,
+    };
+    (log_level, telemetry)
+}
+
+/// 日志输出 layer (text 或 json)
+type FmtLayer = Box<dyn Layer<Registry> + Send + Sync>;
+
+/// 挂载了可重载过滤规则的日志输出 layer
+type ReloadableFmt<F> = Layered<Filtered<F, reload::Layer<EnvFilter, Registry>, Registry>, Registry>;
+
+/// 再挂载链路追踪 layer (带独立过滤规则) 后的完整 subscriber
+type TracedSubscriber<F, T> = Layered<Filtered<T, EnvFilter, ReloadableFmt<F>>, ReloadableFmt<F>>;
+
+/// 组装 subscriber：可重载的过滤规则只作用于日志输出 layer (per-layer filter)，
+/// 链路追踪 layer 使用自己的过滤规则，调高 log_level 不会丢掉要导出的 span
+fn build_subscriber<F, T>(
+    fmt_layer: F,
+    filter: EnvFilter,
+    trace_layer: T,
+    trace_filter: EnvFilter,
+) -> (TracedSubscriber<F, T>, reload::Handle<EnvFilter, Registry>)
+where
+    F: Layer<Registry> + Send + Sync,
+    T: Layer<ReloadableFmt<F>> + Send + Sync,
+{
+    let (filter_layer, handle) = reload::Layer::new(filter);
+    let subscriber = tracing_subscriber::registry()
+        .with(fmt_layer.with_filter(filter_layer))
+        .with(trace_layer.with_filter(trace_filter));
+    (subscriber, handle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use tracing::{Subscriber, info_span, span};
+    use tracing_subscriber::layer::Context;
+
+    /// 记录收到的 span 名称
+    #[derive(Clone, Default)]
+    struct SpanNames(Arc<Mutex<Vec<String>>>);
+
+    impl<S: Subscriber> Layer<S> for SpanNames {
+        fn on_new_span(&self, attrs: &span::Attributes<'_>, _id: &span::Id, _ctx: Context<'_, S>) {
+            self.0.lock().unwrap().push(attrs.metadata().name().to_string());
+        }
+    }
+
+    impl SpanNames {
+        fn take(&self) -> Vec<String> {
+            std::mem::take(&mut *self.0.lock().unwrap())
+        }
+    }
+
+    #[test]
+    fn log_level_does_not_filter_trace_layer() {
+        let fmt = SpanNames::default();
+        let trace = SpanNames::default();
+        let (subscriber, handle) = build_subscriber(
+            fmt.clone(),
+            EnvFilter::new("info"),
+            trace.clone(),
+            EnvFilter::new(DEFAULT_OTEL_FILTER),
+        );
+        let log_level = LogLevelHandle {
+            handle,
+            default_directives: "info".to_string(),
+        };
+
+        tracing::subscriber::with_default(subscriber, || {
+            let _ = info_span!("before").entered();
+            assert_eq!(fmt.take(), vec!["before"]);
+            assert_eq!(trace.take(), vec!["before"]);
+
+            // Nacos 把 log_level 调成 warn：日志不再输出 info span，链路追踪照常收到
+            log_level.apply(Some("warn"));
+            let _ = info_span!("request").entered();
+            assert!(fmt.take().is_empty());
+            assert_eq!(trace.take(), vec!["request"]);
+
+            // 删除 log_level 后恢复启动时的规则
+            log_level.apply(None);
+            let _ = info_span!("after").entered();
+            assert_eq!(fmt.take(), vec!["after"]);
+        });
+    }
+}
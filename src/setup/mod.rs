@@ -3,54 +3,77 @@
 // 它的职责是声明子模块，并“重导出” (re-export) 公共函数
 
 // 1. 声明子模块
+pub mod config_source;
 pub mod database;
 pub mod http;
+pub mod logging;
 pub mod nacos;
 pub mod redis;
+pub mod shutdown;
+pub mod startup;
+pub mod telemetry;
 
 // 2. 重导出子模块的公共函数
 pub use nacos::{AppConfigChangeListener, deregister_nacos_instance, register_nacos_instance};
+pub use logging::{LogLevelHandle, init_logging};
+pub use shutdown::{close_pools, shutdown_timings};
+pub use telemetry::TelemetryHandle;
 
 use crate::config::Config;
-use crate::config::app_specific::parse_nacos_config;
+use config_source::ConfigSource;
+use crate::services::jwt_service::JwksCache;
+use crate::clients::load_balancer::LoadBalancer;
+use crate::clients::circuit_breaker::CircuitBreakerRegistry;
+use crate::metrics::Metrics;
+use crate::health::HealthRegistry;
+use crate::middleware::permission::PermissionRegistry;
 use crate::state::AppState;
 use axum::Router;
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::net::TcpListener;
-use tokio::sync::RwLock;
-use tracing::info;
+use tokio::sync::{RwLock, oneshot};
+use tracing::{error, info, warn};
 
 // --- 封装所有启动逻辑的主函数 ---
 /// 初始化所有应用服务（Nacos 客户端、数据库池、配置加载和监听）
-/// 并返回一个构建好的 AppState
-pub async fn setup_application_state(config: &Config) -> anyhow::Result<AppState> {
-    // 创建 Nacos 客户端
-    info!("正在连接 Nacos: {}", &config.nacos_addr);
-    let naming_client = Arc::new(nacos::build_nacos_naming_client(config)?);
-    let config_client = Arc::new(nacos::build_nacos_config_client(config)?);
-
-    // 获取初始 Nacos 配置并解析
-    let initial_config_resp = config_client
-        .get_config(
-            config.nacos_config_data_id.clone(),
-            config.nacos_config_group.clone(),
-        )
-        .await?;
-    info!(
-        "从 Nacos 获取到初始 ConfigResponse: {:?}",
-        initial_config_resp
-    );
+/// 并返回一个构建好的 AppState 以及初始配置的来源
+pub async fn setup_application_state(
+    config: &Config,
+    log_level: Arc<LogLevelHandle>,
+    telemetry: Arc<TelemetryHandle>,
+) -> anyhow::Result<(AppState, ConfigSource)> {
+    // 创建 Nacos 客户端 (SDK 在后台连接，这里不会因 Nacos 不可达而失败)；离线模式下不创建
+    let nacos_clients = if config.app_config_file.is_some() {
+        None
+    } else {
+        info!("正在连接 Nacos: {}", &config.nacos_addr);
+        Some((
+            Arc::new(nacos::build_nacos_naming_client(config)?),
+            Arc::new(nacos::build_nacos_config_client(config)?),
+        ))
+    };
 
-    let initial_app_config = parse_nacos_config(&initial_config_resp.content())
-        .expect("无法解析初始 Nacos 配置！请检查 Nacos 中的配置格式。");
-    info!("成功解析初始 Nacos 配置: {:?}", initial_app_config);
+    // --- 修改点：获取初始配置 (Nacos / 本地快照 / 离线模式的本地文件，见 config_source.rs) ---
+    let (initial_app_config, config_source) =
+        config_source::load_initial_config(config, nacos_clients.as_ref().map(|(_, c)| &**c)).await?;
+    info!("成功解析初始配置 ({:?}): {:?}", config_source, initial_app_config);
+    // 应用 Nacos 中配置的日志级别
+    log_level.apply(initial_app_config.log_level.as_deref());
+    // 应用 Nacos 中配置的链路追踪
+    telemetry.apply(initial_app_config.tracing.as_ref());
+    crate::services::jwt_service::warn_if_claims_unchecked(&initial_app_config);
 
     // 并行构建 DB 和 Redis 连接池
     info!("正在并行创建数据库和 Redis 连接池...");
     let (db_pool_result, redis_pool_result) = tokio::join!(
-        database::build_db_pool(&initial_app_config),
-        redis::build_redis_pool(&initial_app_config)
+        startup::connect_pool(config, "db", "MySQL", |lazy| {
+            database::build_db_pool(&initial_app_config, lazy)
+        }),
+        startup::connect_pool(config, "redis", "Redis", |lazy| {
+            redis::build_redis_pool(&initial_app_config, lazy)
+        })
     );
 
     // HTTP 客户端创建是同步的，不需要 join
@@ -60,6 +83,8 @@ pub async fn setup_application_state(config: &Config) -> anyhow::Result<AppState
     let redis_pool = redis_pool_result?;
     info!("数据库和 Redis 连接池创建成功");
 
+    let metrics = Arc::new(Metrics::new()?);
+
     // 将解析后的配置放入 RwLock
     let app_config_rwlock = Arc::new(RwLock::new(initial_app_config));
 
@@ -67,44 +92,110 @@ pub async fn setup_application_state(config: &Config) -> anyhow::Result<AppState
     let app_state = AppState {
         // --- 新增：存入基础配置 ---
         base_config: Arc::new(config.clone()), // 克隆基础配置
-        naming_client: naming_client.clone(),
-        config_client: config_client.clone(),
+        naming_client: nacos_clients.as_ref().map(|(naming, _)| naming.clone()),
+        config_client: nacos_clients.as_ref().map(|(_, config)| config.clone()),
         app_config: app_config_rwlock.clone(),
-        db_pool: db_pool,
-        redis_pool: redis_pool,
+        db_pool,
+        redis_pool,
         http_client,
+        jwks_cache: Arc::new(JwksCache::default()),
+        load_balancer: Arc::new(LoadBalancer::default()),
+        circuit_breakers: Arc::new(CircuitBreakerRegistry::default()),
+        log_level: log_level.clone(),
+        metrics: metrics.clone(),
+        telemetry: telemetry.clone(),
+        health: Arc::new(HealthRegistry::default()),
+        permissions: PermissionRegistry::default(),
+    };
+
+    // 离线模式下不依赖 Nacos，不做配置监听，readiness 也不检查 Nacos
+    let Some((_, config_client)) = nacos_clients else {
+        app_state.health.unregister("nacos");
+        return Ok((app_state, config_source));
     };
 
     // 添加配置监听器
-    config_client
-        .add_listener(
-            config.nacos_config_data_id.clone(),
-            config.nacos_config_group.clone(),
-            Arc::new(AppConfigChangeListener {
-                app_config: app_state.app_config.clone(),
-            }),
-        )
-        .await?;
+    let listener = Arc::new(AppConfigChangeListener {
+        base_config: app_state.base_config.clone(),
+        app_config: app_state.app_config.clone(),
+        log_level,
+        metrics,
+        telemetry,
+    });
+    let data_id = config.nacos_config_data_id.clone();
+    let group = config.nacos_config_group.clone();
+    if config_source == ConfigSource::Snapshot {
+        // add_listener 会先向 Nacos 拉取一次配置，Nacos 不可达时会一直等待，放到后台；
+        // Nacos 恢复后拉到的最新配置会通过监听器生效
+        tokio::spawn(async move {
+            match config_client.add_listener(data_id, group, listener).await {
+                Ok(()) => info!("Nacos 已恢复，已添加 Nacos 配置监听器"),
+                Err(e) => error!("添加 Nacos 配置监听器失败: {}", e),
+            }
+        });
+        info!("Nacos 不可用，配置监听器将在 Nacos 恢复后添加");
+        return Ok((app_state, config_source));
+    }
+    config_client.add_listener(data_id, group, listener).await?;
     info!("已添加 Nacos 配置监听器");
     // 7. 返回构建好的 AppState
-    Ok(app_state)
+    Ok((app_state, config_source))
 }
 
 // --- 封装 Axum 服务器启动 (保持不变) ---
 /// 绑定端口并启动 Axum Web 服务器
+///
+/// `shutdown_signal` 完成时停止接收新连接，其输出为等待进行中请求完成的最长时间；
+/// 超过该时间仍未排空时直接返回 (剩余连接随进程退出断开)。
+///
+/// 返回 `true` 表示所有连接都已排空，`false` 表示排空超时。
 pub async fn run_server(
     app: Router,
     server_addr: &str,
     // 接收一个“停机信号”
-    shutdown_signal: impl Future<Output = ()> + Send + 'static,
-) -> anyhow::Result<()> {
+    shutdown_signal: impl Future<Output = Duration> + Send + 'static,
+) -> anyhow::Result<bool> {
     let addr = server_addr.parse::<SocketAddr>()?;
     info!("服务器已启动，正在监听: http://{}", &addr);
 
     let listener = TcpListener::bind(addr).await?;
-    axum::serve(listener, app.into_make_service())
-        .with_graceful_shutdown(shutdown_signal)
-        .await?;
+
+    // 停机信号的输出 (排空时限) 通过 oneshot 交给下面的计时分支
+    let (drain_tx, drain_rx) = oneshot::channel();
+    let signal = async move {
+        let _ = drain_tx.send(shutdown_signal.await);
+    };
+    let drain_deadline = async move {
+        match drain_rx.await {
+            Ok(timeout) => tokio::time::sleep(timeout).await,
+            // 信号 future 被丢弃 (服务器已自行退出)，不需要计时
+            Err(_) => std::future::pending().await,
+        }
+    };
+
+    // 带上 ConnectInfo，访问日志需要对端地址
+    let server = axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+        .with_graceful_shutdown(signal);
+    // 超时后只是不再等待：已建立的连接任务不会被中止，会在进程退出 (运行时关闭) 时随之中断
+    tokio::select! {
+        result = server => {
+            result?;
+            Ok(true)
+        }
+        _ = drain_deadline => {
+            warn!("等待进行中的请求超时，不再等待剩余连接 (进程退出时中断)，继续停机");
+            Ok(false)
+        }
+    }
+}
+
+/// 启动独立的管理端口 (只提供 /metrics，不参与优雅停机，随进程退出)
+pub async fn run_admin_server(app: Router, addr: &str) -> anyhow::Result<()> {
+    let addr = addr.parse::<SocketAddr>()?;
+    info!("管理端口已启动，正在监听: http://{}/metrics", &addr);
+
+    let listener = TcpListener::bind(addr).await?;
+    axum::serve(listener, app.into_make_service()).await?;
 
     Ok(())
 }
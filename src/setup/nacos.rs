@@ -10,6 +10,9 @@ use nacos_sdk::api::{
 };
 use std::sync::Arc; 
 use tokio::sync::RwLock;
+use super::logging::LogLevelHandle;
+use super::telemetry::TelemetryHandle;
+use crate::metrics::Metrics;
 use tracing::{error, info};
 
 
@@ -59,7 +62,7 @@ pub fn build_nacos_config_client(config: &Config) -> anyhow::Result<ConfigServic
 pub async fn register_nacos_instance(config: &Config, client: &Arc<NamingService>) -> anyhow::Result<()> {
     // 从 server_addr (例如 "127.0.0.1:3000") 中解析出 IP 和 Port
     let parts: Vec<&str> = config.server_addr.split(':').collect();
-    let ip = parts.get(0).unwrap_or(&"127.0.0.1").to_string(); // 提供默认 IP
+    let ip = parts.first().unwrap_or(&"127.0.0.1").to_string(); // 提供默认 IP
     let port: i32 = parts.get(1).unwrap_or(&"3000").parse()?; // 提供默认端口并解析
 
     let service_name = config.app_name.clone(); 
@@ -88,7 +91,7 @@ pub async fn register_nacos_instance(config: &Config, client: &Arc<NamingService
 pub async fn deregister_nacos_instance(config: &Config, client: &Arc<NamingService>) -> anyhow::Result<()> {
     info!("正在从 Nacos 注销服务...");
     let parts: Vec<&str> = config.server_addr.split(':').collect();
-    let ip = parts.get(0).unwrap_or(&"127.0.0.1").to_string();
+    let ip = parts.first().unwrap_or(&"127.0.0.1").to_string();
     let port: i32 = parts.get(1).unwrap_or(&"3000").parse()?;
     let service_name = config.app_name.clone();
 
@@ -110,7 +113,15 @@ pub async fn deregister_nacos_instance(config: &Config, client: &Arc<NamingServi
 // --- Nacos 配置监听器实现 ---
 // (监听器中的错误处理保持不变，因为它是在运行时发生，不应让整个服务崩溃)
 pub struct AppConfigChangeListener {
-    pub app_config: Arc<RwLock<AppSpecificConfig>>
+    // --- 新增：用于写入本地配置快照 ---
+    pub base_config: Arc<Config>,
+    pub app_config: Arc<RwLock<AppSpecificConfig>>,
+    // --- 新增：配置变更时同步更新日志级别 ---
+    pub log_level: Arc<LogLevelHandle>,
+    // --- 新增：记录热更新次数 ---
+    pub metrics: Arc<Metrics>,
+    // --- 新增：配置变更时同步更新链路追踪 ---
+    pub telemetry: Arc<TelemetryHandle>,
 }
 
 // --- 修改点 ---
@@ -123,6 +134,10 @@ impl ConfigChangeListener for AppConfigChangeListener {
 
         // 克隆 Arc 指针，以便在异步任务中使用
         let app_config_clone = self.app_config.clone();
+        let log_level = self.log_level.clone();
+        let metrics = self.metrics.clone();
+        let telemetry = self.telemetry.clone();
+        let base_config = self.base_config.clone();
         // 克隆配置内容，因为 config_resp 生命周期可能不够长
         let content_clone = config_resp.content().to_string(); 
 
@@ -132,14 +147,20 @@ impl ConfigChangeListener for AppConfigChangeListener {
             match parse_nacos_config(&content_clone) {
                 Ok(new_config) => {
                     info!("成功解析 Nacos 配置变更: {:?}", new_config);
+                    super::config_source::save_snapshot(&base_config, &content_clone);
+                    log_level.apply(new_config.log_level.as_deref());
+                    telemetry.apply(new_config.tracing.as_ref());
+                    crate::services::jwt_service::warn_if_claims_unchecked(&new_config);
                     // 在异步任务中获取写锁
                     let mut config_guard = app_config_clone.write().await;
                     *config_guard = new_config;
                     info!("AppState 中的配置已更新");
+                    metrics.record_config_reload(true);
                 }
                 Err(e) => {
                     // 在运行时解析失败，只记录错误，不崩溃
                     error!("解析 Nacos 配置变更失败: {}", e);
+                    metrics.record_config_reload(false);
                 }
             }
         });
@@ -4,16 +4,32 @@
 use crate::config::app_specific::AppSpecificConfig;
 // --- 修改点 ---
 // 不再直接 use bb8，而是通过 bb8_redis::bb8 访问
+use bb8_redis::bb8::{ManageConnection, Pool};
 use bb8_redis::{RedisConnectionManager};
-use tracing::{info};
+use redis::aio::{ConnectionLike, MultiplexedConnection};
+use redis::{Arg, Cmd, ErrorKind, Pipeline, RedisError, RedisFuture, Value};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tracing::{Instrument, Span, field::Empty, info};
 
+/// Redis 连接池最大连接数 (指标中也会用到)
+pub const REDIS_POOL_MAX_SIZE: u32 = 10;
+
+/// Redis 连接池类型 (连接带链路追踪)
+pub type RedisPool = Pool<TracedRedisConnectionManager>;
+
+/// 关闭连接池时取出每个空闲连接的最长等待时间
+const CLOSE_CHECKOUT_TIMEOUT: Duration = Duration::from_secs(1);
 
 /// 构建 Redis 连接池
 // --- 修改点 ---
-// 更新返回类型，使用完整的 bb8_redis::bb8::Pool 路径
+// 连接管理器包装为 TracedRedisConnectionManager，每条命令生成一个 span
+// `lazy` 为 true 时不在启动时建立连接 (降级启动，见 setup/startup.rs)
 pub async fn build_redis_pool(
     nacos_config: &AppSpecificConfig,
-) -> anyhow::Result<bb8_redis::bb8::Pool<RedisConnectionManager>> {
+    lazy: bool,
+) -> anyhow::Result<RedisPool> {
     
     // 1. 从 Nacos 配置中获取 URL
     let redis_config = nacos_config
@@ -27,18 +43,176 @@ pub async fn build_redis_pool(
         .ok_or_else(|| anyhow::anyhow!("Nacos 配置 [redis] 中缺少 'url' 字段"))?;
 
     // 2. 创建 Redis 连接管理器
-    let manager = RedisConnectionManager::new(redis_url.clone())?;
+    let manager = TracedRedisConnectionManager {
+        inner: RedisConnectionManager::new(redis_url.clone())?,
+        closed: Arc::new(AtomicBool::new(false)),
+    };
 
     // 3. 创建 bb8 连接池
     // --- 修改点 ---
     // 使用完整的 bb8_redis::bb8::Pool 路径
-    let pool = bb8_redis::bb8::Pool::builder()
-        .max_size(10) // 设置最大连接数
-        .connection_timeout(std::time::Duration::from_secs(5))
-        .build(manager)
-        .await?;
-    
+    let builder = Pool::builder()
+        .max_size(REDIS_POOL_MAX_SIZE) // 设置最大连接数
+        .connection_timeout(std::time::Duration::from_secs(5));
+    let pool = if lazy {
+        builder.build_unchecked(manager)
+    } else {
+        let pool = builder.build(manager).await?;
+        // 未设置 min_idle 时 build 不会建立连接，取一次连接确认 Redis 可达
+        pool.get().await?;
+        pool
+    };
+
     info!("Redis 连接池创建成功, URL: {}", redis_url);
     Ok(pool)
 }
 
+
+
+/// 关闭 Redis 连接池：断开所有空闲连接，之后不再建立新连接
+///
+/// bb8 没有显式的 close：这里取出全部空闲连接并打上关闭标记，归还时被当作损坏的连接丢弃。
+/// 仍被借出的连接在归还时同样被丢弃。返回 (已断开的空闲连接数, 仍在使用的连接数)。
+pub async fn close_redis_pool(pool: &RedisPool) -> (usize, u32) {
+    let idle = pool.state().idle_connections;
+    let mut connections = Vec::with_capacity(idle as usize);
+    for _ in 0..idle {
+        match tokio::time::timeout(CLOSE_CHECKOUT_TIMEOUT, pool.get()).await {
+            Ok(Ok(connection)) => connections.push(connection),
+            _ => break,
+        }
+    }
+    if let Some(connection) = connections.first() {
+        connection.closed.store(true, Ordering::Relaxed);
+    }
+    let closed = connections.len();
+    drop(connections);
+
+    let state = pool.state();
+    (closed, state.connections.saturating_sub(state.idle_connections))
+}
+
+
+
+// --- 新增：带链路追踪的 Redis 连接 ---
+
+/// 包装 `RedisConnectionManager`，取出的连接会为每条命令生成一个 client span
+#[derive(Clone, Debug)]
+pub struct TracedRedisConnectionManager {
+    inner: RedisConnectionManager,
+    // 连接池已关闭 (见 `close_redis_pool`)：不再建立新连接，归还的连接直接丢弃
+    closed: Arc<AtomicBool>,
+}
+
+impl ManageConnection for TracedRedisConnectionManager {
+    type Connection = TracedRedisConnection;
+    type Error = RedisError;
+
+    async fn connect(&self) -> Result<Self::Connection, Self::Error> {
+        if self.closed.load(Ordering::Relaxed) {
+            return Err(RedisError::from((ErrorKind::ClientError, "Redis 连接池已关闭")));
+        }
+        Ok(TracedRedisConnection {
+            inner: self.inner.connect().await?,
+            closed: self.closed.clone(),
+        })
+    }
+
+    // 连接检查 (PING) 直接使用内部连接，不产生 span
+    async fn is_valid(&self, conn: &mut Self::Connection) -> Result<(), Self::Error> {
+        self.inner.is_valid(&mut conn.inner).await
+    }
+
+    // 连接池关闭后，归还的连接都视为已损坏，bb8 会直接丢弃 (断开连接) 而不是放回池中
+    fn has_broken(&self, conn: &mut Self::Connection) -> bool {
+        self.closed.load(Ordering::Relaxed) || self.inner.has_broken(&mut conn.inner)
+    }
+}
+
+/// 带链路追踪的 Redis 连接 (只记录命令名，不记录 key 和参数)
+pub struct TracedRedisConnection {
+    inner: MultiplexedConnection,
+    // 与所属连接管理器共享的关闭标记
+    closed: Arc<AtomicBool>,
+}
+
+impl ConnectionLike for TracedRedisConnection {
+    fn req_packed_command<'a>(&'a mut self, cmd: &'a Cmd) -> RedisFuture<'a, Value> {
+        let span = redis_span(&command_name(cmd));
+        Box::pin(
+            async move {
+                let result = self.inner.req_packed_command(cmd).await;
+                record_error(result.is_err());
+                result
+            }
+            .instrument(span),
+        )
+    }
+
+    fn req_packed_commands<'a>(
+        &'a mut self,
+        pipeline: &'a Pipeline,
+        offset: usize,
+        count: usize,
+    ) -> RedisFuture<'a, Vec<Value>> {
+        let span = redis_span("PIPELINE");
+        Box::pin(
+            async move {
+                let result = self.inner.req_packed_commands(pipeline, offset, count).await;
+                record_error(result.is_err());
+                result
+            }
+            .instrument(span),
+        )
+    }
+
+    fn get_db(&self) -> i64 {
+        self.inner.get_db()
+    }
+}
+
+/// 辅助函数：命令名 (第一个参数)
+fn command_name(cmd: &Cmd) -> String {
+    match cmd.args_iter().next() {
+        Some(Arg::Simple(name)) => String::from_utf8_lossy(name).to_ascii_uppercase(),
+        _ => "UNKNOWN".to_string(),
+    }
+}
+
+/// 辅助函数：Redis 命令的 span
+fn redis_span(operation: &str) -> Span {
+    tracing::info_span!(
+        "redis",
+        otel.name = %format!("redis {}", operation),
+        otel.kind = "client",
+        db.system.name = "redis",
+        db.operation.name = operation,
+        otel.status_code = Empty,
+    )
+}
+
+/// 辅助函数：命令失败时把当前 span 标记为错误
+fn record_error(failed: bool) {
+    if failed {
+        Span::current().record("otel.status_code", "ERROR");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn closed_pool_does_not_open_new_connections() {
+        let manager = TracedRedisConnectionManager {
+            inner: RedisConnectionManager::new("redis://127.0.0.1:1").unwrap(),
+            closed: Arc::new(AtomicBool::new(true)),
+        };
+        let e = manager.connect().await.err().expect("closed manager must not connect");
+        assert_eq!(e.kind(), ErrorKind::ClientError);
+
+        // 没有空闲连接时无需断开
+        let pool = Pool::builder().build_unchecked(manager);
+        assert_eq!(close_redis_pool(&pool).await, (0, 0));
+    }
+}
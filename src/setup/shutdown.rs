@@ -0,0 +1,64 @@
+// src/setup/shutdown.rs
+// 优雅停机的辅助函数
+//
+// 停机顺序 (见 main.rs)：readiness 置为 DOWN -> 从 Nacos 注销 -> 等待传播 (propagation_delay)
+// -> 停止接收新连接并等待进行中的请求 (最长 drain_timeout) -> 关闭 DB / Redis 连接池 -> 导出剩余链路数据
+
+use super::redis;
+use crate::state::AppState;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+/// 默认的传播等待时间
+const DEFAULT_PROPAGATION_DELAY: Duration = Duration::from_secs(5);
+/// 默认的请求排空时间
+const DEFAULT_DRAIN_TIMEOUT: Duration = Duration::from_secs(20);
+/// 关闭数据库连接池的最长等待时间 (`close` 会等待借出的连接归还)
+const DB_CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 从 Nacos 配置 `shutdown` 中读取 (传播等待时间, 请求排空时间)
+pub async fn shutdown_timings(state: &AppState) -> (Duration, Duration) {
+    let config = state.app_config.read().await;
+    let shutdown = config.shutdown.as_ref();
+    (
+        shutdown
+            .and_then(|s| s.propagation_delay_ms)
+            .map(Duration::from_millis)
+            .unwrap_or(DEFAULT_PROPAGATION_DELAY),
+        shutdown
+            .and_then(|s| s.drain_timeout_ms)
+            .map(Duration::from_millis)
+            .unwrap_or(DEFAULT_DRAIN_TIMEOUT),
+    )
+}
+
+/// 关闭 DB 与 Redis 连接池
+///
+/// - `drained` 为 false (请求排空超时) 时跳过数据库连接池：仍在执行的请求持有连接，`close` 会一直等待
+/// - 否则最多等待 `DB_CLOSE_TIMEOUT`，保证停机不会卡在这里
+///
+/// Redis 连接池没有显式的 close，由 `close_redis_pool` 断开空闲连接并阻止新建连接；
+/// Nacos 监听器、管理端口等仍持有 AppState 克隆，但之后用到 Redis 时会直接失败。
+pub async fn close_pools(state: &AppState, drained: bool) {
+    if !drained {
+        warn!("请求未排空，跳过关闭数据库连接池 (随进程退出关闭)");
+    } else {
+        info!("正在关闭数据库连接池...");
+        match tokio::time::timeout(DB_CLOSE_TIMEOUT, state.db_pool.close_by_ref()).await {
+            Ok(Ok(())) => info!("数据库连接池已关闭"),
+            Ok(Err(e)) => error!("关闭数据库连接池失败: {}", e),
+            Err(_) => warn!("关闭数据库连接池超时 ({:?})，随进程退出关闭", DB_CLOSE_TIMEOUT),
+        }
+    }
+
+    info!("正在关闭 Redis 连接池...");
+    let (closed, in_use) = redis::close_redis_pool(&state.redis_pool).await;
+    if in_use > 0 {
+        warn!(
+            "Redis 连接池已断开 {} 个空闲连接，仍有 {} 个连接在使用 (归还时断开，或随进程退出关闭)",
+            closed, in_use
+        );
+    } else {
+        info!("Redis 连接池已关闭 (断开 {} 个空闲连接)", closed);
+    }
+}
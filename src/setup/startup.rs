@@ -0,0 +1,226 @@
+// src/setup/startup.rs
+// 启动时连接依赖 (Nacos / MySQL / Redis) 的重试与降级
+//
+// - 每个依赖按指数退避重试，最多 `STARTUP_MAX_ATTEMPTS` 次 (默认 10)，首次间隔 `STARTUP_BACKOFF_MS` (默认 500ms)，
+//   最长 30s；日志中标明是哪个依赖失败，方便在依赖与服务同时启动时排查
+// - 每次尝试带超时 (Nacos 不可达时 SDK 的请求会一直等待连接建立，不会自己返回错误)
+// - `STARTUP_LAZY_DEPENDENCIES` 中列出的依赖 (db / redis) 只尝试一次，失败时以延迟连接的方式创建连接池，
+//   服务降级启动，首次使用时再建立连接；期间 readiness 会报告该组件 DOWN
+
+use crate::config::Config;
+use std::future::Future;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// 两次重试之间的最长间隔
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+/// 单次尝试的超时 (需大于数据库的 connect_timeout)
+const ATTEMPT_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// 带指数退避地重试 `op`，直到成功或达到最大次数
+pub async fn retry<T, F, Fut>(config: &Config, dependency: &str, mut op: F) -> anyhow::Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = anyhow::Result<T>>,
+{
+    let max_attempts = config.startup_max_attempts;
+    let mut backoff = Duration::from_millis(config.startup_backoff_ms);
+    let mut attempt = 1;
+    loop {
+        match attempt_with_timeout(op()).await {
+            Ok(value) => {
+                if attempt > 1 {
+                    info!("(Startup) {} 在第 {} 次尝试时连接成功", dependency, attempt);
+                }
+                return Ok(value);
+            }
+            Err(e) if attempt < max_attempts => {
+                warn!(
+                    "(Startup) {} 不可用 (第 {}/{} 次)，{:?} 后重试: {:#}",
+                    dependency, attempt, max_attempts, backoff, e
+                );
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+                attempt += 1;
+            }
+            Err(e) => {
+                return Err(e.context(format!(
+                    "启动失败：{} 在 {} 次尝试后仍不可用",
+                    dependency, max_attempts
+                )));
+            }
+        }
+    }
+}
+
+/// 创建连接池：`key` (db / redis) 在 `STARTUP_LAZY_DEPENDENCIES` 中时允许降级，否则带重试
+///
+/// `build(lazy)` 在 `lazy` 为 true 时应只创建连接池而不建立连接。
+pub async fn connect_pool<T, F, Fut>(
+    config: &Config,
+    key: &str,
+    dependency: &str,
+    mut build: F,
+) -> anyhow::Result<T>
+where
+    F: FnMut(bool) -> Fut,
+    Fut: Future<Output = anyhow::Result<T>>,
+{
+    if !config.startup_lazy_dependencies.iter().any(|name| name == key) {
+        return retry(config, dependency, || build(false)).await;
+    }
+
+    match attempt_with_timeout(build(false)).await {
+        Ok(pool) => Ok(pool),
+        Err(e) => {
+            warn!(
+                "(Startup) {} 不可用，降级启动 (首次使用时再连接): {:#}",
+                dependency, e
+            );
+            build(true).await
+        }
+    }
+}
+
+/// 只尝试一次 (带超时)，失败时错误中标明依赖名；用于有兜底方案 (如本地快照) 的依赖
+pub async fn attempt_once<T>(
+    dependency: &str,
+    attempt: impl Future<Output = anyhow::Result<T>>,
+) -> anyhow::Result<T> {
+    attempt_with_timeout(attempt)
+        .await
+        .map_err(|e| e.context(format!("{} 不可用", dependency)))
+}
+
+/// 辅助函数：单次尝试，超时视为失败
+async fn attempt_with_timeout<T>(
+    attempt: impl Future<Output = anyhow::Result<T>>,
+) -> anyhow::Result<T> {
+    match tokio::time::timeout(ATTEMPT_TIMEOUT, attempt).await {
+        Ok(result) => result,
+        Err(_) => Err(anyhow::anyhow!("超时 ({:?})", ATTEMPT_TIMEOUT)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+    use std::sync::Mutex;
+    use tokio::time::Instant;
+
+    fn config(max_attempts: u32, backoff_ms: u64, lazy: &[&str]) -> Config {
+        Config {
+            startup_max_attempts: max_attempts,
+            startup_backoff_ms: backoff_ms,
+            startup_lazy_dependencies: lazy.iter().map(|name| name.to_string()).collect(),
+            ..test_support::base_config()
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn retry_backs_off_until_success() {
+        let calls = Mutex::new(0);
+        let started = Instant::now();
+        let result = retry(&config(5, 500, &[]), "MySQL", || async {
+            let mut calls = calls.lock().unwrap();
+            *calls += 1;
+            if *calls < 3 { Err(anyhow::anyhow!("refused")) } else { Ok(*calls) }
+        })
+        .await;
+
+        assert_eq!(result.unwrap(), 3);
+        // 两次重试分别等待 500ms、1s
+        assert_eq!(started.elapsed(), Duration::from_millis(1500));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn retry_gives_up_after_max_attempts() {
+        let calls = Mutex::new(0);
+        let result: anyhow::Result<()> = retry(&config(3, 100, &[]), "Redis", || async {
+            *calls.lock().unwrap() += 1;
+            Err(anyhow::anyhow!("refused"))
+        })
+        .await;
+
+        assert_eq!(*calls.lock().unwrap(), 3);
+        let message = format!("{:#}", result.unwrap_err());
+        assert!(message.contains("启动失败：Redis 在 3 次尝试后仍不可用"), "{}", message);
+        assert!(message.contains("refused"), "{}", message);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn retry_backoff_is_capped() {
+        let started = Instant::now();
+        let result: anyhow::Result<()> = retry(&config(4, 20_000, &[]), "Nacos", || async {
+            Err(anyhow::anyhow!("refused"))
+        })
+        .await;
+
+        assert!(result.is_err());
+        // 20s、30s (40s 被限制)、30s
+        assert_eq!(started.elapsed(), Duration::from_secs(80));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn hanging_attempt_times_out_and_is_retried() {
+        let calls = Mutex::new(0);
+        let started = Instant::now();
+        let result = retry(&config(2, 500, &[]), "Nacos", || {
+            let first = {
+                let mut calls = calls.lock().unwrap();
+                *calls += 1;
+                *calls == 1
+            };
+            async move {
+                if first {
+                    std::future::pending::<()>().await;
+                }
+                Ok("connected")
+            }
+        })
+        .await;
+
+        assert_eq!(result.unwrap(), "connected");
+        assert_eq!(started.elapsed(), ATTEMPT_TIMEOUT + Duration::from_millis(500));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn lazy_dependency_falls_back_to_lazy_pool() {
+        let calls = Mutex::new(Vec::new());
+        let build = |lazy: bool| {
+            calls.lock().unwrap().push(lazy);
+            async move {
+                if lazy { Ok("lazy pool") } else { Err(anyhow::anyhow!("refused")) }
+            }
+        };
+
+        let started = Instant::now();
+        let pool = connect_pool(&config(5, 500, &["redis"]), "redis", "Redis", build).await;
+        assert_eq!(pool.unwrap(), "lazy pool");
+        // 只尝试一次，不做退避
+        assert_eq!(*calls.lock().unwrap(), vec![false, true]);
+        assert_eq!(started.elapsed(), Duration::ZERO);
+
+        // 不在降级列表中的依赖按重试处理，不会创建延迟连接的连接池
+        calls.lock().unwrap().clear();
+        let pool = connect_pool(&config(2, 500, &["redis"]), "db", "MySQL", build).await;
+        assert!(pool.is_err());
+        assert_eq!(*calls.lock().unwrap(), vec![false, false]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn lazy_dependency_falls_back_after_timeout() {
+        let build = |lazy: bool| async move {
+            if !lazy {
+                std::future::pending::<()>().await;
+            }
+            Ok("lazy pool")
+        };
+
+        let started = Instant::now();
+        let pool = connect_pool(&config(5, 500, &["db"]), "db", "MySQL", build).await;
+        assert_eq!(pool.unwrap(), "lazy pool");
+        assert_eq!(started.elapsed(), ATTEMPT_TIMEOUT);
+    }
+}
@@ -0,0 +1,258 @@
+// src/setup/telemetry.rs
+// OpenTelemetry 链路追踪 (OTLP/HTTP 导出)
+//
+// - tracing 的 span 通过 `tracing-opentelemetry` 转成 OTel span：入站请求 (request_context)、
+//   service_client 调用、Redis 命令 (setup/redis.rs)
+// - SeaORM 查询通过 metric 回调直接生成带真实起止时间的 OTel span (`record_db_query`)
+// - 导出地址与采样率来自 Nacos 配置 `tracing`，变更时重建 TracerProvider 并替换 tracer，无需重启
+
+use crate::config::app_specific::TracingConfig;
+use axum::http::HeaderMap;
+use opentelemetry::propagation::{Extractor, TextMapPropagator};
+use opentelemetry::trace::{
+    Span as _, SpanBuilder, SpanKind, Status, TraceContextExt, Tracer, TracerProvider as _,
+};
+use opentelemetry::{Context, KeyValue};
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::Resource;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::{Sampler, SdkTracer, SdkTracerProvider};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use std::time::SystemTime;
+use tracing::{error, info, warn};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+/// 默认的 OTLP/HTTP 导出地址 (本地 collector)
+const DEFAULT_OTLP_ENDPOINT: &str = "http://localhost:4318/v1/traces";
+/// instrumentation scope 名称
+const TRACER_NAME: &str = "axum-template";
+
+/// 当前是否在导出链路数据
+static ENABLED: AtomicBool = AtomicBool::new(false);
+/// 全局 tracer (SeaORM 回调等拿不到 AppState 的地方使用)
+static TRACER: OnceLock<SwappableTracer> = OnceLock::new();
+
+/// 可替换内部 SdkTracer 的 tracer
+///
+/// `OpenTelemetryLayer` 安装后无法替换，因此由它持有这个包装，配置变更时只替换内部的 tracer。
+#[derive(Clone)]
+pub struct SwappableTracer {
+    inner: Arc<RwLock<SdkTracer>>,
+}
+
+impl Tracer for SwappableTracer {
+    type Span = opentelemetry_sdk::trace::Span;
+
+    fn build_with_context(&self, builder: SpanBuilder, parent_cx: &Context) -> Self::Span {
+        self.inner
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .build_with_context(builder, parent_cx)
+    }
+}
+
+impl SwappableTracer {
+    fn swap(&self, tracer: SdkTracer) {
+        *self.inner.write().unwrap_or_else(|e| e.into_inner()) = tracer;
+    }
+}
+
+/// 运行时调整链路追踪配置的句柄
+pub struct TelemetryHandle {
+    service_name: String,
+    tracer: SwappableTracer,
+    state: Mutex<TelemetryState>,
+}
+
+struct TelemetryState {
+    provider: SdkTracerProvider,
+    // 最近一次生效的配置 (None 表示尚未应用过)
+    applied: Option<Option<TracingConfig>>,
+}
+
+impl TelemetryHandle {
+    /// 创建句柄 (初始不导出，等待 Nacos 配置)
+    pub fn new(service_name: String) -> Self {
+        let provider = disabled_provider();
+        let tracer = SwappableTracer {
+            inner: Arc::new(RwLock::new(provider.tracer(TRACER_NAME))),
+        };
+        let _ = TRACER.set(tracer.clone());
+
+        TelemetryHandle {
+            service_name,
+            tracer,
+            state: Mutex::new(TelemetryState {
+                provider,
+                applied: None,
+            }),
+        }
+    }
+
+    /// 交给 `OpenTelemetryLayer` 使用的 tracer
+    pub fn tracer(&self) -> SwappableTracer {
+        self.tracer.clone()
+    }
+
+    /// 应用 Nacos 配置中的 tracing (配置未变化时什么也不做)
+    pub fn apply(&self, config: Option<&TracingConfig>) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if state.applied.as_ref().is_some_and(|applied| applied.as_ref() == config) {
+            return;
+        }
+
+        let enabled = config.is_some_and(|c| c.enabled.unwrap_or(true));
+        let provider = if enabled {
+            let endpoint = config
+                .and_then(|c| c.endpoint.as_deref())
+                .unwrap_or(DEFAULT_OTLP_ENDPOINT);
+            let ratio = config
+                .and_then(|c| c.sample_ratio)
+                .unwrap_or(1.0)
+                .clamp(0.0, 1.0);
+            match self.build_provider(endpoint, ratio) {
+                Ok(provider) => {
+                    info!("(Telemetry) 链路追踪已启用: endpoint={}, sample_ratio={}", endpoint, ratio);
+                    provider
+                }
+                Err(e) => {
+                    error!("(Telemetry) 创建 OTLP 导出器失败，保持当前配置: {}", e);
+                    return;
+                }
+            }
+        } else {
+            if state.applied.is_some() {
+                info!("(Telemetry) 链路追踪已关闭");
+            }
+            disabled_provider()
+        };
+
+        self.tracer.swap(provider.tracer(TRACER_NAME));
+        ENABLED.store(enabled, Ordering::Relaxed);
+        let old = std::mem::replace(&mut state.provider, provider);
+        state.applied = Some(config.cloned());
+        drop(state);
+
+        // 旧 provider 的 shutdown 会阻塞到剩余 span 导出完毕，放到独立线程
+        std::thread::spawn(move || {
+            if let Err(e) = old.shutdown() {
+                warn!("(Telemetry) 关闭旧的 TracerProvider 失败: {}", e);
+            }
+        });
+    }
+
+    /// 导出剩余的 span (停机时调用)
+    pub fn shutdown(&self) {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if let Err(e) = state.provider.shutdown() {
+            warn!("(Telemetry) 导出剩余 span 失败: {}", e);
+        }
+    }
+
+    fn build_provider(
+        &self,
+        endpoint: &str,
+        ratio: f64,
+    ) -> Result<SdkTracerProvider, opentelemetry_otlp::ExporterBuildError> {
+        let exporter = SpanExporter::builder()
+            .with_http()
+            .with_endpoint(endpoint)
+            .build()?;
+
+        Ok(SdkTracerProvider::builder()
+            .with_batch_exporter(exporter)
+            .with_sampler(Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(ratio))))
+            .with_resource(
+                Resource::builder()
+                    .with_service_name(self.service_name.clone())
+                    .build(),
+            )
+            .build())
+    }
+}
+
+/// 辅助函数：不导出任何 span 的 provider
+fn disabled_provider() -> SdkTracerProvider {
+    SdkTracerProvider::builder()
+        .with_sampler(Sampler::AlwaysOff)
+        .build()
+}
+
+/// 当前是否在导出链路数据
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// 从入站请求的 traceparent / tracestate 中提取上游的 trace context
+pub fn extract_parent(headers: &HeaderMap) -> Context {
+    TraceContextPropagator::new().extract(&HeaderExtractor(headers))
+}
+
+/// 当前 span 的 trace context (用于出站调用的 traceparent / tracestate)
+///
+/// 未启用链路追踪或当前不在有效的 span 中时返回 None，此时由调用方原样透传入站的 Header。
+pub fn current_trace_headers() -> Option<(String, Option<String>)> {
+    if !is_enabled() {
+        return None;
+    }
+    let context = tracing::Span::current().context();
+    if !context.span().span_context().is_valid() {
+        return None;
+    }
+
+    let mut carrier: HashMap<String, String> = HashMap::new();
+    TraceContextPropagator::new().inject_context(&context, &mut carrier);
+    let traceparent = carrier.remove("traceparent")?;
+    let tracestate = carrier.remove("tracestate").filter(|state| !state.is_empty());
+    Some((traceparent, tracestate))
+}
+
+/// SeaORM metric 回调：为每条 SQL 生成一个 client span (父 span 为当前 tracing span)
+///
+/// 只记录带占位符的 SQL，不记录参数值。
+pub fn record_db_query(info: &sea_orm::metric::Info<'_>) {
+    let Some(tracer) = TRACER.get().filter(|_| is_enabled()) else {
+        return;
+    };
+
+    let end = SystemTime::now();
+    let start = end.checked_sub(info.elapsed).unwrap_or(end);
+    let sql = info.statement.sql.as_str();
+    let operation = sql
+        .split_whitespace()
+        .next()
+        .unwrap_or("QUERY")
+        .to_ascii_uppercase();
+
+    let parent = tracing::Span::current().context();
+    let mut span = tracer.build_with_context(
+        SpanBuilder::from_name(operation.clone())
+            .with_kind(SpanKind::Client)
+            .with_start_time(start)
+            .with_attributes([
+                KeyValue::new("db.system.name", "mysql"),
+                KeyValue::new("db.operation.name", operation),
+                KeyValue::new("db.query.text", sql.to_string()),
+            ]),
+        &parent,
+    );
+    if info.failed {
+        span.set_status(Status::error("query failed"));
+    }
+    span.end_with_timestamp(end);
+}
+
+/// 辅助结构：从 http::HeaderMap 中读取传播字段
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|key| key.as_str()).collect()
+    }
+}
@@ -12,10 +12,16 @@ use tokio::sync::RwLock;
 // 导入 SeaORM 的数据库连接类型
 use sea_orm::DatabaseConnection;
 
-// 从 `bb8_redis` 中导入 `bb8` 提供的类型
-use bb8_redis::RedisConnectionManager;
-use bb8_redis::bb8::Pool;
+// Redis 连接池 (bb8，连接带链路追踪)
+use crate::setup::redis::RedisPool;
 use reqwest::Client; // <-- 新增：导入 reqwest 客户端
+use crate::services::jwt_service::JwksCache;
+use crate::clients::load_balancer::LoadBalancer;
+use crate::clients::circuit_breaker::CircuitBreakerRegistry;
+use crate::setup::{LogLevelHandle, TelemetryHandle};
+use crate::metrics::Metrics;
+use crate::health::HealthRegistry;
+use crate::middleware::permission::PermissionRegistry;
 
 
 /// AppState 结构体包含了所有需要在 handlers 之间共享的状态。
@@ -25,15 +31,40 @@ pub struct AppState {
     // --- 新增：持有基础配置 ---
     pub base_config: Arc<Config>,
 
-    pub naming_client: Arc<NamingService>,
-    pub config_client: Arc<ConfigService>,
+    // 离线模式 (APP_CONFIG_FILE) 下不创建 Nacos 客户端，为 None
+    pub naming_client: Option<Arc<NamingService>>,
+    pub config_client: Option<Arc<ConfigService>>,
     // --- 新增字段 ---
     // 添加 app_config 字段来持有从 Nacos 解析的配置
     pub app_config: Arc<RwLock<AppSpecificConfig>>,
     // pub db_pool: PgPool, // 将来添加数据库连接池
     pub db_pool: DatabaseConnection, // <-- 使用 SeaORM 的连接池类型
 
-    pub redis_pool: Pool<RedisConnectionManager>,
+    pub redis_pool: RedisPool,
 
     pub http_client: Client,
+
+    // --- 新增：本地 JWT 校验使用的 JWKS 缓存 ---
+    pub jwks_cache: Arc<JwksCache>,
+
+    // --- 新增：下游服务调用的负载均衡状态 (轮询计数、进行中请求数) ---
+    pub load_balancer: Arc<LoadBalancer>,
+
+    // --- 新增：下游服务熔断器 ---
+    pub circuit_breakers: Arc<CircuitBreakerRegistry>,
+
+    // --- 新增：运行时调整日志级别的句柄 (Nacos log_level 变更时使用) ---
+    pub log_level: Arc<LogLevelHandle>,
+
+    // --- 新增：Prometheus 指标 ---
+    pub metrics: Arc<Metrics>,
+
+    // --- 新增：运行时调整链路追踪 (OTLP 导出地址、采样率) 的句柄 ---
+    pub telemetry: Arc<TelemetryHandle>,
+
+    // --- 新增：readiness 检查的组件注册表 (可注册新的依赖检查) ---
+    pub health: Arc<HealthRegistry>,
+
+    // --- 新增：权限守卫审计清单 (组装路由时登记，GET /admin/permissions 查看) ---
+    pub permissions: PermissionRegistry,
 }
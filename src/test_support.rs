@@ -0,0 +1,96 @@
+// src/test_support.rs
+// 测试辅助：构造不依赖 Nacos / MySQL / Redis 的 AppState
+//
+// - 数据库使用 SQLite 内存库 (只有一个连接，所有查询看到同一份数据)，按实体建表
+// - Redis 连接池以延迟连接的方式创建，不会真的去连接
+// - 离线模式：没有 Nacos 客户端
+
+use crate::clients::circuit_breaker::CircuitBreakerRegistry;
+use crate::clients::load_balancer::LoadBalancer;
+use crate::config::Config;
+use crate::config::app_specific::{AppSpecificConfig, RedisConfig};
+use crate::health::HealthRegistry;
+use crate::metrics::Metrics;
+use crate::middleware::auth::{CurrentUser, PrincipalKind};
+use crate::middleware::permission::PermissionRegistry;
+use crate::models::kms_app_access;
+use crate::services::jwt_service::JwksCache;
+use crate::setup::{LogLevelHandle, TelemetryHandle, redis};
+use crate::state::AppState;
+use sea_orm::{ConnectOptions, ConnectionTrait, Database, DatabaseConnection, Schema};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+/// 构造测试用的 AppState
+pub async fn app_state(app_config: AppSpecificConfig) -> AppState {
+    let mut redis_config = app_config.clone();
+    redis_config.redis = Some(RedisConfig {
+        url: Some("redis://127.0.0.1:1".to_string()),
+    });
+
+    AppState {
+        base_config: Arc::new(base_config()),
+        naming_client: None,
+        config_client: None,
+        app_config: Arc::new(RwLock::new(app_config)),
+        db_pool: database().await,
+        redis_pool: redis::build_redis_pool(&redis_config, true).await.unwrap(),
+        http_client: reqwest::Client::new(),
+        jwks_cache: Arc::new(JwksCache::default()),
+        load_balancer: Arc::new(LoadBalancer::default()),
+        circuit_breakers: Arc::new(CircuitBreakerRegistry::default()),
+        log_level: Arc::new(LogLevelHandle::detached()),
+        metrics: Arc::new(Metrics::new().unwrap()),
+        telemetry: Arc::new(TelemetryHandle::new("axum-template-test".to_string())),
+        health: Arc::new(HealthRegistry::default()),
+        permissions: PermissionRegistry::default(),
+    }
+}
+
+/// 构造一个用户 (authorities 直接作为权限，不做角色展开)
+pub fn user(username: &str, authorities: &[&str]) -> CurrentUser {
+    CurrentUser::new(
+        format!("id-{}", username),
+        username.to_string(),
+        authorities.iter().map(|a| a.to_string()).collect(),
+        PrincipalKind::User,
+    )
+}
+
+/// 辅助函数：SQLite 内存库，并按实体建表
+async fn database() -> DatabaseConnection {
+    let mut options = ConnectOptions::new("sqlite::memory:");
+    // 每个连接都是一个独立的内存库，只保留一个连接
+    options.max_connections(1).min_connections(1).sqlx_logging(false);
+    let db = Database::connect(options).await.unwrap();
+
+    let backend = db.get_database_backend();
+    let schema = Schema::new(backend);
+    db.execute(backend.build(&schema.create_table_from_entity(kms_app_access::Entity)))
+        .await
+        .unwrap();
+    db
+}
+
+/// 测试用的基础配置
+pub fn base_config() -> Config {
+    Config {
+        app_name: "axum-template-test".to_string(),
+        server_addr: "127.0.0.1:0".to_string(),
+        database_url: None,
+        nacos_addr: "127.0.0.1:8848".to_string(),
+        nacos_naming_namespace: String::new(),
+        nacos_config_namespace: String::new(),
+        nacos_username: None,
+        nacos_password: None,
+        nacos_config_data_id: "axum-template-test".to_string(),
+        nacos_config_group: "DEFAULT_GROUP".to_string(),
+        auth_service_name: "rtsp-auth".to_string(),
+        metrics_addr: "127.0.0.1:0".to_string(),
+        startup_max_attempts: 1,
+        startup_backoff_ms: 0,
+        startup_lazy_dependencies: Vec::new(),
+        app_config_file: None,
+        config_snapshot_file: None,
+    }
+}
@@ -0,0 +1,84 @@
+// src/utils/access_key.rs
+// App 访问密钥 (app_access_key) 的生成与摘要
+//
+// 约定：
+// - 明文密钥格式为 `ak_` + 64 位十六进制 (32 字节随机数)，只在创建/轮换时返回一次
+// - 数据库中只保存明文的 SHA-256 摘要 (十六进制)，以及用于展示的短前缀
+// - 密钥本身有 256 位熵，无需加盐或慢哈希
+// - 改为摘要存储之前创建的旧记录 (明文) 由 migrations/001_kms_app_access_key_rotation.sql 改写为摘要，
+//   认证时只按摘要查找
+
+use super::digest::sha256_hex;
+use rand::RngCore;
+
+/// 明文密钥前缀，方便在日志/代码扫描中识别
+const KEY_PREFIX: &str = "ak_";
+/// 随机部分的字节数
+const KEY_RANDOM_BYTES: usize = 32;
+/// 展示前缀中保留的随机字符数 (例如 `ak_3f9a1c2e`)
+const DISPLAY_PREFIX_CHARS: usize = 8;
+
+/// 一次新生成的密钥
+pub struct GeneratedKey {
+    // 明文 (只能返回给调用方一次，不落库)
+    pub plaintext: String,
+    // 展示用短前缀 (落库)
+    pub display_prefix: String,
+    // 明文的摘要 (落库)
+    pub hash: String,
+}
+
+/// 生成一个新的访问密钥
+pub fn generate() -> GeneratedKey {
+    let mut bytes = [0u8; KEY_RANDOM_BYTES];
+    rand::rng().fill_bytes(&mut bytes);
+
+    let plaintext = format!("{}{}", KEY_PREFIX, hex::encode(bytes));
+    let display_prefix = display_prefix(&plaintext);
+    let hash = hash(&plaintext);
+
+    GeneratedKey {
+        plaintext,
+        display_prefix,
+        hash,
+    }
+}
+
+/// 计算明文密钥的摘要 (用于落库和查找)
+pub fn hash(plaintext: &str) -> String {
+    sha256_hex(plaintext)
+}
+
+/// 辅助函数：展示用短前缀 (只用于新生成的密钥；旧记录的前缀由迁移脚本置为固定占位符)
+fn display_prefix(plaintext: &str) -> String {
+    plaintext[..KEY_PREFIX.len() + DISPLAY_PREFIX_CHARS].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generate_returns_prefixed_key_with_matching_hash() {
+        let key = generate();
+
+        assert!(key.plaintext.starts_with(KEY_PREFIX));
+        assert_eq!(key.plaintext.len(), KEY_PREFIX.len() + KEY_RANDOM_BYTES * 2);
+        assert_eq!(key.display_prefix, key.plaintext[..KEY_PREFIX.len() + DISPLAY_PREFIX_CHARS]);
+        assert_eq!(key.hash.len(), 64);
+        assert_eq!(key.hash, hash(&key.plaintext));
+    }
+
+    #[test]
+    fn generate_is_random() {
+        assert_ne!(generate().plaintext, generate().plaintext);
+    }
+
+    #[test]
+    fn hash_is_sha256_hex() {
+        assert_eq!(
+            hash("abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+}
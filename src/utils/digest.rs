@@ -0,0 +1,9 @@
+// src/utils/digest.rs
+// 通用的摘要工具函数
+
+use sha2::{Digest, Sha256};
+
+/// 计算字符串的 SHA-256 摘要 (十六进制小写)
+pub fn sha256_hex(input: &str) -> String {
+    hex::encode(Sha256::digest(input.as_bytes()))
+}
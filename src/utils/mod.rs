@@ -3,4 +3,16 @@
 // 声明通用的工具子模块
 
 // 声明我们自定义的 JSON 验证提取器
-pub mod validated_json;
\ No newline at end of file
+pub mod validated_json;
+
+// 声明 Query 版本的验证提取器
+pub mod validated_query;
+
+// 通用分页参数与分页查询
+pub mod pagination;
+
+// App 访问密钥的生成与摘要
+pub mod access_key;
+
+// 通用摘要 (SHA-256)
+pub mod digest;
@@ -0,0 +1,224 @@
+// src/utils/pagination.rs
+// 通用的分页 / 排序查询参数，以及基于 SeaORM 的分页执行函数。
+// 类似于 Spring Data 的 Pageable + Page<T>，可复用于 `models` 下的任意实体。
+
+use crate::errors::{AppError, ServiceError};
+use crate::response::PageResult;
+use sea_orm::{
+    ColumnTrait, ConnectionTrait, EntityTrait, FromQueryResult, IdenStatic, Iterable, Order, PaginatorTrait,
+    PrimaryKeyToColumn, QueryOrder, Select,
+};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+/// 默认页大小
+const DEFAULT_PAGE_SIZE: u64 = 10;
+
+/// 通用分页查询参数 (配合 `ValidatedQuery<PageQuery>` 使用)
+///
+/// 例如: `?page=2&size=20&sort=-create_time,name`
+/// - `sort` 为逗号分隔的列名 (数据库列名，snake_case)，前缀 `-` 表示降序
+#[derive(Debug, Clone, Default, Deserialize, Validate)]
+pub struct PageQuery {
+    // 页码，从 1 开始
+    #[validate(range(min = 1, message = "页码(page)必须从 1 开始"))]
+    pub page: Option<u64>,
+
+    // 每页大小
+    #[validate(range(min = 1, max = 100, message = "每页大小(size)必须在 1 到 100 之间"))]
+    pub size: Option<u64>,
+
+    // 排序字段
+    pub sort: Option<String>,
+}
+
+impl PageQuery {
+    /// 当前页码 (默认 1)
+    pub fn page(&self) -> u64 {
+        self.page.unwrap_or(1)
+    }
+
+    /// 每页大小 (默认 10)
+    pub fn size(&self) -> u64 {
+        self.size.unwrap_or(DEFAULT_PAGE_SIZE)
+    }
+
+    /// 将 `sort` 参数解析为实体列 + 排序方向
+    ///
+    /// 列名通过 SeaORM 为 `Column` 生成的 `FromStr` 解析，且必须在 `sortable` 中
+    /// (密钥摘要等敏感列不能排序，否则可以通过排序结果推测其取值)，否则返回 10001。
+    pub fn sort_orders<C>(&self, sortable: &[C]) -> Result<Vec<(C, Order)>, ServiceError>
+    where
+        C: ColumnTrait,
+    {
+        let Some(sort) = self.sort.as_deref() else {
+            return Ok(Vec::new());
+        };
+
+        sort.split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(|item| {
+                let (column, order) = match item.strip_prefix('-') {
+                    Some(column) => (column, Order::Desc),
+                    None => (item.strip_prefix('+').unwrap_or(item), Order::Asc),
+                };
+                C::from_str(column)
+                    .ok()
+                    .filter(|c| sortable.iter().any(|s| s.as_str() == c.as_str()))
+                    .map(|c| (c, order))
+                    .ok_or_else(|| ServiceError::InvalidArgument(format!("不支持的排序字段: {}", column)))
+            })
+            .collect()
+    }
+}
+
+/// 执行分页查询
+///
+/// 1. 按 `query.sort` 排序 (只允许 `sortable` 中的列)，最后总是追加主键作为兜底排序键，
+///    排序列有重复值时翻页也不会重复或漏掉记录；未指定时按主键降序
+/// 2. 查询总数和当前页数据，组装为 `PageResult`
+///
+/// # Arguments
+/// * `db` - 数据库连接
+/// * `select` - 已经拼好过滤条件的 `Select<E>`
+/// * `query` - 分页参数
+/// * `sortable` - 允许排序的列
+pub async fn paginate<E, C>(
+    db: &C,
+    select: Select<E>,
+    query: &PageQuery,
+    sortable: &[E::Column],
+) -> Result<PageResult<E::Model>, AppError>
+where
+    C: ConnectionTrait,
+    E: EntityTrait,
+    E::Model: FromQueryResult + Serialize + Send + Sync,
+{
+    let select = apply_sort(select, query, sortable)?;
+
+    let page = query.page();
+    let size = query.size();
+    let paginator = select.paginate(db, size);
+    let total = paginator.num_items().await?;
+    // SeaORM 的页码从 0 开始
+    let items = paginator.fetch_page(page - 1).await?;
+
+    Ok(PageResult {
+        items,
+        total,
+        page,
+        size,
+    })
+}
+
+/// 辅助函数：按 `query.sort` 排序，并追加 (未出现在排序列中的) 主键
+fn apply_sort<E: EntityTrait>(
+    mut select: Select<E>,
+    query: &PageQuery,
+    sortable: &[E::Column],
+) -> Result<Select<E>, ServiceError> {
+    let orders = query.sort_orders(sortable)?;
+    // 未指定排序时按主键降序 (最新的在前)，否则主键按升序兜底
+    let pk_order = if orders.is_empty() { Order::Desc } else { Order::Asc };
+    let mut sorted = Vec::new();
+    for (column, order) in orders {
+        sorted.push(column);
+        select = select.order_by(column, order);
+    }
+    for pk in E::PrimaryKey::iter() {
+        let column = pk.into_column();
+        if !sorted.iter().any(|c| c.as_str() == column.as_str()) {
+            select = select.order_by(column, pk_order.clone());
+        }
+    }
+    Ok(select)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::kms_app_access::{Column, Entity};
+    use sea_orm::{DbBackend, QueryTrait};
+
+    const SORTABLE: &[Column] = &[Column::Id, Column::Name, Column::CreateTime];
+
+    fn query(page: Option<u64>, size: Option<u64>, sort: Option<&str>) -> PageQuery {
+        PageQuery {
+            page,
+            size,
+            sort: sort.map(str::to_string),
+        }
+    }
+
+    /// 把解析结果转换为 (列名, 方向) 便于断言
+    fn orders(sort: &str) -> Result<Vec<(String, Order)>, ServiceError> {
+        query(None, None, Some(sort))
+            .sort_orders(SORTABLE)
+            .map(|orders| orders.into_iter().map(|(c, o)| (format!("{:?}", c), o)).collect())
+    }
+
+    /// 排序后生成的 SQL 中 ORDER BY 之后的部分
+    fn order_by(sort: Option<&str>) -> String {
+        let sql = apply_sort(Entity::find(), &query(None, None, sort), SORTABLE)
+            .unwrap()
+            .build(DbBackend::MySql)
+            .to_string();
+        sql.split_once("ORDER BY ").unwrap().1.to_string()
+    }
+
+    #[test]
+    fn page_and_size_defaults() {
+        let q = PageQuery::default();
+        assert_eq!((q.page(), q.size()), (1, DEFAULT_PAGE_SIZE));
+        let q = query(Some(3), Some(50), None);
+        assert_eq!((q.page(), q.size()), (3, 50));
+    }
+
+    #[test]
+    fn page_and_size_are_validated() {
+        assert!(query(Some(1), Some(100), None).validate().is_ok());
+        assert!(query(Some(0), None, None).validate().is_err());
+        assert!(query(None, Some(0), None).validate().is_err());
+        assert!(query(None, Some(101), None).validate().is_err());
+    }
+
+    #[test]
+    fn sort_parses_direction_prefixes() {
+        assert!(PageQuery::default().sort_orders(SORTABLE).unwrap().is_empty());
+        assert_eq!(
+            orders("-create_time, name,+id,").unwrap(),
+            vec![
+                ("CreateTime".to_string(), Order::Desc),
+                ("Name".to_string(), Order::Asc),
+                ("Id".to_string(), Order::Asc),
+            ]
+        );
+    }
+
+    #[test]
+    fn sort_rejects_unknown_and_unsortable_columns() {
+        for sort in ["name,-password", "app_access_key", "-prev_app_access_key"] {
+            match orders(sort) {
+                Err(ServiceError::InvalidArgument(msg)) => {
+                    assert!(msg.contains(sort.rsplit(['-', ',']).next().unwrap()))
+                }
+                other => panic!("unexpected: {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn primary_key_is_always_the_last_sort_key() {
+        assert_eq!(order_by(None), "`kms_app_access`.`id` DESC");
+        assert_eq!(
+            order_by(Some("-create_time")),
+            "`kms_app_access`.`create_time` DESC, `kms_app_access`.`id` ASC"
+        );
+        // 已显式按主键排序时不重复追加
+        assert_eq!(
+            order_by(Some("name,-id")),
+            "`kms_app_access`.`name` ASC, `kms_app_access`.`id` DESC"
+        );
+    }
+}
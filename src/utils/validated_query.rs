@@ -0,0 +1,42 @@
+// src/utils/validated_query.rs
+// 与 `ValidatedJson` 对应的 Query 版本：
+// 自动反序列化 URL 查询参数并 *立即* 运行 `validator`。
+
+use axum::{
+    extract::{FromRequestParts, Query},
+    http::request::Parts,
+};
+use serde::de::DeserializeOwned;
+use validator::Validate; // 导入 Validate trait
+
+use crate::errors::{AppError, ServiceError}; // 导入我们的错误类型
+
+/// 一个自定义提取器，它封装了 `axum::extract::Query`
+/// 并在反序列化后自动调用 `.validate()`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ValidatedQuery<T>(pub T);
+
+impl<T, S> FromRequestParts<S> for ValidatedQuery<T>
+where
+    T: DeserializeOwned + Validate,
+    S: Send + Sync,
+{
+    type Rejection = AppError; // 验证失败时，返回我们统一的 AppError
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        // 1. 使用 Axum 内置的 Query 提取器来反序列化
+        let Query(params) = Query::<T>::from_request_parts(parts, state)
+            .await
+            .map_err(|e| {
+                // 将查询参数格式错误转换为我们的业务错误 (10001)
+                AppError::Service(ServiceError::InvalidArgument(format!("查询参数格式错误: {}", e)))
+            })?;
+
+        // 2. 调用 `validator` 库的 .validate() 方法
+        params.validate().map_err(|e| {
+            AppError::Service(ServiceError::InvalidArgument(format!("请求参数不合法: {}", e)))
+        })?;
+
+        Ok(ValidatedQuery(params))
+    }
+}